@@ -0,0 +1,170 @@
+// src/engine/aabb.rs
+
+use crate::engine::{
+    interval::Interval,
+    ray::Ray,
+    vec3::{self, Point3, Vec3},
+};
+
+// smallest extent along any axis, so that flat objects still have a hittable box
+const MIN_EXTENT: f64 = 1e-4;
+
+/// an axis-aligned bounding box
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    /// constructs new `Aabb` from given intervals along each axis
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Aabb {
+        Aabb { x, y, z }
+    }
+
+    /// constructs new `Aabb` treating the two points as extrema of the box
+    pub fn from_points(a: Point3, b: Point3) -> Aabb {
+        Aabb {
+            x: Interval::new(a.x().min(b.x()), a.x().max(b.x())),
+            y: Interval::new(a.y().min(b.y()), a.y().max(b.y())),
+            z: Interval::new(a.z().min(b.z()), a.z().max(b.z())),
+        }
+    }
+
+    /// constructs an empty `Aabb` containing no points
+    pub fn empty() -> Aabb {
+        Aabb::new(Interval::empty(), Interval::empty(), Interval::empty())
+    }
+
+    /// constructs the smallest `Aabb` enclosing both given boxes
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Aabb {
+        Aabb {
+            x: Interval::enclosing(&a.x, &b.x),
+            y: Interval::enclosing(&a.y, &b.y),
+            z: Interval::enclosing(&a.z, &b.z),
+        }
+    }
+
+    /// constructs the `Aabb` of points inside both given boxes, empty if they do not overlap
+    pub fn overlap(a: &Aabb, b: &Aabb) -> Aabb {
+        let overlap = |i: Interval, j: Interval| Interval::new(i.min.max(j.min), i.max.min(j.max));
+        Aabb {
+            x: overlap(a.x, b.x),
+            y: overlap(a.y, b.y),
+            z: overlap(a.z, b.z),
+        }
+    }
+
+    /// constructs new `Aabb` tightly enclosing a disc of given center, normal axis and radius
+    pub fn from_disc(center: Point3, axis: Vec3, radius: f64) -> Aabb {
+        let a = vec3::unit_vector(axis);
+        // the disc's extent along each world axis shrinks as that axis aligns with its normal
+        let extent = |component: f64| radius * (1.0 - component * component).max(0.0).sqrt();
+        let half = Vec3::new(extent(a.x()), extent(a.y()), extent(a.z()));
+        Aabb::from_points(center - half, center + half)
+    }
+
+    /// returns whether the box contains no points
+    pub fn is_empty(&self) -> bool {
+        self.x.size() < 0.0 || self.y.size() < 0.0 || self.z.size() < 0.0
+    }
+
+    /// returns the box grown by delta on every side
+    pub fn expand(&self, delta: f64) -> Aabb {
+        Aabb::new(
+            self.x.expand(delta),
+            self.y.expand(delta),
+            self.z.expand(delta),
+        )
+    }
+
+    /// returns the box with every axis widened to a minimum extent, for flat objects
+    pub fn padded(&self) -> Aabb {
+        let pad = |i: Interval| {
+            if i.size() < MIN_EXTENT {
+                i.expand(MIN_EXTENT / 2.0)
+            } else {
+                i
+            }
+        };
+        Aabb::new(pad(self.x), pad(self.y), pad(self.z))
+    }
+
+    /// returns the box's eight corners
+    pub fn corners(&self) -> [Point3; 8] {
+        let (min, max) = (self.min(), self.max());
+        let mut corners = [Point3::zero(); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Point3::new(
+                if i & 1 == 0 { min.x() } else { max.x() },
+                if i & 2 == 0 { min.y() } else { max.y() },
+                if i & 4 == 0 { min.z() } else { max.z() },
+            );
+        }
+        corners
+    }
+
+    /// returns the interval along axis n (0 = x, 1 = y, 2 = z)
+    pub fn axis_interval(&self, n: usize) -> Interval {
+        match n {
+            1 => self.y,
+            2 => self.z,
+            _ => self.x,
+        }
+    }
+
+    /// returns the index of the box's longest axis (0 = x, 1 = y, 2 = z)
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() { 0 } else { 2 }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// returns the box's minimum corner
+    pub fn min(&self) -> Point3 {
+        Point3::new(self.x.min, self.y.min, self.z.min)
+    }
+
+    /// returns the box's maximum corner
+    pub fn max(&self) -> Point3 {
+        Point3::new(self.x.max, self.y.max, self.z.max)
+    }
+
+    /// returns the parametric span of the ray inside the box, clipped to ray_t
+    pub fn hit_span(&self, ray: &Ray, mut ray_t: Interval) -> Option<Interval> {
+        let ray_orig = ray.origin();
+        let ray_dir = ray.direction();
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let adinv = 1.0 / ray_dir[axis];
+
+            let t0 = (ax.min - ray_orig[axis]) * adinv;
+            let t1 = (ax.max - ray_orig[axis]) * adinv;
+
+            if t0 < t1 {
+                ray_t.min = ray_t.min.max(t0);
+                ray_t.max = ray_t.max.min(t1);
+            } else {
+                ray_t.min = ray_t.min.max(t1);
+                ray_t.max = ray_t.max.min(t0);
+            }
+
+            if ray_t.max <= ray_t.min {
+                return None;
+            }
+        }
+
+        Some(ray_t)
+    }
+
+    /// returns whether the ray passes through the box within ray_t
+    pub fn hit(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.hit_span(ray, ray_t).is_some()
+    }
+}
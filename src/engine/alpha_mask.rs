@@ -0,0 +1,65 @@
+// src/engine/alpha_mask.rs
+
+use crate::engine::{
+    color::Color, hittable::HitRecord, material::Material, ray::Ray, texture::Texture,
+};
+
+use std::rc::Rc;
+
+/// a material modifier that cuts a surface out where an opacity texture is transparent, so that
+/// rays pass through as if nothing was hit, e.g. for foliage cards and fences
+#[derive(Clone)]
+pub struct AlphaMask {
+    base: Rc<dyn Material>, // material of the surface where it is opaque
+    mask: Rc<dyn Texture>,  // opacity read from the texture's first channel
+}
+
+impl AlphaMask {
+    /// constructs new `AlphaMask` applying the given opacity texture to a base material
+    pub fn new(base: Rc<dyn Material>, mask: Rc<dyn Texture>) -> AlphaMask {
+        AlphaMask { base, mask }
+    }
+
+    /// returns the material of the surface where it is opaque
+    pub fn base(&self) -> Rc<dyn Material> {
+        self.base.clone()
+    }
+
+    /// returns the opacity texture
+    pub fn mask(&self) -> Rc<dyn Texture> {
+        self.mask.clone()
+    }
+}
+
+impl Material for AlphaMask {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        self.base.scatter(r_in, rec, attenuation, scattered)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.base.eval(r_in, rec, scattered)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.base.scattering_pdf(r_in, rec, scattered)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        let mask = self.mask.scalar(rec.u(), rec.v(), &rec.p()).clamp(0.0, 1.0);
+        mask * self.base.opacity(rec)
+    }
+}
@@ -0,0 +1,142 @@
+// src/engine/aperture.rs
+
+use crate::engine::{
+    color,
+    image::Image,
+    utils::{self, PI},
+};
+
+use std::io;
+use std::rc::Rc;
+
+/// the shape of a thin lens's opening, which out of focus highlights (bokeh) take on
+#[derive(Clone, Default)]
+pub enum Aperture {
+    /// a round opening
+    #[default]
+    Circle,
+    /// a regular polygon formed by a number of straight blades, turned by rotation degrees
+    Polygon { blades: u32, rotation: f64 },
+    /// an opening whose transmission follows an image's luminance
+    Image(Rc<ImageAperture>),
+}
+
+impl Aperture {
+    /// returns a random point on the aperture within the unit disk, for circles and polygons,
+    /// or the [-1,1] square, for images
+    pub fn sample(&self) -> (f64, f64) {
+        match self {
+            Aperture::Circle => sample_disk(),
+            Aperture::Polygon { blades, rotation } if *blades >= 3 => {
+                sample_polygon(*blades, utils::deg_to_rad(*rotation))
+            }
+            Aperture::Polygon { .. } => sample_disk(),
+            Aperture::Image(image) => image.sample(),
+        }
+    }
+}
+
+/// an aperture image sampled in proportion to its luminance, so its bright parts let more light
+/// through
+#[derive(Debug, Clone)]
+pub struct ImageAperture {
+    width: usize,
+    height: usize,
+    marginal: Vec<f64>,         // cumulative distribution over rows, top row first
+    conditional: Vec<Vec<f64>>, // cumulative distribution over the pixels of each row
+}
+
+impl ImageAperture {
+    /// constructs new `ImageAperture` from an image whose luminance gives the transmission,
+    /// stretched over the aperture's square
+    /// returns an error if the image has no pixels or is black everywhere
+    pub fn new(image: &Image) -> io::Result<ImageAperture> {
+        let (width, height) = (image.width(), image.height());
+
+        let mut conditional = Vec::with_capacity(height);
+        let mut row_sums = Vec::with_capacity(height);
+        for y in 0..height {
+            let weights =
+                (0..width).map(|x| color::luminance(&image.pixel(x as i64, y as i64)).max(0.0));
+            let (cdf, sum) = cumulative(weights);
+            conditional.push(cdf);
+            row_sums.push(sum);
+        }
+        let (marginal, total) = cumulative(row_sums.into_iter());
+        if width == 0 || height == 0 || total <= 0.0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "aperture image lets no light through",
+            ));
+        }
+
+        Ok(ImageAperture {
+            width,
+            height,
+            marginal,
+            conditional,
+        })
+    }
+
+    /// returns the width and height of the aperture image in pixels
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// returns a random point in the [-1,1] square, with the image's top row at +1
+    pub fn sample(&self) -> (f64, f64) {
+        let row = sample_cumulative(&self.marginal, utils::random_0_to_1());
+        let column = sample_cumulative(&self.conditional[row], utils::random_0_to_1());
+
+        // jitter within the chosen pixel
+        let (dx, dy) = utils::random_2d();
+        let x = (column as f64 + dx) / self.width as f64;
+        let y = (row as f64 + dy) / self.height as f64;
+        (2.0 * x - 1.0, 1.0 - 2.0 * y)
+    }
+}
+
+/// returns the normalized running sums of the weights, ending at one, and their total
+fn cumulative<I: Iterator<Item = f64>>(weights: I) -> (Vec<f64>, f64) {
+    let mut sum = 0.0;
+    let mut cdf: Vec<f64> = weights
+        .map(|w| {
+            sum += w;
+            sum
+        })
+        .collect();
+    if sum > 0.0 {
+        cdf.iter_mut().for_each(|c| *c /= sum);
+    }
+    (cdf, sum)
+}
+
+/// returns the index of the first entry of a cumulative distribution above u
+fn sample_cumulative(cdf: &[f64], u: f64) -> usize {
+    cdf.partition_point(|&c| c <= u)
+        .min(cdf.len().saturating_sub(1))
+}
+
+/// returns a uniformly random point in the unit disk
+fn sample_disk() -> (f64, f64) {
+    let (u1, u2) = utils::random_2d();
+    let r = u1.sqrt();
+    let theta = 2.0 * PI * u2;
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// returns a uniformly random point in the regular polygon with given number of corners on the
+/// unit circle, the first at angle rotation
+fn sample_polygon(corners: u32, rotation: f64) -> (f64, f64) {
+    // pick one of the equal triangles fanning out from the center, then a point inside it
+    let k = ((utils::random_0_to_1() * corners as f64) as u32).min(corners - 1);
+    let step = 2.0 * PI / corners as f64;
+    let (a0, a1) = (rotation + k as f64 * step, rotation + (k + 1) as f64 * step);
+
+    let (mut s, mut t) = utils::random_2d();
+    if s + t > 1.0 {
+        s = 1.0 - s;
+        t = 1.0 - t;
+    }
+    (s * a0.cos() + t * a1.cos(), s * a0.sin() + t * a1.sin())
+}
@@ -0,0 +1,172 @@
+// src/engine/bvh.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    ray::Ray,
+};
+
+use std::rc::Rc;
+
+/// a bounding volume hierarchy: a binary tree of boxes over a set of objects, so that rays only
+/// test the objects whose boxes they pass through
+pub struct BvhNode {
+    left: Rc<dyn Hittable>,
+    right: Option<Rc<dyn Hittable>>, // none in a leaf over a single object
+    bbox: Aabb,                      // box enclosing both children
+}
+
+impl BvhNode {
+    /// constructs new `BvhNode` over given objects, splitting them in half along the longest
+    /// axis of their boxes at every level
+    /// NOTE: an empty set of objects gives a node that is never hit
+    pub fn new(mut objects: Vec<Rc<dyn Hittable>>) -> BvhNode {
+        BvhNode::build(&mut objects)
+    }
+
+    /// constructs new `BvhNode` over the objects of a list
+    pub fn from_list(list: &HittableList) -> BvhNode {
+        BvhNode::new(list.objects().to_vec())
+    }
+
+    /// builds the subtree over a slice of objects, reordering it
+    fn build(objects: &mut [Rc<dyn Hittable>]) -> BvhNode {
+        let bbox = objects.iter().fold(Aabb::empty(), |bbox, object| {
+            Aabb::surrounding(&bbox, &object.bounding_box())
+        });
+
+        // a single object goes in the left child alone, so it is not intersected twice
+        let (left, right): (Rc<dyn Hittable>, Option<Rc<dyn Hittable>>) = match objects.len() {
+            0 => (Rc::new(HittableList::new()), None),
+            1 => (objects[0].clone(), None),
+            2 => (objects[0].clone(), Some(objects[1].clone())),
+            n => {
+                // sort by box centroid along the longest axis and split at the median
+                let axis = bbox.longest_axis();
+                let centroid = |object: &Rc<dyn Hittable>| {
+                    let interval = object.bounding_box().axis_interval(axis);
+                    interval.min + interval.max
+                };
+                objects.sort_by(|a, b| centroid(a).total_cmp(&centroid(b)));
+
+                let (low, high) = objects.split_at_mut(n / 2);
+                (
+                    Rc::new(BvhNode::build(low)),
+                    Some(Rc::new(BvhNode::build(high))),
+                )
+            }
+        };
+
+        BvhNode { left, right, bbox }
+    }
+
+    /// finds the closest hit in the node, applying alpha tests if alpha_test is set
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, alpha_test: bool) -> bool {
+        if !self.bbox.hit(ray, ray_t) {
+            return false;
+        }
+        let child_hit = |child: &Rc<dyn Hittable>, child_t: Interval, rec: &mut HitRecord| {
+            if alpha_test {
+                child.hit(ray, child_t, rec)
+            } else {
+                child.hit_ignoring_alpha(ray, child_t, rec)
+            }
+        };
+
+        let hit_left = child_hit(&self.left, ray_t, rec);
+        let right = match &self.right {
+            Some(right) => right,
+            None => return hit_left,
+        };
+        let t_max = if hit_left { rec.t() } else { ray_t.max };
+
+        // a miss may still write to its record, so the right child must not clobber a left hit
+        let mut temp_rec = HitRecord::new();
+        let hit_right = child_hit(right, Interval::new(ray_t.min, t_max), &mut temp_rec);
+        if hit_right {
+            *rec = temp_rec;
+        }
+
+        hit_left || hit_right
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, true)
+    }
+
+    fn hit_ignoring_alpha(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, false)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+        if !self.bbox.hit(ray, ray_t) {
+            return 1.0;
+        }
+
+        let left = self.left.transmittance(ray, ray_t);
+        match &self.right {
+            Some(right) if left > 0.0 => left * right.transmittance(ray, ray_t),
+            _ => left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        color::Color,
+        material::Lambertian,
+        sphere::Sphere,
+        utils::INFINITY,
+        vec3::{Point3, Vec3},
+    };
+
+    /// a medium letting half the light through wherever a ray crosses its box
+    struct HalfMedium {
+        bbox: Aabb,
+    }
+
+    impl Hittable for HalfMedium {
+        fn hit(&self, _ray: &Ray, _ray_t: Interval, _rec: &mut HitRecord) -> bool {
+            false
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            self.bbox
+        }
+
+        fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+            if self.bbox.hit(ray, ray_t) { 0.5 } else { 1.0 }
+        }
+    }
+
+    #[test]
+    fn single_object_leaf_is_crossed_once() {
+        let mat = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let medium: Rc<dyn Hittable> = Rc::new(HalfMedium {
+            bbox: Aabb::from_points(Point3::new(-3.0, -1.0, -1.0), Point3::new(-1.0, 1.0, 1.0)),
+        });
+        // the medium sorts first along x, so it ends up alone in a leaf
+        let bvh = BvhNode::new(vec![
+            Rc::new(Sphere::new(Point3::new(5.0, 0.0, 0.0), 1.0, mat.clone())),
+            medium.clone(),
+            Rc::new(Sphere::new(Point3::new(2.0, 0.0, 0.0), 1.0, mat)),
+        ]);
+
+        let ray = Ray::new(Point3::new(-2.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        let ray_t = Interval::new(0.001, INFINITY);
+        assert_eq!(
+            bvh.transmittance(&ray, ray_t),
+            medium.transmittance(&ray, ray_t)
+        );
+    }
+}
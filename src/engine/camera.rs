@@ -1,29 +1,249 @@
 // src/engine/camera.rs
 
 use crate::engine::{
+    aperture::Aperture,
     color::{self, Color},
+    film::Film,
+    filter::Filter,
     hittable::{HitRecord, Hittable},
     interval::Interval,
+    lens_system::LensSystem,
+    light::{Light, LightList},
     ray::Ray,
-    utils::{self, INFINITY},
+    sampler::{self, SamplerType},
+    sky::PreethamSky,
+    utils::{self, INFINITY, PI},
     vec3::{self, Point3, Vec3},
 };
 use std::io::{self, Write};
 
-#[derive(Default, Clone, Copy)]
+/// weighting used to combine light sampling and bsdf sampling (multiple importance sampling)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MisHeuristic {
+    Balance,
+    #[default]
+    Power,
+}
+
+/// what rays escaping the scene see
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Background {
+    /// white to light blue vertical gradient
+    #[default]
+    Gradient,
+    /// uniform color in every direction
+    Solid(Color),
+    /// analytic daylight sky; its sun also lights the scene through shadow rays
+    Sky(PreethamSky),
+}
+
+/// how the camera maps pixels to ray directions
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// pinhole rays through a planar viewport spanning vfov vertically
+    #[default]
+    Perspective,
+    /// parallel rays through a viewport of given height in world units, centered on lookfrom
+    Orthographic { height: f64 },
+    /// equidistant fisheye, angle from the view direction growing linearly with distance from
+    /// the image center; its image circle fits the image height and spans fov degrees
+    Fisheye { fov: f64 },
+    /// full 360 by 180 degree panorama, longitude across the width and latitude down the height
+    /// NOTE: use an aspect ratio of 2 for square pixels
+    Equirectangular,
+}
+
+/// where the two eye images of a stereo render go in the output image
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+    /// left eye on the left half, right eye on the right half
+    #[default]
+    SideBySide,
+    /// left eye on the top half, right eye on the bottom half
+    TopBottom,
+}
+
+/// eye separation and convergence of a stereo camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    ipd: f64,         // interpupillary distance between the eyes in world units
+    convergence: f64, // distance at which the two eyes' images coincide
+    layout: StereoLayout,
+}
+
+impl Stereo {
+    /// constructs new `Stereo` settings from eye separation, convergence distance and layout
+    pub fn new(ipd: f64, convergence: f64, layout: StereoLayout) -> Stereo {
+        Stereo {
+            ipd: ipd.max(0.0),
+            convergence: convergence.max(1e-6),
+            layout,
+        }
+    }
+
+    /// returns the interpupillary distance between the eyes
+    pub fn ipd(&self) -> f64 {
+        self.ipd
+    }
+
+    /// returns the distance at which the eyes' images coincide
+    pub fn convergence(&self) -> f64 {
+        self.convergence
+    }
+
+    /// returns the layout of the eye images in the output
+    pub fn layout(&self) -> StereoLayout {
+        self.layout
+    }
+}
+
+/// per-pixel sample counts of adaptive sampling, which keeps sampling a pixel until the standard
+/// error of its mean luminance drops below a fraction of that mean
+/// NOTE: adaptive renders reconstruct with the single pixel box filter in place of the camera's
+/// filter, since a wider filter would weigh the neighbors of a pixel by how many samples each
+/// happened to take and bias the image towards the noisier ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    min_samples: i32,        // samples every pixel takes before its error is trusted
+    max_samples: i32,        // samples after which a pixel stops regardless of its error
+    max_relative_error: f64, // standard error over mean at which a pixel has converged
+}
+
+impl AdaptiveSampling {
+    /// constructs new `AdaptiveSampling` settings from sample count bounds and the relative error
+    /// threshold
+    pub fn new(min_samples: i32, max_samples: i32, max_relative_error: f64) -> AdaptiveSampling {
+        let min_samples = min_samples.max(2);
+        AdaptiveSampling {
+            min_samples,
+            max_samples: max_samples.max(min_samples),
+            max_relative_error: max_relative_error.max(0.0),
+        }
+    }
+
+    /// returns the samples every pixel takes
+    pub fn min_samples(&self) -> i32 {
+        self.min_samples
+    }
+
+    /// returns the samples no pixel goes beyond
+    pub fn max_samples(&self) -> i32 {
+        self.max_samples
+    }
+
+    /// returns the relative error at which a pixel stops sampling
+    pub fn max_relative_error(&self) -> f64 {
+        self.max_relative_error
+    }
+}
+
+/// running mean and variance of a pixel's sample luminances (welford's algorithm)
+#[derive(Debug, Default, Clone, Copy)]
+struct PixelStatistics {
+    count: i32,
+    mean: f64,
+    m2: f64, // sum of squared differences from the mean
+}
+
+impl PixelStatistics {
+    /// adds sample value x
+    fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// returns the standard error of the mean relative to the mean, zero without any variance
+    fn relative_error(&self) -> f64 {
+        if self.count < 2 || self.m2 <= 0.0 {
+            return 0.0;
+        }
+        if self.mean <= 0.0 {
+            return INFINITY;
+        }
+        let variance = self.m2 / (self.count - 1) as f64;
+        (variance / self.count as f64).sqrt() / self.mean
+    }
+}
+
+#[derive(Clone)]
 pub struct Camera {
     // private camera parameters
-    image_height: i32,        // rendered image height in pixel count
-    center: Point3,           // camera center
-    pixel00_loc: Point3,      // location of pixel 0,0
-    pixel_delta_u: Vec3,      // offset to pixel to the right
-    pixel_delta_v: Vec3,      // offset to pixel below
-    pixel_samples_scale: f64, // color scale factor for a sum of pixels
+    image_height: i32,       // rendered image height in pixel count
+    center: Point3,          // camera center
+    pixel00_loc: Point3,     // location of pixel 0,0
+    pixel_delta_u: Vec3,     // offset to pixel to the right
+    pixel_delta_v: Vec3,     // offset to pixel below
+    u: Vec3,                 // camera frame basis vector pointing right
+    v: Vec3,                 // camera frame basis vector pointing up
+    w: Vec3,                 // camera frame basis vector pointing opposite the view direction
+    defocus_radius: f64,     // radius of the lens aperture
+    sample_counts: Vec<i32>, // samples each output pixel took in the last render
 
     // public camera parameters
-    pub aspect_ratio: f64,      // ratio of image width over height
-    pub image_width: i32,       // rendered image width in pixel count
-    pub samples_per_pixel: i32, // count of random samples for each pixel
+    pub aspect_ratio: f64,                  // ratio of image width over height
+    pub image_width: i32,                   // rendered image width in pixel count
+    pub samples_per_pixel: i32,             // count of random samples for each pixel
+    pub adaptive: Option<AdaptiveSampling>, // varies the samples per pixel with their noise if set
+    pub sampler: SamplerType,               // distribution of the random numbers behind each sample
+    pub filter: Filter, // weighting of samples around each pixel, box if adaptive
+    pub max_depth: i32, // maximum number of ray bounces into scene
+    pub vfov: f64,      // vertical view angle (field of view) in degrees
+    pub lookfrom: Point3, // point camera is looking from
+    pub lookat: Point3, // point camera is looking at
+    pub vup: Vec3,      // camera-relative "up" direction
+    pub projection: Projection, // mapping from pixels to ray directions
+    pub stereo: Option<Stereo>, // renders both eyes into one image if set
+    pub defocus_angle: f64, // variation angle of rays through each pixel
+    pub focus_dist: f64, // distance from lookfrom to the plane of perfect focus
+    pub aperture: Aperture, // shape of the lens opening when defocused
+    pub anamorphic_squeeze: f64, // ratio of the aperture's height to its width
+    pub lens_system: Option<LensSystem>, // traces rays through real lens elements if set
+    pub shutter_open: f64, // time at which the shutter opens
+    pub shutter_close: f64, // time at which the shutter closes
+    pub mis_heuristic: MisHeuristic, // weighting between light and bsdf samples
+    pub background: Background, // radiance of rays that escape the scene
+}
+
+impl Default for Camera {
+    /// a perspective camera at the origin looking down -z with a 90 degree vertical field of view
+    fn default() -> Self {
+        Camera {
+            image_height: 0,
+            center: Point3::zero(),
+            pixel00_loc: Point3::zero(),
+            pixel_delta_u: Vec3::zero(),
+            pixel_delta_v: Vec3::zero(),
+            u: Vec3::zero(),
+            v: Vec3::zero(),
+            w: Vec3::zero(),
+            defocus_radius: 0.0,
+            sample_counts: Vec::new(),
+            aspect_ratio: 0.0,
+            image_width: 0,
+            samples_per_pixel: 0,
+            adaptive: None,
+            sampler: SamplerType::default(),
+            filter: Filter::default(),
+            max_depth: 0,
+            vfov: 90.0,
+            lookfrom: Point3::zero(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            projection: Projection::default(),
+            stereo: None,
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            aperture: Aperture::default(),
+            anamorphic_squeeze: 1.0,
+            lens_system: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            mis_heuristic: MisHeuristic::default(),
+            background: Background::default(),
+        }
+    }
 }
 
 impl Camera {
@@ -32,81 +252,645 @@ impl Camera {
         let ar_height = (self.image_width as f64 / self.aspect_ratio) as i32;
         self.image_height = if ar_height < 1 { 1 } else { ar_height };
 
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
-
-        self.center = Point3::zero();
+        self.center = self.lookfrom;
 
         // determine viewport dimensions
-        let focal_length = 1.0;
-        let viewport_height = 2.0;
+        let focal_length = (self.lookfrom - self.lookat).length();
+        let theta = utils::deg_to_rad(self.vfov);
+        let h = (theta / 2.0).tan();
+        let viewport_height = match self.projection {
+            Projection::Orthographic { height } => height,
+            _ => 2.0 * h * focal_length,
+        };
         let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
 
+        // calculate the u,v,w unit basis vectors for the camera coordinate frame
+        self.w = vec3::unit_vector(self.lookfrom - self.lookat);
+        self.u = vec3::unit_vector(vec3::cross(&self.vup, &self.w));
+        self.v = vec3::cross(&self.w, &self.u);
+
         // calculate vectors across horizontal and down vertical viewport edges
-        let viewport_u = Vec3::new(viewport_width, 0.0, 0.0);
-        let viewport_v = Vec3::new(0.0, -viewport_height, 0.0);
+        let viewport_u = viewport_width * self.u;
+        let viewport_v = viewport_height * -self.v;
 
         // calculate horizontal and vertical delta vectors from pixel to pixel
         self.pixel_delta_u = viewport_u / self.image_width as f64;
         self.pixel_delta_v = viewport_v / self.image_height as f64;
 
-        // calculate the location of the upper left pixel
-        let viewport_upper_left =
-            self.center - Vec3::new(0.0, 0.0, focal_length) - viewport_u / 2.0 - viewport_v / 2.0;
+        // calculate the location of the upper left pixel; orthographic rays start on the
+        // viewport itself
+        let viewport_center = match self.projection {
+            Projection::Orthographic { .. } => self.center,
+            _ => self.center - focal_length * self.w,
+        };
+        let viewport_upper_left = viewport_center - viewport_u / 2.0 - viewport_v / 2.0;
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+
+        // calculate the camera defocus disk radius
+        self.defocus_radius = self.focus_dist * utils::deg_to_rad(self.defocus_angle / 2.0).tan();
     }
 
-    fn ray_color(&self, ray: &Ray, world: &dyn Hittable) -> Color {
+    /// returns the radiance arriving along the ray, combining explicit light sampling and bsdf
+    /// sampling at each non-specular bounce with multiple importance sampling
+    /// NOTE: bsdf_pdf is the pdf with which the previous bounce sampled the ray, or None for
+    /// camera rays and specular bounces, whose emission is always counted in full
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: i32,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        punctual_lights: &LightList,
+        bsdf_pdf: Option<f64>,
+    ) -> Color {
+        // if we've exceeded the ray bounce limit, no more light is gathered
+        if depth <= 0 {
+            return Color::zero();
+        }
+
         let mut rec = HitRecord::new();
-        if world.hit(&ray, Interval::new(0.0, INFINITY), &mut rec) {
-            return 0.5 * (rec.normal() + Color::new(1.0, 1.0, 1.0));
+        // ignore hits very close to the origin to avoid shadow acne
+        if !world.hit(ray, Interval::new(0.001, INFINITY), &mut rec) {
+            return self.background_color(ray, bsdf_pdf.is_none());
+        }
+
+        let mat = match rec.mat() {
+            Some(mat) => mat,
+            None => return Color::zero(),
+        };
+
+        // weight emission found by bsdf sampling against the chance light sampling found it too
+        let mut color_from_emission = mat.emitted(ray, &rec);
+        if let Some(bsdf_pdf) = bsdf_pdf
+            && color_from_emission != Color::zero()
+        {
+            let light_pdf = lights.pdf_value(&ray.origin(), &ray.direction(), ray.time());
+            color_from_emission *= self.mis_weight(bsdf_pdf, light_pdf);
+        }
+
+        let mut scattered = Ray::default();
+        let mut attenuation = Color::zero();
+        if !mat.scatter(ray, &rec, &mut attenuation, &mut scattered) {
+            return color_from_emission;
+        }
+
+        if mat.is_specular() {
+            return color_from_emission
+                + attenuation
+                    * self.ray_color(&scattered, depth - 1, world, lights, punctual_lights, None);
+        }
+
+        let color_from_lights = self.sample_lights(ray, &rec, world, lights)
+            + self.sample_punctual_lights(ray, &rec, world, punctual_lights);
+        let scattering_pdf = mat.scattering_pdf(ray, &rec, &scattered);
+        let color_from_scatter = attenuation
+            * self.ray_color(
+                &scattered,
+                depth - 1,
+                world,
+                lights,
+                punctual_lights,
+                Some(scattering_pdf),
+            );
+
+        color_from_emission + color_from_lights + color_from_scatter
+    }
+
+    /// returns the direct light reflected along the ray from a shadow ray towards a sampled light,
+    /// weighted against the chance bsdf sampling found the same light
+    fn sample_lights(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+    ) -> Color {
+        let to_light = lights.random(&rec.p(), ray.time());
+        let light_pdf = lights.pdf_value(&rec.p(), &to_light, ray.time());
+        if light_pdf <= 0.0 {
+            return Color::zero();
+        }
+
+        let shadow_ray = Ray::new_with_time(rec.p(), to_light, ray.time());
+        let mat = match rec.mat() {
+            Some(mat) => mat,
+            None => return Color::zero(),
+        };
+        let f = mat.eval(ray, rec, &shadow_ray);
+        if f == Color::zero() {
+            return Color::zero();
+        }
+
+        // find how far away the sampled light point is, then how much of its light reaches us
+        // through the surfaces and media in between
+        let mut light_rec = HitRecord::new();
+        if !lights.hit_ignoring_alpha(&shadow_ray, Interval::new(0.001, INFINITY), &mut light_rec) {
+            return Color::zero();
+        }
+        let light_t = light_rec.t();
+        let transmittance =
+            world.transmittance(&shadow_ray, Interval::new(0.001, light_t * (1.0 - 1e-6)));
+        if transmittance <= 0.0 {
+            return Color::zero();
+        }
+
+        // the emission comes from the world's copy of the light, whose material is the real one
+        // and whose alpha test decides whether a partially transparent light is seen
+        let mut light_rec = HitRecord::new();
+        let light_span = Interval::new(light_t * (1.0 - 1e-6), light_t * (1.0 + 1e-6));
+        if !world.hit(&shadow_ray, light_span, &mut light_rec) {
+            return Color::zero();
+        }
+        let emitted = match light_rec.mat() {
+            Some(light_mat) => light_mat.emitted(&shadow_ray, &light_rec),
+            None => return Color::zero(),
+        };
+
+        let scattering_pdf = mat.scattering_pdf(ray, rec, &shadow_ray);
+        let weight = self.mis_weight(light_pdf, scattering_pdf);
+
+        weight * transmittance * f * emitted / light_pdf
+    }
+
+    /// returns the direct light reflected along the ray from every punctual light
+    /// NOTE: punctual lights have no geometry for bsdf sampling to find, so no mis weight applies
+    fn sample_punctual_lights(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        world: &dyn Hittable,
+        punctual_lights: &LightList,
+    ) -> Color {
+        let mat = match rec.mat() {
+            Some(mat) => mat,
+            None => return Color::zero(),
+        };
+
+        // a sky background contributes its sun alongside the scene's lights
+        let sun = match self.background {
+            Background::Sky(sky) => Some(sky.sun_light()),
+            _ => None,
+        };
+        let sun_light = sun.as_ref().map(|sun| sun as &dyn Light);
+
+        let mut color = Color::zero();
+        for light in punctual_lights.iter().map(|l| l.as_ref()).chain(sun_light) {
+            let sample = match light.sample_li(&rec.p()) {
+                Some(sample) if sample.pdf > 0.0 => sample,
+                _ => continue,
+            };
+
+            let shadow_ray = Ray::new_with_time(rec.p(), sample.direction, ray.time());
+            let f = mat.eval(ray, rec, &shadow_ray);
+            if f == Color::zero() {
+                continue;
+            }
+
+            // the light is dimmed by whatever lies between the point and the light
+            let shadow_t = Interval::new(0.001, sample.distance * (1.0 - 1e-6));
+            let transmittance = world.transmittance(&shadow_ray, shadow_t);
+            if transmittance <= 0.0 {
+                continue;
+            }
+
+            color += transmittance * f * sample.radiance / sample.pdf;
+        }
+        color
+    }
+
+    /// returns the background radiance seen by a ray escaping the scene
+    /// NOTE: the sun disk is only visible to camera rays and specular bounces, other bounces
+    /// receive sunlight through shadow rays in `sample_punctual_lights`
+    fn background_color(&self, ray: &Ray, include_sun: bool) -> Color {
+        match self.background {
+            Background::Gradient => {
+                let unit_direction = vec3::unit_vector(ray.direction());
+                let a = 0.5 * (unit_direction.y() + 1.0);
+                (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+            }
+            Background::Solid(color) => color,
+            Background::Sky(sky) if include_sun => sky.radiance_with_sun(ray.direction()),
+            Background::Sky(sky) => sky.radiance(ray.direction()),
         }
+    }
 
-        let unit_direction = vec3::unit_vector(ray.direction());
-        let a = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+    /// returns the weight of a sample drawn with pdf_f when the other strategy has pdf_g
+    fn mis_weight(&self, pdf_f: f64, pdf_g: f64) -> f64 {
+        match self.mis_heuristic {
+            MisHeuristic::Balance => utils::balance_heuristic(pdf_f, pdf_g),
+            MisHeuristic::Power => utils::power_heuristic(pdf_f, pdf_g),
+        }
     }
 
-    /// construct a camera ray originating from the origin and directed at randomly sampled piont around the pixel location i,j
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let offset = self.sample_square();
+    /// construct a camera ray through the point at offset from the center of pixel location i,j
+    /// at a random time within the shutter interval, seen from an eye offset along the camera's
+    /// right by eye, zero for a mono camera, and the weight of the radiance it carries
+    /// returns None for points outside a fisheye's image circle or blocked by a lens system,
+    /// which stay black
+    fn get_ray(&self, i: i32, j: i32, offset: Vec3, eye: f64) -> Option<(Ray, f64)> {
         let pixel_sample = self.pixel00_loc
             + ((i as f64 + offset.x()) * self.pixel_delta_u)
             + ((j as f64 + offset.y()) * self.pixel_delta_v);
+        // the sampled point in pixel units from the upper left corner of the image
+        let x = i as f64 + 0.5 + offset.x();
+        let y = j as f64 + 0.5 + offset.y();
+
+        let mut weight = 1.0;
+        let (ray_origin, ray_direction) = match (&self.lens_system, self.projection) {
+            (Some(lens), _) => {
+                let (lens_ray, lens_weight) = self.lens_ray(lens, x, y)?;
+                weight = lens_weight;
+                (lens_ray.origin(), lens_ray.direction())
+            }
+            (None, Projection::Perspective) => (self.center, pixel_sample - self.center),
+            (None, Projection::Orthographic { .. }) => (pixel_sample, -self.w),
+            (None, Projection::Fisheye { fov }) => {
+                (self.center, self.fisheye_direction(x, y, fov)?)
+            }
+            (None, Projection::Equirectangular) => {
+                (self.center, self.equirectangular_direction(x, y))
+            }
+        };
+        let (ray_origin, ray_direction) = match self.stereo {
+            Some(stereo) if eye != 0.0 => self.eye_ray(ray_origin, ray_direction, eye, stereo),
+            _ => (ray_origin, ray_direction),
+        };
+        // a lens system brings its own aperture
+        let (ray_origin, ray_direction) = if self.defocus_angle > 0.0 && self.lens_system.is_none()
+        {
+            self.defocus_ray(ray_origin, ray_direction)
+        } else {
+            (ray_origin, ray_direction)
+        };
+        let ray_time = self.sample_time();
+
+        Some((
+            Ray::new_with_time(ray_origin, ray_direction, ray_time),
+            weight,
+        ))
+    }
+
+    /// returns the world space ray a lens system sends out for image point x,y, with the film
+    /// at the camera center, and its weight
+    /// NOTE: the lens sets the field of view through its focal length and film size, so vfov and
+    /// the projection are ignored
+    fn lens_ray(&self, lens: &LensSystem, x: f64, y: f64) -> Option<(Ray, f64)> {
+        let aspect = self.image_width as f64 / self.image_height as f64;
+        let (film_width, film_height) = lens.film_size(aspect);
+        let film_x = (x / self.image_width as f64 - 0.5) * film_width;
+        let film_y = (0.5 - y / self.image_height as f64) * film_height;
+
+        let (ray, weight) = lens.sample_ray(film_x, film_y)?;
+        let to_world = |v: Vec3| v.x() * self.u + v.y() * self.v + v.z() * self.w;
+        Some((
+            Ray::new(
+                self.center + to_world(ray.origin()),
+                to_world(ray.direction()),
+            ),
+            weight,
+        ))
+    }
+
+    /// returns the origin and direction of the ray an eye offset by eye sees in place of the mono
+    /// ray, aimed to meet the mono ray at the convergence distance
+    /// NOTE: planar projections converge on a plane of constant depth with parallel eye axes;
+    /// panoramas converge on a sphere and use omni-directional stereo, turning the eyes to face
+    /// each ray so the offset stays perpendicular to it
+    fn eye_ray(&self, origin: Point3, direction: Vec3, eye: f64, stereo: Stereo) -> (Point3, Vec3) {
+        let (offset, target) = match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                let depth = vec3::dot(&direction, &-self.w);
+                let target = origin + (stereo.convergence() / depth) * direction;
+                (eye * self.u, target)
+            }
+            Projection::Fisheye { .. } => {
+                let target = origin + stereo.convergence() * vec3::unit_vector(direction);
+                (eye * self.u, target)
+            }
+            Projection::Equirectangular => {
+                let target = origin + stereo.convergence() * vec3::unit_vector(direction);
+                // the offset shrinks with the cosine of the latitude, merging the eyes at the poles
+                let right = vec3::cross(&vec3::unit_vector(direction), &self.v);
+                (eye * right, target)
+            }
+        };
+        let eye_origin = origin + offset;
+        (eye_origin, target - eye_origin)
+    }
+
+    /// returns the origin and direction of a ray leaving a random point of the thin lens
+    /// aperture towards where the pinhole ray meets the plane of focus, or for panoramas the
+    /// sphere of focus
+    fn defocus_ray(&self, origin: Point3, direction: Vec3) -> (Point3, Vec3) {
+        let (ax, ay) = self.aperture.sample();
+        let ax = ax / self.anamorphic_squeeze.max(1e-3);
+
+        let (target, right, up) = match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                let depth = vec3::dot(&direction, &-self.w);
+                (
+                    origin + (self.focus_dist / depth) * direction,
+                    self.u,
+                    self.v,
+                )
+            }
+            Projection::Fisheye { .. } | Projection::Equirectangular => {
+                // the lens faces each ray, keeping its axes level with the horizon
+                let d = vec3::unit_vector(direction);
+                let right = vec3::cross(&d, &self.v);
+                let right = if right.near_zero() {
+                    self.u
+                } else {
+                    vec3::unit_vector(right)
+                };
+                (origin + self.focus_dist * d, right, vec3::cross(&right, &d))
+            }
+        };
+        let lens_origin = origin + self.defocus_radius * (ax * right + ay * up);
+        (lens_origin, target - lens_origin)
+    }
+
+    /// returns the eye, 0 for the left and 1 for the right, and the pixel within that eye's image
+    /// of output pixel col,row
+    fn eye_pixel(&self, col: i32, row: i32) -> (usize, i32, i32) {
+        let layout = match self.stereo {
+            Some(stereo) => stereo.layout(),
+            None => return (0, col, row),
+        };
+        match layout {
+            StereoLayout::SideBySide if col < self.image_width => (0, col, row),
+            StereoLayout::SideBySide => (1, col - self.image_width, row),
+            StereoLayout::TopBottom if row < self.image_height => (0, col, row),
+            StereoLayout::TopBottom => (1, col, row - self.image_height),
+        }
+    }
+
+    /// returns the offset along the camera's right axis of the given eye's origin
+    fn eye_offset(&self, eye: usize) -> f64 {
+        match self.stereo {
+            Some(stereo) if eye == 0 => -0.5 * stereo.ipd(),
+            Some(stereo) => 0.5 * stereo.ipd(),
+            None => 0.0,
+        }
+    }
+
+    /// returns the filter the film reconstructs pixels with, the single pixel box for adaptive
+    /// sampling so pixels with more samples don't outweigh their neighbors
+    fn film_filter(&self) -> Filter {
+        match self.adaptive {
+            Some(_) => Filter::default(),
+            None => self.filter,
+        }
+    }
+
+    /// returns the width and height of the output image, holding both eyes for stereo
+    fn output_size(&self) -> (i32, i32) {
+        match self.stereo.map(|stereo| stereo.layout()) {
+            None => (self.image_width, self.image_height),
+            Some(StereoLayout::SideBySide) => (2 * self.image_width, self.image_height),
+            Some(StereoLayout::TopBottom) => (self.image_width, 2 * self.image_height),
+        }
+    }
+
+    /// returns the direction seen at image point x,y through an equidistant fisheye lens
+    /// spanning fov degrees across the image height, or None outside its image circle
+    fn fisheye_direction(&self, x: f64, y: f64, fov: f64) -> Option<Vec3> {
+        let radius = 0.5 * self.image_height as f64;
+        let dx = (x - 0.5 * self.image_width as f64) / radius;
+        let dy = (0.5 * self.image_height as f64 - y) / radius;
+        let r = (dx * dx + dy * dy).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        // the angle from the view direction grows linearly out to half the field of view
+        let theta = r * 0.5 * utils::deg_to_rad(fov);
+        let phi = dy.atan2(dx);
+        Some(theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w)
+    }
 
-        let ray_origin = self.center;
-        let ray_direction = pixel_sample - ray_origin;
+    /// returns the direction seen at image point x,y of an equirectangular panorama, the image
+    /// center looking along the view direction
+    fn equirectangular_direction(&self, x: f64, y: f64) -> Vec3 {
+        let longitude = 2.0 * PI * (x / self.image_width as f64 - 0.5);
+        let latitude = PI * (0.5 - y / self.image_height as f64);
+        latitude.cos() * (longitude.sin() * self.u - longitude.cos() * self.w)
+            + latitude.sin() * self.v
+    }
 
-        Ray::new(ray_origin, ray_direction)
+    /// returns a random time in the [shutter_open, shutter_close) interval
+    fn sample_time(&self) -> f64 {
+        self.shutter_open + utils::random_0_to_1() * (self.shutter_close - self.shutter_open)
     }
 
     /// returns the vector to a random point in the [-0.5,-0.5] to [0.5,0.5] unit square
     fn sample_square(&self) -> Vec3 {
-        Vec3::new(
-            utils::random_0_to_1() - 0.5,
-            utils::random_0_to_1() - 0.5,
-            0.0,
-        )
+        let (u1, u2) = utils::random_2d();
+        Vec3::new(u1 - 0.5, u2 - 0.5, 0.0)
     }
 
     // public camera functions
-    pub fn render(&mut self, world: &dyn Hittable) {
+    /// renders the world to stdout as a ppm image, sampling the given lights directly at each bounce
+    /// NOTE: lights may be an empty `HittableList` to rely on scattered rays alone; a stereo
+    /// camera writes both eyes, each image_width by image_height, into one image
+    pub fn render(
+        &mut self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        punctual_lights: &LightList,
+    ) {
         self.initialize();
 
-        // header for ppm file
-        println!("P3\n{} {}\n255", self.image_width, self.image_height);
+        // without adaptive sampling every pixel takes exactly samples_per_pixel
+        let (min_samples, max_samples, max_relative_error) = match self.adaptive {
+            Some(adaptive) => (
+                adaptive.min_samples(),
+                adaptive.max_samples(),
+                adaptive.max_relative_error(),
+            ),
+            None => (self.samples_per_pixel, self.samples_per_pixel, INFINITY),
+        };
+
+        // draw every random number of a pixel sample, from the camera ray to the last bounce, from
+        // the camera's sampler
+        sampler::set_thread_sampler(Some(self.sampler.build(max_samples.max(1) as u32, 0)));
 
-        for j in 0..self.image_height {
-            eprint!("\rScanlines remaining: {}", self.image_height - j);
+        // splat each sample onto the film of its eye, so filters don't blur one eye into the other
+        let eyes = if self.stereo.is_some() { 2 } else { 1 };
+        let mut films = vec![
+            Film::new(
+                self.image_width as usize,
+                self.image_height as usize,
+                self.film_filter()
+            );
+            eyes
+        ];
+
+        let (output_width, output_height) = self.output_size();
+        self.sample_counts = Vec::with_capacity((output_width * output_height) as usize);
+        for row in 0..output_height {
+            eprint!("\rScanlines remaining: {}", output_height - row);
             io::stderr().flush().unwrap();
-            for i in 0..self.image_width {
-                let mut pixel_color: Color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&r, world);
+            for col in 0..output_width {
+                let (eye, i, j) = self.eye_pixel(col, row);
+                let film = &mut films[eye];
+                let mut statistics = PixelStatistics::default();
+                for sample in 0..max_samples {
+                    sampler::start_pixel_sample(col, row, sample as u32);
+                    let offset = self.sample_square();
+                    let mut sample_color = Color::new(0.0, 0.0, 0.0);
+                    if let Some((r, weight)) = self.get_ray(i, j, offset, self.eye_offset(eye)) {
+                        sample_color = weight
+                            * self.ray_color(
+                                &r,
+                                self.max_depth,
+                                world,
+                                lights,
+                                punctual_lights,
+                                None,
+                            );
+                    }
+                    film.add_sample(
+                        i as f64 + 0.5 + offset.x(),
+                        j as f64 + 0.5 + offset.y(),
+                        sample_color,
+                    );
+
+                    statistics.add(color::luminance(&sample_color));
+                    if statistics.count >= min_samples
+                        && statistics.relative_error() <= max_relative_error
+                    {
+                        break;
+                    }
                 }
-                _ = color::write_color(&mut io::stdout(), self.pixel_samples_scale * pixel_color);
+                self.sample_counts.push(statistics.count);
             }
         }
+
+        // header for ppm file
+        println!("P3\n{} {}\n255", output_width, output_height);
+        for row in 0..output_height {
+            for col in 0..output_width {
+                let (eye, i, j) = self.eye_pixel(col, row);
+                let film = &films[eye];
+                _ = color::write_color(&mut io::stdout(), film.pixel(i as usize, j as usize));
+            }
+        }
+        sampler::set_thread_sampler(None);
         eprint!("\rDone.                      \n");
     }
+
+    /// writes the number of samples each pixel of the last render took as a grayscale ppm image,
+    /// white at the most samples any pixel could take
+    pub fn write_sample_map<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let (output_width, output_height) = self.output_size();
+        if self.sample_counts.len() != (output_width * output_height) as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "camera has not rendered an image yet",
+            ));
+        }
+
+        let max_samples = match self.adaptive {
+            Some(adaptive) => adaptive.max_samples(),
+            None => self.samples_per_pixel,
+        };
+        writeln!(writer, "P3\n{} {}\n255", output_width, output_height)?;
+        for &count in &self.sample_counts {
+            let level = count as f64 / max_samples.max(1) as f64;
+            color::write_color(writer, Color::new(level, level, level))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// returns an initialized 4 by 2 camera with the given stereo settings
+    fn stereo_camera(stereo: Stereo) -> Camera {
+        let mut camera = Camera {
+            aspect_ratio: 2.0,
+            image_width: 4,
+            stereo: Some(stereo),
+            ..Camera::default()
+        };
+        camera.initialize();
+        camera
+    }
+
+    #[test]
+    fn adaptive_sampling_reconstructs_with_the_pixel_box() {
+        let mut camera = Camera {
+            filter: Filter::mitchell_netravali(2.0),
+            ..Camera::default()
+        };
+        assert_eq!(camera.film_filter(), Filter::mitchell_netravali(2.0));
+        camera.adaptive = Some(AdaptiveSampling::new(8, 64, 0.05));
+        assert_eq!(camera.film_filter(), Filter::default());
+    }
+
+    #[test]
+    fn pixel_statistics_track_mean_and_variance() {
+        let mut statistics = PixelStatistics::default();
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            statistics.add(x);
+        }
+        assert_eq!(statistics.count, 8);
+        assert!((statistics.mean - 5.0).abs() < 1e-12);
+        // sample variance 32 / 7, standard error sqrt(32 / 56), relative to a mean of 5
+        let expected = (32.0f64 / 56.0).sqrt() / 5.0;
+        assert!((statistics.relative_error() - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pixel_statistics_stop_once_the_error_is_small() {
+        // constant samples have converged as soon as there are two of them
+        let mut statistics = PixelStatistics::default();
+        statistics.add(0.5);
+        statistics.add(0.5);
+        assert_eq!(statistics.relative_error(), 0.0);
+
+        // noisy black pixels never converge on their relative error
+        let mut statistics = PixelStatistics::default();
+        statistics.add(1.0);
+        statistics.add(-1.0);
+        assert_eq!(statistics.relative_error(), INFINITY);
+
+        // alternating samples shrink the error with the square root of the count
+        let mut statistics = PixelStatistics::default();
+        let mut count_at_threshold = None;
+        for k in 0..10000 {
+            statistics.add(if k % 2 == 0 { 0.5 } else { 1.5 });
+            if statistics.count >= 2 && statistics.relative_error() <= 0.05 {
+                count_at_threshold = Some(statistics.count);
+                break;
+            }
+        }
+        // standard error 0.5 / sqrt(n) falls below 5% of the mean of 1 at about n = 100
+        let count = count_at_threshold.unwrap();
+        assert!((95..=105).contains(&count), "{count}");
+    }
+
+    #[test]
+    fn eye_pixel_splits_the_output_between_the_eyes() {
+        let camera = stereo_camera(Stereo::new(0.1, 5.0, StereoLayout::SideBySide));
+        assert_eq!(camera.eye_pixel(3, 1), (0, 3, 1));
+        assert_eq!(camera.eye_pixel(4, 1), (1, 0, 1));
+        assert_eq!(camera.eye_offset(0), -0.05);
+        assert_eq!(camera.eye_offset(1), 0.05);
+
+        let camera = stereo_camera(Stereo::new(0.1, 5.0, StereoLayout::TopBottom));
+        assert_eq!(camera.eye_pixel(3, 1), (0, 3, 1));
+        assert_eq!(camera.eye_pixel(3, 2), (1, 3, 0));
+    }
+
+    #[test]
+    fn eye_pixel_keeps_the_right_eye_without_separation() {
+        // with both eyes at the center, the right eye's pixels still go to its own film
+        let camera = stereo_camera(Stereo::new(0.0, 5.0, StereoLayout::SideBySide));
+        assert_eq!(camera.eye_pixel(5, 0), (1, 1, 0));
+        assert_eq!(camera.eye_offset(1), 0.0);
+    }
 }
@@ -0,0 +1,181 @@
+// src/engine/capsule.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{self, Material},
+    onb::Onb,
+    polynomial,
+    ray::Ray,
+    utils::PI,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a capsule: every point within a radius of the segment between two end points, i.e. a
+/// cylinder closed by hemispheres
+#[derive(Clone)]
+pub struct Capsule {
+    start: Point3, // center of the bottom hemisphere
+    frame: Onb,    // local frame whose w axis runs from start to end
+    length: f64,   // distance between the hemisphere centers
+    radius: f64,
+    mat: Rc<dyn Material>,
+}
+
+impl Capsule {
+    /// constructs new `Capsule` around the segment from start to end with given radius
+    pub fn new(start: Point3, end: Point3, radius: f64, mat: Rc<dyn Material>) -> Capsule {
+        let axis = end - start;
+        Capsule {
+            start,
+            // a zero length capsule is a sphere, any frame will do
+            frame: Onb::new(&if axis.near_zero() {
+                Vec3::new(0.0, 0.0, 1.0)
+            } else {
+                axis
+            }),
+            length: axis.length(),
+            radius: radius.max(0.0),
+            mat,
+        }
+    }
+
+    /// returns the center of the bottom hemisphere
+    pub fn start(&self) -> Point3 {
+        self.start
+    }
+
+    /// returns the center of the top hemisphere
+    pub fn end(&self) -> Point3 {
+        self.start + self.length * self.frame.w()
+    }
+
+    /// returns the capsule's radius
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// returns the capsule's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// returns the local space outward normal, uv and tangents at a local point on the surface
+    /// u runs around the axis and v along the profile from the bottom pole to the top pole
+    fn surface_at(&self, p: &Point3) -> (Vec3, (f64, f64), Vec3, Vec3) {
+        // the nearest point on the segment is the center of the local cross section
+        let z = p.z().clamp(0.0, self.length);
+        let normal = (*p - Point3::new(0.0, 0.0, z)) / self.radius;
+
+        // arc length along the profile: a quarter circle, the straight side, a quarter circle
+        let quarter = 0.5 * PI * self.radius;
+        let profile_length = 2.0 * quarter + self.length;
+        let elevation = normal.z().clamp(-1.0, 1.0).asin();
+        let s = if p.z() < 0.0 {
+            quarter + self.radius * elevation
+        } else if p.z() > self.length {
+            quarter + self.length + self.radius * elevation
+        } else {
+            quarter + p.z()
+        };
+
+        let phi = p.y().atan2(p.x());
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let uv = (phi / (2.0 * PI), s / profile_length);
+
+        // the profile's unit tangent, written in terms of the normal, covers all three parts
+        let ring = (normal.x() * normal.x() + normal.y() * normal.y())
+            .sqrt()
+            .max(1e-8);
+        let dpdu = 2.0 * PI * Vec3::new(-p.y(), p.x(), 0.0);
+        let dpdv = profile_length
+            * Vec3::new(
+                -normal.z() * normal.x() / ring,
+                -normal.z() * normal.y() / ring,
+                ring,
+            );
+        (normal, uv, dpdu, dpdv)
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if self.radius <= 0.0 {
+            return false;
+        }
+
+        // move the ray into the local frame, where the segment runs along +z from the origin
+        let o = self.frame.to_local(&(ray.origin() - self.start));
+        let d = self.frame.to_local(&ray.direction());
+        let r2 = self.radius * self.radius;
+
+        // collect crossings of the side and of each hemisphere on its own half, then take
+        // the nearest acceptable one
+        let mut candidates = Vec::with_capacity(6);
+        if let Some((t0, t1)) = polynomial::solve_quadratic(
+            d.x() * d.x() + d.y() * d.y(),
+            2.0 * (o.x() * d.x() + o.y() * d.y()),
+            o.x() * o.x() + o.y() * o.y() - r2,
+        ) {
+            for t in [t0, t1] {
+                let z = o.z() + t * d.z();
+                if (0.0..=self.length).contains(&z) {
+                    candidates.push(t);
+                }
+            }
+        }
+        for z_center in [0.0, self.length] {
+            let oc = o - Point3::new(0.0, 0.0, z_center);
+            if let Some((t0, t1)) = polynomial::solve_quadratic(
+                d.length_squared(),
+                2.0 * vec3::dot(&oc, &d),
+                oc.length_squared() - r2,
+            ) {
+                for t in [t0, t1] {
+                    let z = o.z() + t * d.z();
+                    let on_hemisphere = if z_center == 0.0 {
+                        z < 0.0
+                    } else {
+                        z > self.length
+                    };
+                    if on_hemisphere {
+                        candidates.push(t);
+                    }
+                }
+            }
+        }
+        candidates.sort_by(f64::total_cmp);
+
+        for t in candidates {
+            if !ray_t.contains(t) {
+                continue;
+            }
+            let local_p = o + t * d;
+            let (normal, (u, v), dpdu, dpdv) = self.surface_at(&local_p);
+
+            rec.set_t(t);
+            rec.set_p(ray.at(t));
+            rec.set_uv(u, v);
+            rec.set_tangents(self.frame.transform(&dpdu), self.frame.transform(&dpdv));
+            rec.set_face_normal(ray, &vec3::unit_vector(self.frame.transform(&normal)));
+            rec.set_mat(self.mat.clone());
+            if material::passes_alpha_test(self.mat.as_ref(), rec) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let half = Vec3::new(self.radius, self.radius, self.radius);
+        let end = self.end();
+        Aabb::surrounding(
+            &Aabb::from_points(self.start - half, self.start + half),
+            &Aabb::from_points(end - half, end + half),
+        )
+    }
+}
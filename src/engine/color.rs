@@ -5,6 +5,11 @@ use std::io::Write;
 
 pub type Color = Vec3;
 
+/// returns the luminance of a linear rgb color
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
 pub fn write_color<W: Write>(writer: &mut W, pixel_color: Color) -> std::io::Result<()> {
     // extract color components
     let r = pixel_color.x();
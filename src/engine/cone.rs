@@ -0,0 +1,202 @@
+// src/engine/cone.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{self, Material},
+    onb::Onb,
+    polynomial,
+    ray::Ray,
+    utils::PI,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a capped circular cone, or a frustum when the top radius is nonzero, between two end points
+#[derive(Clone)]
+pub struct Cone {
+    base: Point3,     // center of the bottom cap
+    frame: Onb,       // local frame whose w axis runs from the bottom cap to the top
+    height: f64,      // distance between the caps
+    base_radius: f64, // radius at the bottom cap
+    top_radius: f64,  // radius at the top, zero for a pointed cone
+    capped: bool,     // whether the end discs are part of the surface
+    mat: Rc<dyn Material>,
+}
+
+impl Cone {
+    /// constructs new capped pointed `Cone` with its base disc at base and its apex at apex
+    pub fn new(base: Point3, apex: Point3, radius: f64, mat: Rc<dyn Material>) -> Cone {
+        Cone::new_frustum(base, apex, radius, 0.0, mat)
+    }
+
+    /// constructs new capped `Cone` frustum whose radius narrows (or widens) linearly from
+    /// base_radius at base to top_radius at top
+    pub fn new_frustum(
+        base: Point3,
+        top: Point3,
+        base_radius: f64,
+        top_radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Cone {
+        let axis = top - base;
+        Cone {
+            base,
+            frame: Onb::new(&axis),
+            height: axis.length(),
+            base_radius: base_radius.max(0.0),
+            top_radius: top_radius.max(0.0),
+            capped: true,
+            mat,
+        }
+    }
+
+    /// returns the center of the cone's bottom cap
+    pub fn base(&self) -> Point3 {
+        self.base
+    }
+
+    /// returns the center of the cone's top, the apex for a pointed cone
+    pub fn top(&self) -> Point3 {
+        self.base + self.height * self.frame.w()
+    }
+
+    /// returns the cone's radius at its base
+    pub fn base_radius(&self) -> f64 {
+        self.base_radius
+    }
+
+    /// returns the cone's radius at its top
+    pub fn top_radius(&self) -> f64 {
+        self.top_radius
+    }
+
+    /// returns whether the cone is closed by end caps
+    pub fn is_capped(&self) -> bool {
+        self.capped
+    }
+
+    /// returns the cone's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// sets whether the cone is closed by end caps
+    pub fn set_capped(&mut self, capped: bool) {
+        self.capped = capped;
+    }
+
+    /// returns the change in radius per unit of height
+    fn slope(&self) -> f64 {
+        (self.top_radius - self.base_radius) / self.height
+    }
+
+    /// returns the radius at local height z
+    fn radius_at(&self, z: f64) -> f64 {
+        self.base_radius + self.slope() * z
+    }
+
+    /// returns the local space outward normal, uv and tangents at a local point on the side
+    /// or on one of the caps
+    fn surface_at(&self, p: &Point3, on_cap: bool) -> (Vec3, (f64, f64), Vec3, Vec3) {
+        if on_cap {
+            // caps use a planar mapping of the disc onto the unit square
+            let (normal, radius) = if p.z() < 0.5 * self.height {
+                (Vec3::new(0.0, 0.0, -1.0), self.base_radius)
+            } else {
+                (Vec3::new(0.0, 0.0, 1.0), self.top_radius)
+            };
+            let uv = (0.5 * (p.x() / radius + 1.0), 0.5 * (p.y() / radius + 1.0));
+            let dpdu = Vec3::new(2.0 * radius, 0.0, 0.0);
+            let dpdv = Vec3::new(0.0, 2.0 * radius, 0.0);
+            return (normal, uv, dpdu, dpdv);
+        }
+
+        // the side wraps u around the axis and runs v from the bottom to the top
+        let k = self.slope();
+        let mut normal = Vec3::new(p.x(), p.y(), -k * self.radius_at(p.z()));
+        if normal.near_zero() {
+            normal = Vec3::new(0.0, 0.0, 1.0); // the apex
+        }
+        let phi = p.y().atan2(p.x());
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let uv = (phi / (2.0 * PI), p.z() / self.height);
+        let dpdu = 2.0 * PI * Vec3::new(-p.y(), p.x(), 0.0);
+        let dpdv = self.height * Vec3::new(k * phi.cos(), k * phi.sin(), 1.0);
+        (vec3::unit_vector(normal), uv, dpdu, dpdv)
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if self.height <= 0.0 || self.base_radius.max(self.top_radius) <= 0.0 {
+            return false;
+        }
+
+        // move the ray into the local frame, where the axis is +z from the origin
+        let o = self.frame.to_local(&(ray.origin() - self.base));
+        let d = self.frame.to_local(&ray.direction());
+
+        // the radius along the ray is linear in t: a + b t
+        let k = self.slope();
+        let a = self.radius_at(o.z());
+        let b = k * d.z();
+
+        // collect every crossing of the side and caps, then take the nearest acceptable one
+        let mut candidates = Vec::with_capacity(4);
+        if let Some((t0, t1)) = polynomial::solve_quadratic(
+            d.x() * d.x() + d.y() * d.y() - b * b,
+            2.0 * (o.x() * d.x() + o.y() * d.y() - a * b),
+            o.x() * o.x() + o.y() * o.y() - a * a,
+        ) {
+            for t in [t0, t1] {
+                // keeping z within the caps also rejects the mirrored nappe of the double cone
+                let z = o.z() + t * d.z();
+                if (0.0..=self.height).contains(&z) {
+                    candidates.push((t, false));
+                }
+            }
+        }
+        if self.capped && d.z() != 0.0 {
+            for (z, radius) in [(0.0, self.base_radius), (self.height, self.top_radius)] {
+                let t = (z - o.z()) / d.z();
+                let (x, y) = (o.x() + t * d.x(), o.y() + t * d.y());
+                if radius > 0.0 && x * x + y * y <= radius * radius {
+                    candidates.push((t, true));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (t, on_cap) in candidates {
+            if !ray_t.contains(t) {
+                continue;
+            }
+            let local_p = o + t * d;
+            let (normal, (u, v), dpdu, dpdv) = self.surface_at(&local_p, on_cap);
+
+            rec.set_t(t);
+            rec.set_p(ray.at(t));
+            rec.set_uv(u, v);
+            rec.set_tangents(self.frame.transform(&dpdu), self.frame.transform(&dpdv));
+            rec.set_face_normal(ray, &self.frame.transform(&normal));
+            rec.set_mat(self.mat.clone());
+            if material::passes_alpha_test(self.mat.as_ref(), rec) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let axis = self.frame.w();
+        Aabb::surrounding(
+            &Aabb::from_disc(self.base, axis, self.base_radius),
+            &Aabb::from_disc(self.top(), axis, self.top_radius),
+        )
+        .padded()
+    }
+}
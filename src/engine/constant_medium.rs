@@ -0,0 +1,105 @@
+// src/engine/constant_medium.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{HenyeyGreenstein, Isotropic, Material},
+    ray::Ray,
+    utils::{self, INFINITY},
+    vec3::Vec3,
+};
+
+use std::rc::Rc;
+
+/// a participating medium of uniform density filling a closed boundary
+/// NOTE: the boundary is assumed to be convex, rays entering it more than once are only scattered in the first span
+pub struct ConstantMedium {
+    boundary: Rc<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Rc<dyn Material>,
+}
+
+impl ConstantMedium {
+    /// constructs new `ConstantMedium` with an isotropic phase function of given albedo
+    pub fn new(boundary: Rc<dyn Hittable>, density: f64, albedo: Color) -> ConstantMedium {
+        ConstantMedium::new_with_phase(boundary, density, Rc::new(Isotropic::new(albedo)))
+    }
+
+    /// constructs new `ConstantMedium` with a Henyey-Greenstein phase function of given albedo and asymmetry
+    pub fn new_henyey_greenstein(
+        boundary: Rc<dyn Hittable>,
+        density: f64,
+        albedo: Color,
+        g: f64,
+    ) -> ConstantMedium {
+        ConstantMedium::new_with_phase(boundary, density, Rc::new(HenyeyGreenstein::new(albedo, g)))
+    }
+
+    /// constructs new `ConstantMedium` with an arbitrary phase function
+    pub fn new_with_phase(
+        boundary: Rc<dyn Hittable>,
+        density: f64,
+        phase_function: Rc<dyn Material>,
+    ) -> ConstantMedium {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+
+    /// returns the medium's density
+    pub fn density(&self) -> f64 {
+        -1.0 / self.neg_inv_density
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // find where the ray enters and exits the boundary, regardless of ray_t
+        let mut rec1 = HitRecord::new();
+        let mut rec2 = HitRecord::new();
+
+        if !self.boundary.hit(ray, Interval::universe(), &mut rec1) {
+            return false;
+        }
+        if !self
+            .boundary
+            .hit(ray, Interval::new(rec1.t() + 0.0001, INFINITY), &mut rec2)
+        {
+            return false;
+        }
+
+        // clip the span inside the medium to the requested interval
+        let t_enter = rec1.t().max(ray_t.min).max(0.0);
+        let t_exit = rec2.t().min(ray_t.max);
+        if t_enter >= t_exit {
+            return false;
+        }
+
+        // sample an exponential free-flight distance through the medium
+        let ray_length = ray.direction().length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * (1.0 - utils::random_0_to_1()).ln();
+
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.set_t(t_enter + hit_distance / ray_length);
+        rec.set_p(ray.at(rec.t()));
+
+        // normal and facing are meaningless inside a medium
+        rec.set_normal(Vec3::new(1.0, 0.0, 0.0));
+        rec.set_front_face(true);
+        rec.set_mat(self.phase_function.clone());
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
@@ -0,0 +1,250 @@
+// src/engine/csg.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    utils::INFINITY,
+};
+
+use std::rc::Rc;
+
+// upper bound on the boundary crossings gathered per operand, guarding against runaway loops
+const MAX_CROSSINGS: usize = 64;
+
+/// a boolean operation combining the solids of two operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    Union,        // points inside either operand
+    Intersection, // points inside both operands
+    Difference,   // points inside the left operand but not the right
+}
+
+impl CsgOperation {
+    /// returns whether a point is inside the combined solid given whether it is inside each operand
+    fn inside(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// a constructive solid geometry node combining two closed objects with a boolean operation
+/// NOTE: operands must be closed surfaces whose normals face outwards, so that a front face
+/// hit enters the solid and a back face hit leaves it
+pub struct Csg {
+    left: Rc<dyn Hittable>,
+    right: Rc<dyn Hittable>,
+    operation: CsgOperation,
+}
+
+impl Csg {
+    /// constructs new `Csg` node combining left and right with given operation
+    pub fn new(operation: CsgOperation, left: Rc<dyn Hittable>, right: Rc<dyn Hittable>) -> Csg {
+        Csg {
+            left,
+            right,
+            operation,
+        }
+    }
+
+    /// constructs new `Csg` node of the points inside either object
+    pub fn union(left: Rc<dyn Hittable>, right: Rc<dyn Hittable>) -> Csg {
+        Csg::new(CsgOperation::Union, left, right)
+    }
+
+    /// constructs new `Csg` node of the points inside both objects
+    pub fn intersection(left: Rc<dyn Hittable>, right: Rc<dyn Hittable>) -> Csg {
+        Csg::new(CsgOperation::Intersection, left, right)
+    }
+
+    /// constructs new `Csg` node of the points inside left but not inside right
+    pub fn difference(left: Rc<dyn Hittable>, right: Rc<dyn Hittable>) -> Csg {
+        Csg::new(CsgOperation::Difference, left, right)
+    }
+
+    /// returns the node's left operand
+    pub fn left(&self) -> Rc<dyn Hittable> {
+        self.left.clone()
+    }
+
+    /// returns the node's right operand
+    pub fn right(&self) -> Rc<dyn Hittable> {
+        self.right.clone()
+    }
+
+    /// returns the node's boolean operation
+    pub fn operation(&self) -> CsgOperation {
+        self.operation
+    }
+}
+
+/// returns every crossing of the object's boundary along the whole line of the ray, in order
+fn crossings(object: &dyn Hittable, ray: &Ray) -> Vec<HitRecord> {
+    let mut hits = Vec::new();
+    let mut t_min = -INFINITY;
+    let mut rec = HitRecord::new();
+
+    while hits.len() < MAX_CROSSINGS && object.hit(ray, Interval::new(t_min, INFINITY), &mut rec) {
+        // step just past the hit, so coincident crossings (e.g. at a box edge) are only seen once
+        t_min = rec.t() + 1e-9 * rec.t().abs().max(1.0);
+        hits.push(rec.clone());
+    }
+
+    hits
+}
+
+/// returns whether the ray starts inside the solid, far behind its origin, given its crossings
+/// NOTE: the first crossing of a closed surface leaves the solid only if the line began inside
+fn starts_inside(hits: &[HitRecord]) -> bool {
+    hits.first().is_some_and(|rec| !rec.front_face())
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let bbox = self.bounding_box();
+        if bbox.is_empty() || !bbox.hit(ray, ray_t) {
+            return false;
+        }
+
+        // gather each operand's interval list as its ordered entry/exit crossings
+        let left_hits = crossings(self.left.as_ref(), ray);
+        let right_hits = crossings(self.right.as_ref(), ray);
+        let mut in_left = starts_inside(&left_hits);
+        let mut in_right = starts_inside(&right_hits);
+        let mut inside = self.operation.inside(in_left, in_right);
+
+        // sweep both lists in order of t, reporting the first crossing of the combined solid
+        let (mut i, mut j) = (0, 0);
+        while i < left_hits.len() || j < right_hits.len() {
+            let from_left = j >= right_hits.len()
+                || (i < left_hits.len() && left_hits[i].t() <= right_hits[j].t());
+            let crossing = if from_left {
+                i += 1;
+                in_left = left_hits[i - 1].front_face();
+                &left_hits[i - 1]
+            } else {
+                j += 1;
+                in_right = right_hits[j - 1].front_face();
+                &right_hits[j - 1]
+            };
+
+            let now_inside = self.operation.inside(in_left, in_right);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+
+            if crossing.t() > ray_t.max {
+                return false;
+            }
+            if !ray_t.contains(crossing.t()) {
+                continue;
+            }
+
+            // the operand's surface bounds the combined solid here, but a subtracted operand's
+            // surface faces into its own solid, so its outward normal is reversed
+            *rec = crossing.clone();
+            let operand_outward = if crossing.front_face() {
+                crossing.normal()
+            } else {
+                -crossing.normal()
+            };
+            let outward = if !from_left && self.operation == CsgOperation::Difference {
+                -operand_outward
+            } else {
+                operand_outward
+            };
+            rec.set_face_normal(ray, &outward);
+            return true;
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let left = self.left.bounding_box();
+        let right = self.right.bounding_box();
+        match self.operation {
+            CsgOperation::Union => Aabb::surrounding(&left, &right),
+            CsgOperation::Intersection => Aabb::overlap(&left, &right),
+            CsgOperation::Difference => left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        color::Color,
+        material::Lambertian,
+        sphere::Sphere,
+        vec3::{Point3, Vec3},
+    };
+
+    /// returns unit spheres centered at x = 0 and x = 1, overlapping over x in [0,1]
+    fn overlapping_spheres() -> (Rc<dyn Hittable>, Rc<dyn Hittable>) {
+        let mat = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        (
+            Rc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mat.clone())),
+            Rc::new(Sphere::new(Point3::new(1.0, 0.0, 0.0), 1.0, mat)),
+        )
+    }
+
+    /// returns the entry and exit t of a ray along +x from x = -5 through the solid, checking
+    /// the hit normals face the ray and point out of the solid
+    fn entry_and_exit(solid: &Csg) -> (f64, f64) {
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+
+        let mut entry = HitRecord::new();
+        assert!(solid.hit(&ray, Interval::new(0.001, INFINITY), &mut entry));
+        assert!(entry.front_face());
+        assert!(entry.normal().x() < 0.0);
+
+        let mut exit = HitRecord::new();
+        let after_entry = Interval::new(entry.t() + 1e-6, INFINITY);
+        assert!(solid.hit(&ray, after_entry, &mut exit));
+        assert!(!exit.front_face());
+        assert!(exit.normal().x() < 0.0);
+
+        let mut beyond = HitRecord::new();
+        let after_exit = Interval::new(exit.t() + 1e-6, INFINITY);
+        assert!(!solid.hit(&ray, after_exit, &mut beyond));
+
+        (entry.t(), exit.t())
+    }
+
+    /// checks entry and exit t against the expected values
+    fn assert_span(span: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (span.0 - expected.0).abs() < 1e-9 && (span.1 - expected.1).abs() < 1e-9,
+            "span {span:?}, expected {expected:?}"
+        );
+    }
+
+    #[test]
+    fn union_spans_both_spheres() {
+        let (a, b) = overlapping_spheres();
+        assert_span(entry_and_exit(&Csg::union(a, b)), (4.0, 7.0));
+    }
+
+    #[test]
+    fn intersection_spans_the_overlap() {
+        let (a, b) = overlapping_spheres();
+        assert_span(entry_and_exit(&Csg::intersection(a, b)), (5.0, 6.0));
+    }
+
+    #[test]
+    fn difference_spans_the_left_sphere_outside_the_right() {
+        let (a, b) = overlapping_spheres();
+        assert_span(
+            entry_and_exit(&Csg::difference(a.clone(), b.clone())),
+            (4.0, 5.0),
+        );
+        assert_span(entry_and_exit(&Csg::difference(b, a)), (6.0, 7.0));
+    }
+}
@@ -0,0 +1,490 @@
+// src/engine/curve.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    bvh::BvhNode,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{self, Material},
+    onb::Onb,
+    polynomial,
+    ray::Ray,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// the cross section swept along a curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveShape {
+    Flat,              // a strip that always turns to face the incoming ray, for thin hair and fur
+    Ribbon([Vec3; 2]), // a strip twisting from its start normal to its end normal
+    Tube,              // a round tube, for fibers seen up close
+}
+
+/// a ray and the frame of the space in which it runs along +z from the origin
+struct RaySpace<'a> {
+    ray: &'a Ray,
+    frame: Onb,  // ray space axes in world coordinates, w along the ray
+    length: f64, // length of the ray's direction, ray space distance per unit of t
+}
+
+/// a cubic Bézier curve swept with a width that varies linearly from its start to its end
+///
+/// rays are tested in a space where they run along +z from the origin: the curve is halved until
+/// its pieces are nearly straight, culling pieces whose widened boxes miss the z axis
+#[derive(Clone)]
+pub struct Curve {
+    control_points: [Point3; 4], // control points of the part of the curve this segment covers
+    u_range: (f64, f64),         // span of the whole curve's parameter covered by this segment
+    widths: [f64; 2],            // width at the start and at the end of the whole curve
+    shape: CurveShape,
+    mat: Rc<dyn Material>,
+}
+
+impl Curve {
+    /// constructs new `Curve` from its four Bézier control points, its width at either end,
+    /// its cross section and material
+    pub fn new(
+        control_points: [Point3; 4],
+        widths: [f64; 2],
+        shape: CurveShape,
+        mat: Rc<dyn Material>,
+    ) -> Curve {
+        let shape = match shape {
+            CurveShape::Ribbon([n0, n1]) => {
+                CurveShape::Ribbon([vec3::unit_vector(n0), vec3::unit_vector(n1)])
+            }
+            shape => shape,
+        };
+
+        Curve {
+            control_points,
+            u_range: (0.0, 1.0),
+            widths: [widths[0].max(0.0), widths[1].max(0.0)],
+            shape,
+            mat,
+        }
+    }
+
+    /// returns the segment's control points
+    pub fn control_points(&self) -> [Point3; 4] {
+        self.control_points
+    }
+
+    /// returns the span of the whole curve's parameter covered by the segment
+    pub fn u_range(&self) -> (f64, f64) {
+        self.u_range
+    }
+
+    /// returns the width at the start and at the end of the whole curve
+    pub fn widths(&self) -> [f64; 2] {
+        self.widths
+    }
+
+    /// returns the curve's cross section
+    pub fn shape(&self) -> CurveShape {
+        self.shape
+    }
+
+    /// returns the curve's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// splits the segment into given number of equal parameter segments, whose tighter boxes
+    /// let a bvh cull long curves far better
+    pub fn split(&self, segments: usize) -> Vec<Curve> {
+        let segments = segments.max(1);
+        let (u0, u1) = self.u_range;
+        let cp = &self.control_points;
+
+        (0..segments)
+            .map(|i| {
+                let a = i as f64 / segments as f64;
+                let b = (i + 1) as f64 / segments as f64;
+                Curve {
+                    control_points: [
+                        blossom(cp, a, a, a),
+                        blossom(cp, a, a, b),
+                        blossom(cp, a, b, b),
+                        blossom(cp, b, b, b),
+                    ],
+                    u_range: (lerp(a, u0, u1), lerp(b, u0, u1)),
+                    ..self.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// returns the width at parameter u of the whole curve
+    fn width_at(&self, u: f64) -> f64 {
+        lerp(u, self.widths[0], self.widths[1])
+    }
+
+    /// returns the ribbon's normal at parameter u, spherically interpolating its end normals
+    fn ribbon_normal(&self, n0: &Vec3, n1: &Vec3, u: f64) -> Vec3 {
+        let angle = vec3::dot(n0, n1).clamp(-1.0, 1.0).acos();
+        if angle.sin() < 1e-6 {
+            return vec3::unit_vector((1.0 - u) * *n0 + u * *n1);
+        }
+        (((1.0 - u) * angle).sin() * *n0 + (u * angle).sin() * *n1) / angle.sin()
+    }
+
+    /// intersects the ray with a piece of the segment given by its ray space control points,
+    /// subdividing it depth more times, and keeps the closest accepted hit in rec
+    fn hit_piece(
+        &self,
+        space: &RaySpace,
+        cp: &[Point3; 4],
+        (u0, u1): (f64, f64),
+        depth: usize,
+        ray_t: &mut Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        // the piece lies inside the hull of its control points, widened by its half width
+        let length = space.length;
+        let half_width = 0.5 * self.width_at(u0).max(self.width_at(u1));
+        let bounds = Aabb::surrounding(
+            &Aabb::from_points(cp[0], cp[1]),
+            &Aabb::from_points(cp[2], cp[3]),
+        )
+        .expand(half_width);
+        if bounds.x.min > 0.0
+            || bounds.x.max < 0.0
+            || bounds.y.min > 0.0
+            || bounds.y.max < 0.0
+            || bounds.z.max < ray_t.min * length
+            || bounds.z.min > ray_t.max * length
+        {
+            return false;
+        }
+
+        if depth > 0 {
+            let halves = subdivide(cp);
+            let u_mid = 0.5 * (u0 + u1);
+            let hit_first = self.hit_piece(
+                space,
+                &[halves[0], halves[1], halves[2], halves[3]],
+                (u0, u_mid),
+                depth - 1,
+                ray_t,
+                rec,
+            );
+            let hit_second = self.hit_piece(
+                space,
+                &[halves[3], halves[4], halves[5], halves[6]],
+                (u_mid, u1),
+                depth - 1,
+                ray_t,
+                rec,
+            );
+            return hit_first || hit_second;
+        }
+
+        self.hit_line(space, cp, (u0, u1), ray_t, rec)
+    }
+
+    /// intersects the ray with a piece flat enough to treat as a line between its end points
+    fn hit_line(
+        &self,
+        space: &RaySpace,
+        cp: &[Point3; 4],
+        (u0, u1): (f64, f64),
+        ray_t: &mut Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let (ray, frame, length) = (space.ray, &space.frame, space.length);
+        let direction = ray.direction() / length;
+
+        // closest point to the z axis along the line, in the xy plane
+        let (dx, dy) = (cp[3].x() - cp[0].x(), cp[3].y() - cp[0].y());
+        let denominator = dx * dx + dy * dy;
+        let w = if denominator > 0.0 {
+            ((-cp[0].x() * dx - cp[0].y() * dy) / denominator).clamp(0.0, 1.0)
+        } else if self.shape == CurveShape::Tube {
+            0.5 // seen end on, only the tube's round ends can be hit
+        } else {
+            return false;
+        };
+
+        let mut radius = 0.5 * self.width_at(lerp(w, u0, u1));
+        let ribbon_normal = match self.shape {
+            CurveShape::Ribbon([n0, n1]) => {
+                let normal = self.ribbon_normal(&n0, &n1, lerp(w, u0, u1));
+                // a ribbon seen edge on narrows to nothing
+                radius *= vec3::dot(&normal, &direction).abs();
+                Some(normal)
+            }
+            _ => None,
+        };
+        if radius <= 0.0 {
+            return false;
+        }
+
+        // distance along the ray to the surface, and the parameter of the line's point under it
+        let (s, w) = match self.shape {
+            CurveShape::Tube => match capsule_crossing(cp, radius, *ray_t, length) {
+                Some(crossing) => crossing,
+                None => return false,
+            },
+            _ => {
+                // a strip is crossed where the ray meets its plane, so it is tested in the xy
+                // plane, cut off by the lines through either end normal to the end tangents so
+                // that consecutive pieces meet without gaps or overlaps
+                let flat = cp.map(|p| Point3::new(p.x(), p.y(), 0.0));
+                if vec3::dot(&-flat[0], &(flat[1] - flat[0])) < 0.0
+                    || vec3::dot(&-flat[3], &(flat[2] - flat[3])) < 0.0
+                {
+                    return false;
+                }
+                let (pc, _) = evaluate(cp, w);
+                if pc.x() * pc.x() + pc.y() * pc.y() > radius * radius {
+                    return false;
+                }
+                (pc.z(), w)
+            }
+        };
+        let t = s / length;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // back to world space: the hit's offset from the curve's axis gives its normal and v
+        let u = lerp(w, u0, u1);
+        let p = ray.at(t);
+        let (pc, dpcdw) = evaluate(cp, w);
+        let dpdu = frame.transform(&dpcdw) / (u1 - u0);
+        let tangent = vec3::unit_vector(dpdu);
+        let perpendicular = |v: Vec3| v - vec3::dot(&v, &tangent) * tangent;
+        let (offset, mut outward) = match (self.shape, ribbon_normal) {
+            (CurveShape::Tube, _) => {
+                let offset = frame.transform(&(Point3::new(0.0, 0.0, s) - lerp_point(w, cp)));
+                (offset, offset)
+            }
+            (_, Some(normal)) => (
+                perpendicular(p - ray.origin() - frame.transform(&pc)),
+                perpendicular(normal),
+            ),
+            _ => (
+                perpendicular(p - ray.origin() - frame.transform(&pc)),
+                perpendicular(-direction),
+            ),
+        };
+        if outward.near_zero() {
+            outward = -direction;
+        }
+
+        // v runs across the curve as seen from the ray, from 0 on one edge to 1 on the other
+        let across = vec3::cross(&-direction, &tangent);
+        let h = if across.near_zero() {
+            0.0
+        } else {
+            (vec3::dot(&offset, &vec3::unit_vector(across)) / radius).clamp(-1.0, 1.0)
+        };
+        let dpdv = 2.0 * radius * vec3::unit_vector(vec3::cross(&outward, &tangent));
+
+        // a cut out hit must not replace a closer accepted one, so fill a scratch record
+        let mut candidate = HitRecord::new();
+        candidate.set_t(t);
+        candidate.set_p(p);
+        candidate.set_uv(u, 0.5 + 0.5 * h);
+        candidate.set_tangents(dpdu, dpdv);
+        candidate.set_face_normal(ray, &vec3::unit_vector(outward));
+        candidate.set_mat(self.mat.clone());
+        if !material::passes_alpha_test(self.mat.as_ref(), &candidate) {
+            return false;
+        }
+
+        ray_t.max = t;
+        *rec = candidate;
+        true
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let length = ray.direction().length();
+        let (u0, u1) = self.u_range;
+        let max_width = self.width_at(u0).max(self.width_at(u1));
+        if length == 0.0 || max_width <= 0.0 {
+            return false;
+        }
+
+        // ray space: the ray runs along +z from the origin, x is normal to the curve's chord
+        let direction = ray.direction() / length;
+        let chord = self.control_points[3] - self.control_points[0];
+        let frame = Onb::from_normal_tangent(&direction, &vec3::cross(&direction, &chord));
+        let cp = self
+            .control_points
+            .map(|p| frame.to_local(&(p - ray.origin())));
+
+        // subdivide until the pieces deviate from straight lines by a fraction of the width
+        let mut deviation: f64 = 0.0;
+        for i in 0..2 {
+            let second_difference = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            deviation = deviation
+                .max(second_difference.x().abs())
+                .max(second_difference.y().abs())
+                .max(second_difference.z().abs());
+        }
+        // round silhouettes magnify any deviation, so tubes are refined further than strips
+        let tolerance = match self.shape {
+            CurveShape::Tube => 0.01,
+            _ => 0.05,
+        } * max_width;
+        let depth = (0.5 * (std::f64::consts::SQRT_2 * 6.0 * deviation / (8.0 * tolerance)).log2())
+            .round()
+            .clamp(0.0, 10.0) as usize;
+
+        let space = RaySpace { ray, frame, length };
+        let mut ray_t = ray_t;
+        self.hit_piece(&space, &cp, self.u_range, depth, &mut ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let cp = &self.control_points;
+        let (u0, u1) = self.u_range;
+        Aabb::surrounding(
+            &Aabb::from_points(cp[0], cp[1]),
+            &Aabb::from_points(cp[2], cp[3]),
+        )
+        .expand(0.5 * self.width_at(u0).max(self.width_at(u1)))
+        .padded()
+    }
+}
+
+/// returns a bvh over curves, each split into given number of segments
+pub fn make_curve_bvh(curves: &[Curve], segments: usize) -> BvhNode {
+    BvhNode::new(
+        curves
+            .iter()
+            .flat_map(|curve| curve.split(segments))
+            .map(|curve| Rc::new(curve) as Rc<dyn Hittable>)
+            .collect(),
+    )
+}
+
+/// returns the linear interpolation from a to b at t
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    (1.0 - t) * a + t * b
+}
+
+/// returns the point at w along the chord from the first to the last control point
+fn lerp_point(w: f64, cp: &[Point3; 4]) -> Point3 {
+    (1.0 - w) * cp[0] + w * cp[3]
+}
+
+/// returns the first crossing within ray_t of the ray space z axis with the capsule of given
+/// radius around the chord from the first to the last control point, as the distance along the
+/// axis and the parameter along the chord of the nearest point to the crossing
+/// NOTE: a tube is the union of these capsules, a sphere swept along the curve, so consecutive
+/// pieces overlap at their round ends instead of leaving gaps where the curve bends
+fn capsule_crossing(
+    cp: &[Point3; 4],
+    radius: f64,
+    ray_t: Interval,
+    length: f64,
+) -> Option<(f64, f64)> {
+    let (start, end) = (cp[0], cp[3]);
+    let axis = end - start;
+    let axis_length_squared = axis.length_squared();
+    let along = |s: f64| {
+        if axis_length_squared > 0.0 {
+            vec3::dot(&(Point3::new(0.0, 0.0, s) - start), &axis) / axis_length_squared
+        } else {
+            0.5
+        }
+    };
+
+    let mut nearest: Option<f64> = None;
+    let mut consider = |s: f64| {
+        if ray_t.contains(s / length) && nearest.is_none_or(|nearest| s < nearest) {
+            nearest = Some(s);
+        }
+    };
+
+    // the side, between the planes through either end of the chord
+    let z = Vec3::new(0.0, 0.0, 1.0);
+    if axis_length_squared > 0.0 {
+        let unit_axis = axis / axis_length_squared.sqrt();
+        let perpendicular = |v: Vec3| v - vec3::dot(&v, &unit_axis) * unit_axis;
+        let (q, e) = (perpendicular(-start), perpendicular(z));
+        if e.length_squared() > 1e-12
+            && let Some((s0, s1)) = polynomial::solve_quadratic(
+                e.length_squared(),
+                2.0 * vec3::dot(&q, &e),
+                q.length_squared() - radius * radius,
+            )
+        {
+            for s in [s0, s1] {
+                if (0.0..=1.0).contains(&along(s)) {
+                    consider(s);
+                }
+            }
+        }
+    }
+
+    // the round ends, beyond those planes
+    for (center, is_start) in [(start, true), (end, false)] {
+        let oc = -center;
+        if let Some((s0, s1)) = polynomial::solve_quadratic(
+            1.0,
+            2.0 * vec3::dot(&oc, &z),
+            oc.length_squared() - radius * radius,
+        ) {
+            for s in [s0, s1] {
+                let w = along(s);
+                if (is_start && w <= 0.0) || (!is_start && w >= 1.0) || axis_length_squared == 0.0 {
+                    consider(s);
+                }
+            }
+        }
+    }
+
+    nearest.map(|s| (s, along(s).clamp(0.0, 1.0)))
+}
+
+/// returns the polar form (blossom) of the cubic Bézier curve at (u0,u1,u2)
+fn blossom(cp: &[Point3; 4], u0: f64, u1: f64, u2: f64) -> Point3 {
+    let mix = |t: f64, a: Point3, b: Point3| (1.0 - t) * a + t * b;
+    let a = [
+        mix(u0, cp[0], cp[1]),
+        mix(u0, cp[1], cp[2]),
+        mix(u0, cp[2], cp[3]),
+    ];
+    let b = [mix(u1, a[0], a[1]), mix(u1, a[1], a[2])];
+    mix(u2, b[0], b[1])
+}
+
+/// returns the control points of the curve's two halves, sharing the middle point
+fn subdivide(cp: &[Point3; 4]) -> [Point3; 7] {
+    [
+        cp[0],
+        (cp[0] + cp[1]) / 2.0,
+        (cp[0] + 2.0 * cp[1] + cp[2]) / 4.0,
+        (cp[0] + 3.0 * cp[1] + 3.0 * cp[2] + cp[3]) / 8.0,
+        (cp[1] + 2.0 * cp[2] + cp[3]) / 4.0,
+        (cp[2] + cp[3]) / 2.0,
+        cp[3],
+    ]
+}
+
+/// returns the point on the curve at u and its derivative, by de Casteljau's algorithm
+fn evaluate(cp: &[Point3; 4], u: f64) -> (Point3, Vec3) {
+    let mix = |t: f64, a: Point3, b: Point3| (1.0 - t) * a + t * b;
+    let a = [
+        mix(u, cp[0], cp[1]),
+        mix(u, cp[1], cp[2]),
+        mix(u, cp[2], cp[3]),
+    ];
+    let b = [mix(u, a[0], a[1]), mix(u, a[1], a[2])];
+
+    // the derivative vanishes where control points coincide, so fall back to the chord
+    let derivative = if (b[1] - b[0]).length_squared() > 0.0 {
+        3.0 * (b[1] - b[0])
+    } else {
+        cp[3] - cp[0]
+    };
+    (mix(u, b[0], b[1]), derivative)
+}
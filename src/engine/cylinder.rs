@@ -0,0 +1,166 @@
+// src/engine/cylinder.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{self, Material},
+    onb::Onb,
+    polynomial,
+    ray::Ray,
+    utils::PI,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a capped circular cylinder between two end points
+#[derive(Clone)]
+pub struct Cylinder {
+    base: Point3, // center of the bottom cap
+    frame: Onb,   // local frame whose w axis runs from the bottom cap to the top cap
+    height: f64,  // distance between the caps
+    radius: f64,
+    capped: bool, // whether the end discs are part of the surface
+    mat: Rc<dyn Material>,
+}
+
+impl Cylinder {
+    /// constructs new capped `Cylinder` running from base to top with given radius
+    pub fn new(base: Point3, top: Point3, radius: f64, mat: Rc<dyn Material>) -> Cylinder {
+        let axis = top - base;
+        Cylinder {
+            base,
+            frame: Onb::new(&axis),
+            height: axis.length(),
+            radius: radius.max(0.0),
+            capped: true,
+            mat,
+        }
+    }
+
+    /// returns the center of the cylinder's bottom cap
+    pub fn base(&self) -> Point3 {
+        self.base
+    }
+
+    /// returns the center of the cylinder's top cap
+    pub fn top(&self) -> Point3 {
+        self.base + self.height * self.frame.w()
+    }
+
+    /// returns the cylinder's radius
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// returns whether the cylinder is closed by end caps
+    pub fn is_capped(&self) -> bool {
+        self.capped
+    }
+
+    /// returns the cylinder's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// sets whether the cylinder is closed by end caps, or is an open tube
+    pub fn set_capped(&mut self, capped: bool) {
+        self.capped = capped;
+    }
+
+    /// returns the local space outward normal, uv and tangents at a local point on the side
+    /// or on one of the caps
+    fn surface_at(&self, p: &Point3, on_cap: bool) -> (Vec3, (f64, f64), Vec3, Vec3) {
+        if on_cap {
+            // caps use a planar mapping of the disc onto the unit square
+            let normal = if p.z() < 0.5 * self.height {
+                Vec3::new(0.0, 0.0, -1.0)
+            } else {
+                Vec3::new(0.0, 0.0, 1.0)
+            };
+            let uv = (
+                0.5 * (p.x() / self.radius + 1.0),
+                0.5 * (p.y() / self.radius + 1.0),
+            );
+            let dpdu = Vec3::new(2.0 * self.radius, 0.0, 0.0);
+            let dpdv = Vec3::new(0.0, 2.0 * self.radius, 0.0);
+            return (normal, uv, dpdu, dpdv);
+        }
+
+        // the side wraps u around the axis and runs v from the bottom to the top
+        let normal = Vec3::new(p.x(), p.y(), 0.0) / self.radius;
+        let phi = p.y().atan2(p.x());
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let uv = (phi / (2.0 * PI), p.z() / self.height);
+        let dpdu = 2.0 * PI * Vec3::new(-p.y(), p.x(), 0.0);
+        let dpdv = Vec3::new(0.0, 0.0, self.height);
+        (normal, uv, dpdu, dpdv)
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if self.height <= 0.0 || self.radius <= 0.0 {
+            return false;
+        }
+
+        // move the ray into the local frame, where the axis is +z from the origin
+        let o = self.frame.to_local(&(ray.origin() - self.base));
+        let d = self.frame.to_local(&ray.direction());
+
+        // collect every crossing of the side and caps, then take the nearest acceptable one
+        let mut candidates = Vec::with_capacity(4);
+        if let Some((t0, t1)) = polynomial::solve_quadratic(
+            d.x() * d.x() + d.y() * d.y(),
+            2.0 * (o.x() * d.x() + o.y() * d.y()),
+            o.x() * o.x() + o.y() * o.y() - self.radius * self.radius,
+        ) {
+            for t in [t0, t1] {
+                let z = o.z() + t * d.z();
+                if (0.0..=self.height).contains(&z) {
+                    candidates.push((t, false));
+                }
+            }
+        }
+        if self.capped && d.z() != 0.0 {
+            for z in [0.0, self.height] {
+                let t = (z - o.z()) / d.z();
+                let (x, y) = (o.x() + t * d.x(), o.y() + t * d.y());
+                if x * x + y * y <= self.radius * self.radius {
+                    candidates.push((t, true));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (t, on_cap) in candidates {
+            if !ray_t.contains(t) {
+                continue;
+            }
+            let local_p = o + t * d;
+            let (normal, (u, v), dpdu, dpdv) = self.surface_at(&local_p, on_cap);
+
+            rec.set_t(t);
+            rec.set_p(ray.at(t));
+            rec.set_uv(u, v);
+            rec.set_tangents(self.frame.transform(&dpdu), self.frame.transform(&dpdv));
+            rec.set_face_normal(ray, &vec3::unit_vector(self.frame.transform(&normal)));
+            rec.set_mat(self.mat.clone());
+            if material::passes_alpha_test(self.mat.as_ref(), rec) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let axis = self.frame.w();
+        Aabb::surrounding(
+            &Aabb::from_disc(self.base, axis, self.radius),
+            &Aabb::from_disc(self.top(), axis, self.radius),
+        )
+        .padded()
+    }
+}
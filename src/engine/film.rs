@@ -0,0 +1,116 @@
+// src/engine/film.rs
+
+use crate::engine::{color::Color, filter::Filter};
+
+// smallest total filter weight a pixel counts as covered by, below which negative lobes have
+// cancelled out its samples and dividing by the weight would blow up their noise
+const MIN_WEIGHT: f64 = 1e-6;
+
+/// an image that reconstructs pixels from samples splatted onto every pixel within the filter's
+/// radius, each pixel the filter weighted average of the samples around it
+#[derive(Debug, Clone)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    filter: Filter,
+    sums: Vec<Color>,  // filter weighted sum of the samples splatted onto each pixel
+    weights: Vec<f64>, // sum of the filter weights of those samples
+}
+
+impl Film {
+    /// constructs new black `Film` of given size in pixels, reconstructed with filter
+    pub fn new(width: usize, height: usize, filter: Filter) -> Film {
+        Film {
+            width,
+            height,
+            filter,
+            sums: vec![Color::zero(); width * height],
+            weights: vec![0.0; width * height],
+        }
+    }
+
+    /// returns the width of the film in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// returns the height of the film in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// returns the reconstruction filter of the film
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// adds a sample of color at film point x,y, in pixels from the upper left corner, to every
+    /// pixel whose center lies within the filter's radius
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let radius = self.filter.radius();
+        // pixel i has its center at i + 0.5
+        let x0 = (x - radius - 0.5).ceil().max(0.0) as usize;
+        let y0 = (y - radius - 0.5).ceil().max(0.0) as usize;
+        let x1 = (x + radius - 0.5).floor().min(self.width as f64 - 1.0);
+        let y1 = (y + radius - 0.5).floor().min(self.height as f64 - 1.0);
+        if x1 < 0.0 || y1 < 0.0 {
+            return;
+        }
+
+        for j in y0..=y1 as usize {
+            for i in x0..=x1 as usize {
+                let weight = self
+                    .filter
+                    .evaluate(x - (i as f64 + 0.5), y - (j as f64 + 0.5));
+                if weight != 0.0 {
+                    let index = j * self.width + i;
+                    self.sums[index] += weight * color;
+                    self.weights[index] += weight;
+                }
+            }
+        }
+    }
+
+    /// returns the reconstructed color of pixel i,j, black where the samples' total weight is
+    /// about zero or negative
+    pub fn pixel(&self, i: usize, j: usize) -> Color {
+        let index = j * self.width + i;
+        if self.weights[index] <= MIN_WEIGHT {
+            return Color::zero();
+        }
+        self.sums[index] / self.weights[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_averages_samples_by_filter_weight() {
+        let mut film = Film::new(4, 4, Filter::Tent { radius: 1.5 });
+        let color = Color::new(0.2, 0.4, 0.8);
+        for (x, y) in [(1.2, 1.7), (2.9, 0.4), (0.3, 3.1), (3.6, 2.2)] {
+            film.add_sample(x, y, color);
+        }
+
+        // a single color comes back unchanged wherever samples reach
+        let pixel = film.pixel(1, 1);
+        for (c, expected) in [(pixel.x(), 0.2), (pixel.y(), 0.4), (pixel.z(), 0.8)] {
+            assert!((c - expected).abs() < 1e-12, "{pixel:?}");
+        }
+    }
+
+    #[test]
+    fn pixel_is_black_without_coverage() {
+        let mut film = Film::new(4, 4, Filter::default());
+        film.add_sample(0.5, 0.5, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(film.pixel(3, 3), Color::zero());
+
+        // a sample at the very edge of a tent leaves a weight too small to divide by
+        let mut film = Film::new(4, 4, Filter::Tent { radius: 1.0 });
+        film.add_sample(2.5 - 1e-9, 1.5, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(film.pixel(1, 1), Color::zero());
+        assert!(film.pixel(2, 1).x() > 0.99);
+    }
+}
@@ -0,0 +1,163 @@
+// src/engine/filter.rs
+
+use crate::engine::utils::PI;
+
+/// a pixel reconstruction filter, weighting each sample by its offset from a pixel center
+/// NOTE: radii are in pixels and should be at least 0.5, so every sample reaches a pixel; a box
+/// of radius 0.5 averages the samples within each pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// equal weight over the square of given radius
+    Box { radius: f64 },
+    /// weight falling off linearly to zero at radius
+    Tent { radius: f64 },
+    /// a gaussian of standard deviation sigma, shifted down to reach zero at radius
+    Gaussian { radius: f64, sigma: f64 },
+    /// the mitchell-netravali cubic with parameters b and c, stretched over radius
+    MitchellNetravali { radius: f64, b: f64, c: f64 },
+    /// a sinc windowed by a sinc stretched over radius, so it keeps radius lobes on each side
+    Lanczos { radius: f64 },
+}
+
+impl Default for Filter {
+    /// the box filter over a single pixel
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    /// returns the gaussian filter with the usual standard deviation of half a pixel
+    pub fn gaussian(radius: f64) -> Filter {
+        Filter::Gaussian { radius, sigma: 0.5 }
+    }
+
+    /// returns the mitchell-netravali filter with the recommended b = c = 1/3
+    pub fn mitchell_netravali(radius: f64) -> Filter {
+        Filter::MitchellNetravali {
+            radius,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        }
+    }
+
+    /// returns the distance from a pixel center, along either axis, beyond which samples have
+    /// no weight
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::MitchellNetravali { radius, .. }
+            | Filter::Lanczos { radius } => radius.max(0.0),
+        }
+    }
+
+    /// returns the weight of a sample at offset x,y from a pixel center, which may be negative
+    /// for filters with negative lobes
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let radius = self.radius();
+        // open at the edges so a sample on a pixel border only counts once for a box
+        if x.abs() >= radius || y.abs() >= radius {
+            return 0.0;
+        }
+
+        // all filters are separable
+        let weight_1d = |t: f64| match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { .. } => radius - t.abs(),
+            Filter::Gaussian { sigma, .. } => {
+                (gaussian(t, sigma) - gaussian(radius, sigma)).max(0.0)
+            }
+            Filter::MitchellNetravali { b, c, .. } => mitchell_1d(2.0 * t / radius, b, c),
+            Filter::Lanczos { .. } => sinc(t) * sinc(t / radius),
+        };
+        weight_1d(x) * weight_1d(y)
+    }
+}
+
+/// returns the unnormalized gaussian of standard deviation sigma at x
+fn gaussian(x: f64, sigma: f64) -> f64 {
+    (-x * x / (2.0 * sigma * sigma)).exp()
+}
+
+/// returns the mitchell-netravali cubic at x in [-2,2]
+fn mitchell_1d(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+    let value = if x <= 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)
+    } else if x <= 2.0 {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    value / 6.0
+}
+
+/// returns the normalized sinc, sin(pi x) / (pi x)
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(value: f64, expected: f64) {
+        assert!(
+            (value - expected).abs() < 1e-12,
+            "expected {expected}, got {value}"
+        );
+    }
+
+    #[test]
+    fn filters_vanish_at_their_radius() {
+        for filter in [
+            Filter::Box { radius: 1.5 },
+            Filter::Tent { radius: 1.5 },
+            Filter::gaussian(1.5),
+            Filter::mitchell_netravali(2.0),
+            Filter::Lanczos { radius: 3.0 },
+        ] {
+            let radius = filter.radius();
+            assert_eq!(filter.evaluate(radius, 0.0), 0.0, "{filter:?}");
+            assert_eq!(filter.evaluate(0.0, -radius), 0.0, "{filter:?}");
+            assert!(filter.evaluate(0.0, 0.0) > 0.0, "{filter:?}");
+        }
+        assert_eq!(Filter::Box { radius: -1.0 }.radius(), 0.0);
+    }
+
+    #[test]
+    fn filter_weights_follow_their_profiles() {
+        let filter = Filter::Box { radius: 1.0 };
+        assert_eq!(filter.evaluate(0.9, -0.3), 1.0);
+
+        // separable products of each axis' weight
+        let filter = Filter::Tent { radius: 2.0 };
+        assert_near(filter.evaluate(0.5, 1.0), 1.5 * 1.0);
+
+        let filter = Filter::gaussian(1.0);
+        let axis = |t: f64| (-2.0 * t * t).exp() - (-2.0f64).exp();
+        assert_near(filter.evaluate(0.3, 0.6), axis(0.3) * axis(0.6));
+
+        // the cubic is (6 - 2b) / 6 at the center and b / 6 where its two pieces meet
+        let filter = Filter::mitchell_netravali(2.0);
+        let b: f64 = 1.0 / 3.0;
+        assert_near(filter.evaluate(0.0, 0.0), ((6.0 - 2.0 * b) / 6.0).powi(2));
+        assert_near(filter.evaluate(1.0, 0.0), b / 6.0 * (6.0 - 2.0 * b) / 6.0);
+
+        // lanczos has its zero crossings at every whole pixel and a negative first lobe
+        let filter = Filter::Lanczos { radius: 3.0 };
+        assert_near(filter.evaluate(0.0, 0.0), 1.0);
+        assert!(filter.evaluate(1.0, 0.0).abs() < 1e-12);
+        assert!(filter.evaluate(1.5, 0.0) < 0.0);
+    }
+}
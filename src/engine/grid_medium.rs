@@ -0,0 +1,488 @@
+// src/engine/grid_medium.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{Isotropic, Material},
+    ray::Ray,
+    utils,
+    vec3::{Point3, Vec3},
+};
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+/// a 3d lattice of density samples addressed by integer voxel coordinates
+pub trait DensityGrid {
+    /// returns the number of voxels along x, y and z
+    fn resolution(&self) -> [usize; 3];
+
+    /// returns the density stored at voxel (i,j,k), which is always in range
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64;
+
+    /// returns an upper bound on every voxel's density
+    fn max_density(&self) -> f64;
+
+    /// returns the trilinearly interpolated density at a point in [0,1]^3 grid space
+    /// NOTE: voxel values sit at cell centers, lookups outside the outer centers are clamped
+    fn density_at(&self, uvw: Vec3) -> f64 {
+        let res = self.resolution();
+
+        // continuous voxel coordinates and the lower corner of the surrounding cell
+        let mut base = [0usize; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            let max_index = (res[axis] - 1) as f64;
+            let x = (uvw[axis] * res[axis] as f64 - 0.5).clamp(0.0, max_index);
+            let b = x.floor().min((res[axis].max(2) - 2) as f64);
+            base[axis] = b as usize;
+            frac[axis] = if res[axis] > 1 { x - b } else { 0.0 };
+        }
+
+        let step = |axis: usize| usize::from(res[axis] > 1);
+        let (i0, j0, k0) = (base[0], base[1], base[2]);
+        let (i1, j1, k1) = (i0 + step(0), j0 + step(1), k0 + step(2));
+
+        // interpolate along x, then y, then z
+        let lerp = |a: f64, b: f64, t: f64| (1.0 - t) * a + t * b;
+        let c00 = lerp(self.voxel(i0, j0, k0), self.voxel(i1, j0, k0), frac[0]);
+        let c10 = lerp(self.voxel(i0, j1, k0), self.voxel(i1, j1, k0), frac[0]);
+        let c01 = lerp(self.voxel(i0, j0, k1), self.voxel(i1, j0, k1), frac[0]);
+        let c11 = lerp(self.voxel(i0, j1, k1), self.voxel(i1, j1, k1), frac[0]);
+        let c0 = lerp(c00, c10, frac[1]);
+        let c1 = lerp(c01, c11, frac[1]);
+        lerp(c0, c1, frac[2])
+    }
+}
+
+/// a dense grid storing every voxel, x varying fastest
+#[derive(Debug, Clone)]
+pub struct DenseGrid {
+    resolution: [usize; 3],
+    data: Vec<f32>,
+    max_density: f64,
+}
+
+impl DenseGrid {
+    /// constructs new `DenseGrid` from voxel values laid out x fastest, then y, then z
+    /// returns an error if the data length does not match the resolution or a value is negative
+    /// or NaN
+    pub fn new(nx: usize, ny: usize, nz: usize, data: Vec<f32>) -> io::Result<DenseGrid> {
+        let voxels = nx.checked_mul(ny).and_then(|n| n.checked_mul(nz));
+        if nx == 0 || ny == 0 || nz == 0 || voxels != Some(data.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "grid resolution {}x{}x{} does not match {} voxel values",
+                    nx,
+                    ny,
+                    nz,
+                    data.len()
+                ),
+            ));
+        }
+        if let Some(d) = data.iter().find(|d| d.is_nan() || **d < 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("grid density {} is not a non-negative number", d),
+            ));
+        }
+
+        let max_density = data.iter().fold(0.0_f64, |m, &d| m.max(d as f64));
+        Ok(DenseGrid {
+            resolution: [nx, ny, nz],
+            data,
+            max_density,
+        })
+    }
+
+    /// constructs new `DenseGrid` by evaluating a function at every voxel center in [0,1]^3 grid space
+    pub fn from_fn<F: Fn(Point3) -> f64>(nx: usize, ny: usize, nz: usize, f: F) -> DenseGrid {
+        let (nx, ny, nz) = (nx.max(1), ny.max(1), nz.max(1));
+        let mut data = Vec::with_capacity(nx * ny * nz);
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    let p = Point3::new(
+                        (i as f64 + 0.5) / nx as f64,
+                        (j as f64 + 0.5) / ny as f64,
+                        (k as f64 + 0.5) / nz as f64,
+                    );
+                    data.push(f(p).max(0.0) as f32);
+                }
+            }
+        }
+        // sizes are consistent by construction
+        DenseGrid::new(nx, ny, nz, data).unwrap()
+    }
+
+    /// loads a `DenseGrid` from a raw binary file
+    ///
+    /// the format is three little-endian u32 dimensions (nx, ny, nz) followed by
+    /// nx*ny*nz little-endian f32 densities, x varying fastest
+    pub fn load_raw<P: AsRef<Path>>(path: P) -> io::Result<DenseGrid> {
+        let bytes = std::fs::read(path)?;
+        DenseGrid::from_raw_bytes(&bytes)
+    }
+
+    /// parses a `DenseGrid` from the raw binary format described in `load_raw`
+    pub fn from_raw_bytes(bytes: &[u8]) -> io::Result<DenseGrid> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if bytes.len() < 12 {
+            return Err(invalid("raw grid is missing its 12 byte header"));
+        }
+        let dim = |n: usize| u32::from_le_bytes(bytes[4 * n..4 * n + 4].try_into().unwrap());
+        let (nx, ny, nz) = (dim(0) as usize, dim(1) as usize, dim(2) as usize);
+
+        let body = &bytes[12..];
+        let body_size = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .and_then(|n| n.checked_mul(4));
+        if body_size != Some(body.len()) {
+            return Err(invalid("raw grid body size does not match its header"));
+        }
+        let data = body
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+
+        DenseGrid::new(nx, ny, nz, data)
+    }
+
+    /// writes the grid in the raw binary format described in `load_raw`
+    pub fn write_raw<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for n in self.resolution {
+            writer.write_all(&(n as u32).to_le_bytes())?;
+        }
+        for d in &self.data {
+            writer.write_all(&d.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl DensityGrid for DenseGrid {
+    fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.data[i + nx * (j + ny * k)] as f64
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
+
+// edge length (in voxels) of a sparse grid leaf brick
+const LEAF_DIM: usize = 8;
+const LEAF_VOXELS: usize = LEAF_DIM * LEAF_DIM * LEAF_DIM;
+
+/// a sparse grid in the spirit of NanoVDB: only 8^3 leaf bricks containing density are stored,
+/// every voxel outside an allocated brick reads as zero
+#[derive(Debug, Clone)]
+pub struct SparseGrid {
+    resolution: [usize; 3],
+    leaves: HashMap<[usize; 3], Box<[f32; LEAF_VOXELS]>>,
+    max_density: f64,
+}
+
+impl SparseGrid {
+    /// constructs new empty `SparseGrid` of given resolution
+    /// returns an error if any dimension is zero
+    pub fn new(nx: usize, ny: usize, nz: usize) -> io::Result<SparseGrid> {
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("grid resolution {}x{}x{} has no voxels", nx, ny, nz),
+            ));
+        }
+
+        Ok(SparseGrid {
+            resolution: [nx, ny, nz],
+            leaves: HashMap::new(),
+            max_density: 0.0,
+        })
+    }
+
+    /// constructs new `SparseGrid` from a dense grid, dropping voxels at or below threshold
+    pub fn from_dense(dense: &DenseGrid, threshold: f64) -> SparseGrid {
+        let [nx, ny, nz] = dense.resolution();
+        // a dense grid always has voxels
+        let mut grid = SparseGrid::new(nx, ny, nz).unwrap();
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    let d = dense.voxel(i, j, k);
+                    if d > threshold {
+                        grid.set_voxel(i, j, k, d);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// returns the number of allocated leaf bricks
+    pub fn leaf_count(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// sets the density at voxel (i,j,k), allocating its leaf brick if needed
+    /// NOTE: writes outside the grid resolution are ignored
+    pub fn set_voxel(&mut self, i: usize, j: usize, k: usize, density: f64) {
+        let [nx, ny, nz] = self.resolution;
+        if i >= nx || j >= ny || k >= nz {
+            return;
+        }
+
+        let (key, offset) = SparseGrid::locate(i, j, k);
+        let leaf = self
+            .leaves
+            .entry(key)
+            .or_insert_with(|| Box::new([0.0; LEAF_VOXELS]));
+        leaf[offset] = density as f32;
+        self.max_density = self.max_density.max(density);
+    }
+
+    /// returns the leaf brick key and the offset within that brick of voxel (i,j,k)
+    fn locate(i: usize, j: usize, k: usize) -> ([usize; 3], usize) {
+        let key = [i / LEAF_DIM, j / LEAF_DIM, k / LEAF_DIM];
+        let (li, lj, lk) = (i % LEAF_DIM, j % LEAF_DIM, k % LEAF_DIM);
+        (key, li + LEAF_DIM * (lj + LEAF_DIM * lk))
+    }
+}
+
+impl DensityGrid for SparseGrid {
+    fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64 {
+        let (key, offset) = SparseGrid::locate(i, j, k);
+        self.leaves
+            .get(&key)
+            .map_or(0.0, |leaf| leaf[offset] as f64)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
+
+/// a heterogeneous participating medium whose density comes from a grid stretched over a box
+pub struct GridMedium<G: DensityGrid> {
+    bounds: Aabb,
+    grid: G,
+    density_scale: f64, // multiplies every grid value to give the extinction coefficient
+    phase_function: Rc<dyn Material>,
+}
+
+impl<G: DensityGrid> GridMedium<G> {
+    /// constructs new `GridMedium` with an isotropic phase function of given albedo
+    pub fn new(bounds: Aabb, grid: G, density_scale: f64, albedo: Color) -> GridMedium<G> {
+        GridMedium::new_with_phase(bounds, grid, density_scale, Rc::new(Isotropic::new(albedo)))
+    }
+
+    /// constructs new `GridMedium` with an arbitrary phase function
+    pub fn new_with_phase(
+        bounds: Aabb,
+        grid: G,
+        density_scale: f64,
+        phase_function: Rc<dyn Material>,
+    ) -> GridMedium<G> {
+        GridMedium {
+            bounds,
+            grid,
+            density_scale,
+            phase_function,
+        }
+    }
+
+    /// returns the medium's bounding box
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// returns the medium's density grid
+    pub fn grid(&self) -> &G {
+        &self.grid
+    }
+
+    /// returns the extinction coefficient at a world space point
+    pub fn density(&self, p: Point3) -> f64 {
+        let min = self.bounds.min();
+        let size = self.bounds.max() - min;
+        let uvw = Vec3::new(
+            (p.x() - min.x()) / size.x(),
+            (p.y() - min.y()) / size.y(),
+            (p.z() - min.z()) / size.z(),
+        );
+        self.density_scale * self.grid.density_at(uvw)
+    }
+
+    /// returns the majorant: an upper bound on the extinction coefficient anywhere in the medium
+    fn majorant(&self) -> f64 {
+        self.density_scale * self.grid.max_density()
+    }
+}
+
+impl<G: DensityGrid> Hittable for GridMedium<G> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return false;
+        }
+        let span = match self.bounds.hit_span(ray, ray_t) {
+            Some(span) => span,
+            None => return false,
+        };
+
+        // delta tracking: take tentative steps through a homogenized medium of majorant density
+        // and accept each as a real collision with probability density / majorant
+        let ray_length = ray.direction().length();
+        let mut t = span.min.max(0.0);
+        loop {
+            t -= (1.0 - utils::random_0_to_1()).ln() / (majorant * ray_length);
+            if t >= span.max {
+                return false;
+            }
+            if utils::random_0_to_1() * majorant < self.density(ray.at(t)) {
+                break;
+            }
+        }
+
+        rec.set_t(t);
+        rec.set_p(ray.at(t));
+
+        // normal and facing are meaningless inside a medium
+        rec.set_normal(Vec3::new(1.0, 0.0, 0.0));
+        rec.set_front_face(true);
+        rec.set_mat(self.phase_function.clone());
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// estimates the transmittance along the ray within ray_t with ratio tracking, which scales
+    /// it by 1 - density / majorant at each tentative collision instead of stopping at a real one
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+        let majorant = self.majorant();
+        let span = match self.bounds.hit_span(ray, ray_t) {
+            Some(span) => span,
+            None => return 1.0,
+        };
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+
+        let ray_length = ray.direction().length();
+        let mut t = span.min;
+        let mut transmittance = 1.0;
+        loop {
+            t -= (1.0 - utils::random_0_to_1()).ln() / (majorant * ray_length);
+            if t >= span.max {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.density(ray.at(t)) / majorant;
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// returns the raw grid bytes for given header dimensions and densities
+    fn raw_bytes(dims: [u32; 3], data: &[f32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for n in dims {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        for d in data {
+            bytes.extend_from_slice(&d.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// returns whether the result is an invalid data error
+    fn is_invalid_data<T>(result: io::Result<T>) -> bool {
+        matches!(result, Err(e) if e.kind() == io::ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn raw_bytes_round_trip() {
+        let data = [0.0, 0.5, 1.0, 2.0, 0.25, 0.75];
+        let grid = DenseGrid::from_raw_bytes(&raw_bytes([3, 2, 1], &data)).unwrap();
+        assert_eq!(grid.resolution(), [3, 2, 1]);
+        assert_eq!(grid.voxel(1, 1, 0), 0.25);
+        assert_eq!(grid.max_density(), 2.0);
+
+        let mut written = Vec::new();
+        grid.write_raw(&mut written).unwrap();
+        assert_eq!(written, raw_bytes([3, 2, 1], &data));
+    }
+
+    #[test]
+    fn raw_bytes_without_full_header_are_rejected() {
+        assert!(is_invalid_data(DenseGrid::from_raw_bytes(&[])));
+        assert!(is_invalid_data(DenseGrid::from_raw_bytes(&[
+            1, 0, 0, 0, 1, 0, 0, 0
+        ])));
+    }
+
+    #[test]
+    fn raw_bytes_with_wrong_body_length_are_rejected() {
+        assert!(is_invalid_data(DenseGrid::from_raw_bytes(&raw_bytes(
+            [2, 2, 2],
+            &[1.0; 7]
+        ))));
+        assert!(is_invalid_data(DenseGrid::from_raw_bytes(&raw_bytes(
+            [2, 2, 2],
+            &[1.0; 9]
+        ))));
+        assert!(is_invalid_data(DenseGrid::from_raw_bytes(&raw_bytes(
+            [0, 2, 2],
+            &[]
+        ))));
+    }
+
+    #[test]
+    fn raw_bytes_with_overflowing_dimensions_are_rejected() {
+        let dims = [u32::MAX, u32::MAX, u32::MAX];
+        assert!(is_invalid_data(DenseGrid::from_raw_bytes(&raw_bytes(
+            dims, &[1.0; 4]
+        ))));
+    }
+
+    #[test]
+    fn negative_and_nan_densities_are_rejected() {
+        assert!(is_invalid_data(DenseGrid::new(2, 1, 1, vec![1.0, -0.5])));
+        assert!(is_invalid_data(DenseGrid::new(
+            2,
+            1,
+            1,
+            vec![f32::NAN, 1.0]
+        )));
+    }
+
+    #[test]
+    fn sparse_grid_without_voxels_is_rejected() {
+        assert!(is_invalid_data(SparseGrid::new(4, 0, 4)));
+        assert!(SparseGrid::new(4, 1, 4).is_ok());
+    }
+}
@@ -0,0 +1,479 @@
+// src/engine/hair.rs
+
+use crate::engine::{
+    color::{self, Color},
+    hittable::HitRecord,
+    material::Material,
+    microfacet,
+    onb::Onb,
+    ray::Ray,
+    utils::{self, PI},
+    vec3::{self, Vec3},
+};
+
+// number of scattering lobes modelled explicitly (R, TT and TRT), the rest are folded into one
+const P_MAX: usize = 3;
+
+// sqrt(pi/8), relating the azimuthal roughness to the logistic distribution's scale
+const SQRT_PI_OVER_8: f64 = 0.626_657_068_657_750_1;
+
+/// a hair fiber bsdf after Chiang et al. 2016: a rough dielectric cylinder with an absorbing
+/// interior and tilted cuticle scales, scattering light into lobes that leave the fiber after
+/// reflecting off it (R), passing through it (TT) or reflecting once inside it (TRT)
+///
+/// the local frame's x axis runs along the fiber and the hit's v coordinate gives the offset
+/// across it, from 0 on one edge to 1 on the other
+/// NOTE: the bsdf accounts for the fiber's whole cross section, so suits flat curves best
+#[derive(Debug, Clone)]
+pub struct Hair {
+    sigma_a: Color,         // absorption coefficient of the interior, per unit of diameter
+    eta: f64,               // index of refraction, 1.55 for keratin
+    beta_m: f64,            // longitudinal roughness in [0,1]
+    beta_n: f64,            // azimuthal roughness in [0,1]
+    alpha: f64,             // tilt of the cuticle scales in degrees, typically 2
+    v: [f64; P_MAX + 1],    // longitudinal variance of each lobe
+    s: f64,                 // scale of the azimuthal logistic distribution
+    sin_2k_alpha: [f64; 3], // sin(2^k alpha), shifting the lobes by the scale tilt
+    cos_2k_alpha: [f64; 3], // cos(2^k alpha)
+}
+
+impl Hair {
+    /// constructs new `Hair` bsdf of given interior absorption, longitudinal and azimuthal
+    /// roughness and cuticle scale tilt in degrees
+    /// NOTE: roughnesses are clamped to [0.01,1], where the lobe model is well behaved
+    pub fn new(sigma_a: Color, beta_m: f64, beta_n: f64, alpha: f64) -> Hair {
+        let beta_m = beta_m.clamp(0.01, 1.0);
+        let beta_n = beta_n.clamp(0.01, 1.0);
+
+        // the TT lobe is narrower and the TRT lobe wider than the R lobe
+        let v0 = (0.726 * beta_m + 0.812 * beta_m.powi(2) + 3.7 * beta_m.powi(20)).powi(2);
+        let s =
+            SQRT_PI_OVER_8 * (0.265 * beta_n + 1.194 * beta_n.powi(2) + 5.372 * beta_n.powi(22));
+
+        let mut sin_2k_alpha = [utils::deg_to_rad(alpha).sin(), 0.0, 0.0];
+        let mut cos_2k_alpha = [safe_sqrt(1.0 - sin_2k_alpha[0].powi(2)), 0.0, 0.0];
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1].powi(2) - sin_2k_alpha[i - 1].powi(2);
+        }
+
+        Hair {
+            sigma_a,
+            eta: 1.55,
+            beta_m,
+            beta_n,
+            alpha,
+            v: [v0, 0.25 * v0, 4.0 * v0, 4.0 * v0],
+            s,
+            sin_2k_alpha,
+            cos_2k_alpha,
+        }
+    }
+
+    /// constructs new `Hair` bsdf whose absorption comes from concentrations of the dark brown
+    /// eumelanin and the reddish pheomelanin pigments, e.g. 0.3 for blonde and 8 for black hair
+    pub fn from_melanin(
+        eumelanin: f64,
+        pheomelanin: f64,
+        beta_m: f64,
+        beta_n: f64,
+        alpha: f64,
+    ) -> Hair {
+        let sigma_a = eumelanin.max(0.0) * Color::new(0.419, 0.697, 1.37)
+            + pheomelanin.max(0.0) * Color::new(0.187, 0.4, 1.05);
+        Hair::new(sigma_a, beta_m, beta_n, alpha)
+    }
+
+    /// constructs new `Hair` bsdf whose absorption is chosen so that a fiber in a full head of
+    /// hair appears of roughly the given color
+    pub fn from_color(color: Color, beta_m: f64, beta_n: f64, alpha: f64) -> Hair {
+        let b = beta_n.clamp(0.01, 1.0);
+        let denominator = 5.969 - 0.215 * b + 2.532 * b.powi(2) - 10.73 * b.powi(3)
+            + 5.574 * b.powi(4)
+            + 0.245 * b.powi(5);
+        let sigma = |c: f64| (c.clamp(1e-4, 1.0).ln() / denominator).powi(2);
+        let sigma_a = Color::new(sigma(color.x()), sigma(color.y()), sigma(color.z()));
+        Hair::new(sigma_a, beta_m, beta_n, alpha)
+    }
+
+    /// sets the fiber's index of refraction
+    pub fn set_eta(&mut self, eta: f64) {
+        self.eta = eta.max(1.0 + 1e-6);
+    }
+
+    /// returns the absorption coefficient of the fiber's interior
+    pub fn sigma_a(&self) -> Color {
+        self.sigma_a
+    }
+
+    /// returns the fiber's index of refraction
+    pub fn eta(&self) -> f64 {
+        self.eta
+    }
+
+    /// returns the longitudinal roughness
+    pub fn beta_m(&self) -> f64 {
+        self.beta_m
+    }
+
+    /// returns the azimuthal roughness
+    pub fn beta_n(&self) -> f64 {
+        self.beta_n
+    }
+
+    /// returns the tilt of the cuticle scales in degrees
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// returns the shading frame at the hit, its x axis along the fiber, and the offset h in
+    /// [-1,1] of the hit across the fiber
+    fn frame(rec: &HitRecord) -> (Onb, f64) {
+        let h = (2.0 * rec.v() - 1.0).clamp(-1.0 + 1e-9, 1.0 - 1e-9);
+        (Onb::from_normal_tangent(&rec.normal(), &rec.dpdu()), h)
+    }
+
+    /// returns the outgoing elevation sine and cosine tilted by the cuticle scales for lobe p
+    fn tilted(&self, p: usize, sin_theta_o: f64, cos_theta_o: f64) -> (f64, f64) {
+        let (sin, cos) = (&self.sin_2k_alpha, &self.cos_2k_alpha);
+        match p {
+            0 => (
+                sin_theta_o * cos[1] - cos_theta_o * sin[1],
+                cos_theta_o * cos[1] + sin_theta_o * sin[1],
+            ),
+            1 => (
+                sin_theta_o * cos[0] + cos_theta_o * sin[0],
+                cos_theta_o * cos[0] - sin_theta_o * sin[0],
+            ),
+            2 => (
+                sin_theta_o * cos[2] + cos_theta_o * sin[2],
+                cos_theta_o * cos[2] - sin_theta_o * sin[2],
+            ),
+            _ => (sin_theta_o, cos_theta_o),
+        }
+    }
+
+    /// returns the refracted azimuthal offset angle gamma_t inside the fiber and the fraction of
+    /// light transmitted along a single crossing of it
+    fn refracted(&self, sin_theta_o: f64, cos_theta_o: f64, h: f64) -> (f64, Color) {
+        let sin_theta_t = sin_theta_o / self.eta;
+        let cos_theta_t = safe_sqrt(1.0 - sin_theta_t * sin_theta_t);
+
+        // the modified index of refraction for the projection onto the fiber's cross section
+        let etap = (self.eta * self.eta - sin_theta_o * sin_theta_o).sqrt() / cos_theta_o;
+        let sin_gamma_t = h / etap;
+        let cos_gamma_t = safe_sqrt(1.0 - sin_gamma_t * sin_gamma_t);
+
+        let distance = 2.0 * cos_gamma_t / cos_theta_t;
+        let transmittance = Color::new(
+            (-self.sigma_a.x() * distance).exp(),
+            (-self.sigma_a.y() * distance).exp(),
+            (-self.sigma_a.z() * distance).exp(),
+        );
+        (safe_asin(sin_gamma_t), transmittance)
+    }
+
+    /// returns the attenuation of each lobe by fresnel reflection and absorption
+    fn attenuations(&self, cos_theta_o: f64, h: f64, transmittance: Color) -> [Color; P_MAX + 1] {
+        let cos_gamma_o = safe_sqrt(1.0 - h * h);
+        let f = microfacet::fresnel_dielectric(cos_theta_o * cos_gamma_o, self.eta);
+
+        let r = Color::new(f, f, f);
+        let tt = (1.0 - f) * (1.0 - f) * transmittance;
+        let trt = f * tt * transmittance;
+        // the geometric series of every longer path
+        let remainder = |i: usize| {
+            let tf = f * transmittance[i];
+            if tf < 1.0 {
+                trt[i] * tf / (1.0 - tf)
+            } else {
+                0.0
+            }
+        };
+        [
+            r,
+            tt,
+            trt,
+            Color::new(remainder(0), remainder(1), remainder(2)),
+        ]
+    }
+
+    /// returns the probability of sampling each lobe, proportional to its luminance
+    fn lobe_probabilities(&self, sin_theta_o: f64, cos_theta_o: f64, h: f64) -> [f64; P_MAX + 1] {
+        let (_, transmittance) = self.refracted(sin_theta_o, cos_theta_o, h);
+        let attenuations = self.attenuations(cos_theta_o, h, transmittance);
+        let total: f64 = attenuations.iter().map(color::luminance).sum();
+        if total <= 0.0 {
+            return [1.0, 0.0, 0.0, 0.0];
+        }
+        attenuations.map(|a| color::luminance(&a) / total)
+    }
+
+    /// returns the bsdf times the cosine term for local directions wo and wi
+    fn eval_local(&self, wo: &Vec3, wi: &Vec3, h: f64) -> Color {
+        let (sin_theta_o, cos_theta_o, phi_o) = angles(wo);
+        let (sin_theta_i, cos_theta_i, phi_i) = angles(wi);
+        if cos_theta_o <= 0.0 {
+            return Color::zero();
+        }
+
+        let (gamma_t, transmittance) = self.refracted(sin_theta_o, cos_theta_o, h);
+        let gamma_o = safe_asin(h);
+        let attenuations = self.attenuations(cos_theta_o, h, transmittance);
+        let phi = phi_i - phi_o;
+
+        let mut f = Color::zero();
+        for (p, attenuation) in attenuations.iter().enumerate().take(P_MAX) {
+            let (sin_theta_op, cos_theta_op) = self.tilted(p, sin_theta_o, cos_theta_o);
+            let longitudinal = mp(
+                cos_theta_i,
+                cos_theta_op.abs(),
+                sin_theta_i,
+                sin_theta_op,
+                self.v[p],
+            );
+            f += longitudinal * np(phi, p, self.s, gamma_o, gamma_t) * *attenuation;
+        }
+
+        // the remaining lobes are spread evenly around the fiber
+        let longitudinal = mp(
+            cos_theta_i,
+            cos_theta_o,
+            sin_theta_i,
+            sin_theta_o,
+            self.v[P_MAX],
+        );
+        f + longitudinal / (2.0 * PI) * attenuations[P_MAX]
+    }
+
+    /// returns the solid angle pdf of `sample_local` producing local direction wi
+    fn pdf_local(&self, wo: &Vec3, wi: &Vec3, h: f64) -> f64 {
+        let (sin_theta_o, cos_theta_o, phi_o) = angles(wo);
+        let (sin_theta_i, cos_theta_i, phi_i) = angles(wi);
+        if cos_theta_o <= 0.0 {
+            return 0.0;
+        }
+
+        let (gamma_t, _) = self.refracted(sin_theta_o, cos_theta_o, h);
+        let gamma_o = safe_asin(h);
+        let probabilities = self.lobe_probabilities(sin_theta_o, cos_theta_o, h);
+        let phi = phi_i - phi_o;
+
+        let mut pdf = 0.0;
+        for (p, probability) in probabilities.iter().enumerate().take(P_MAX) {
+            let (sin_theta_op, cos_theta_op) = self.tilted(p, sin_theta_o, cos_theta_o);
+            let longitudinal = mp(
+                cos_theta_i,
+                cos_theta_op.abs(),
+                sin_theta_i,
+                sin_theta_op,
+                self.v[p],
+            );
+            pdf += probability * longitudinal * np(phi, p, self.s, gamma_o, gamma_t);
+        }
+
+        let longitudinal = mp(
+            cos_theta_i,
+            cos_theta_o,
+            sin_theta_i,
+            sin_theta_o,
+            self.v[P_MAX],
+        );
+        pdf + probabilities[P_MAX] * longitudinal / (2.0 * PI)
+    }
+
+    /// samples a local direction wi by picking a lobe, then its elevation and azimuth
+    fn sample_local(&self, wo: &Vec3, h: f64) -> Vec3 {
+        let (sin_theta_o, cos_theta_o, phi_o) = angles(wo);
+
+        let probabilities = self.lobe_probabilities(sin_theta_o, cos_theta_o, h);
+        let mut u = utils::random_0_to_1();
+        let mut p = P_MAX;
+        for (lobe, probability) in probabilities.iter().enumerate().take(P_MAX) {
+            if u < *probability {
+                p = lobe;
+                break;
+            }
+            u -= probability;
+        }
+
+        // elevation around the mirror direction of the tilted outgoing elevation
+        let (sin_theta_op, cos_theta_op) = self.tilted(p, sin_theta_o, cos_theta_o);
+        let u1 = utils::random_0_to_1().max(1e-5);
+        let cos_theta = 1.0 + self.v[p] * (u1 + (1.0 - u1) * (-2.0 / self.v[p]).exp()).ln();
+        let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+        let cos_phi = (2.0 * PI * utils::random_0_to_1()).cos();
+        let sin_theta_i = -cos_theta * sin_theta_op + sin_theta * cos_phi * cos_theta_op;
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+
+        // azimuth around the lobe's exit direction
+        let (gamma_t, _) = self.refracted(sin_theta_o, cos_theta_o, h);
+        let gamma_o = safe_asin(h);
+        let u2 = utils::random_0_to_1();
+        let dphi = if p < P_MAX {
+            exit_azimuth(p, gamma_o, gamma_t) + sample_trimmed_logistic(u2, self.s, -PI, PI)
+        } else {
+            2.0 * PI * u2
+        };
+        let phi_i = phi_o + dphi;
+
+        Vec3::new(
+            sin_theta_i,
+            cos_theta_i * phi_i.cos(),
+            cos_theta_i * phi_i.sin(),
+        )
+    }
+}
+
+impl Material for Hair {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let (uvw, h) = Hair::frame(rec);
+        let wo = uvw.to_local(&-vec3::unit_vector(r_in.direction()));
+        let wi = self.sample_local(&wo, h);
+
+        // weight by the full mixture of lobes, so every lobe that could produce wi counts
+        let pdf = self.pdf_local(&wo, &wi, h);
+        if pdf <= 0.0 {
+            return false;
+        }
+
+        *scattered = Ray::new_with_time(rec.p(), uvw.transform(&wi), r_in.time());
+        *attenuation = self.eval_local(&wo, &wi, h) / pdf;
+        color::luminance(attenuation) > 0.0
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        let (uvw, h) = Hair::frame(rec);
+        let wo = uvw.to_local(&-vec3::unit_vector(r_in.direction()));
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        self.eval_local(&wo, &wi, h)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let (uvw, h) = Hair::frame(rec);
+        let wo = uvw.to_local(&-vec3::unit_vector(r_in.direction()));
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        self.pdf_local(&wo, &wi, h)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+}
+
+/// returns the sine and cosine of a local direction's elevation from the plane normal to the
+/// fiber, and its azimuth around the fiber
+fn angles(w: &Vec3) -> (f64, f64, f64) {
+    let sin_theta = w.x();
+    (
+        sin_theta,
+        safe_sqrt(1.0 - sin_theta * sin_theta),
+        w.z().atan2(w.y()),
+    )
+}
+
+/// returns sqrt(x), treating slightly negative x from rounding as zero
+fn safe_sqrt(x: f64) -> f64 {
+    x.max(0.0).sqrt()
+}
+
+/// returns asin(x), clamping x to [-1,1]
+fn safe_asin(x: f64) -> f64 {
+    x.clamp(-1.0, 1.0).asin()
+}
+
+/// returns the modified bessel function of the first kind of order zero, by its series
+fn i0(x: f64) -> f64 {
+    let mut value = 0.0;
+    let mut x2i = 1.0;
+    let mut factorial = 1.0;
+    let mut four_i = 1.0;
+    for i in 0..10 {
+        if i > 1 {
+            factorial *= i as f64;
+        }
+        value += x2i / (four_i * factorial * factorial);
+        x2i *= x * x;
+        four_i *= 4.0;
+    }
+    value
+}
+
+/// returns ln(i0(x)), switching to an asymptotic expansion where i0 would overflow
+fn log_i0(x: f64) -> f64 {
+    if x > 12.0 {
+        x + 0.5 * (-(2.0 * PI).ln() + (1.0 / x).ln() + 1.0 / (8.0 * x))
+    } else {
+        i0(x).ln()
+    }
+}
+
+/// returns the longitudinal scattering function for elevations theta_i and theta_o
+fn mp(cos_theta_i: f64, cos_theta_o: f64, sin_theta_i: f64, sin_theta_o: f64, v: f64) -> f64 {
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+    if v <= 0.1 {
+        // evaluated in log space, where the terms would otherwise overflow
+        (log_i0(a) - b - 1.0 / v + std::f64::consts::LN_2 + (1.0 / (2.0 * v)).ln()).exp()
+    } else {
+        ((-b).exp() * i0(a)) / ((1.0 / v).sinh() * 2.0 * v)
+    }
+}
+
+/// returns the azimuthal angle by which lobe p leaves the fiber, relative to the incident one
+fn exit_azimuth(p: usize, gamma_o: f64, gamma_t: f64) -> f64 {
+    2.0 * p as f64 * gamma_t - 2.0 * gamma_o + p as f64 * PI
+}
+
+/// returns the logistic distribution's density of scale s at x
+fn logistic(x: f64, s: f64) -> f64 {
+    let x = x.abs();
+    (-x / s).exp() / (s * (1.0 + (-x / s).exp()).powi(2))
+}
+
+/// returns the logistic distribution's cumulative distribution of scale s at x
+fn logistic_cdf(x: f64, s: f64) -> f64 {
+    1.0 / (1.0 + (-x / s).exp())
+}
+
+/// returns the density at x of the logistic distribution of scale s restricted to [a,b]
+fn trimmed_logistic(x: f64, s: f64, a: f64, b: f64) -> f64 {
+    logistic(x, s) / (logistic_cdf(b, s) - logistic_cdf(a, s))
+}
+
+/// samples the logistic distribution of scale s restricted to [a,b] by inverting its cdf
+fn sample_trimmed_logistic(u: f64, s: f64, a: f64, b: f64) -> f64 {
+    let k = logistic_cdf(b, s) - logistic_cdf(a, s);
+    let x = -s * (1.0 / (u * k + logistic_cdf(a, s)) - 1.0).ln();
+    x.clamp(a, b)
+}
+
+/// returns the azimuthal scattering function of lobe p at relative azimuth phi
+fn np(phi: f64, p: usize, s: f64, gamma_o: f64, gamma_t: f64) -> f64 {
+    let dphi = (phi - exit_azimuth(p, gamma_o, gamma_t) + PI).rem_euclid(2.0 * PI) - PI;
+    trimmed_logistic(dphi, s, -PI, PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::material::tests::{
+        assert_attenuation_matches_eval, assert_pdf_matches_sampling, hit_record, incoming_ray,
+    };
+
+    #[test]
+    fn samples_match_eval_and_pdf_across_the_fiber() {
+        let hair = Hair::new(Color::new(0.3, 0.6, 1.2), 0.3, 0.3, 2.0);
+        for v in [0.1, 0.5, 0.85] {
+            let rec = hit_record(true, v);
+            assert_attenuation_matches_eval(&hair, &incoming_ray(), &rec);
+            assert_pdf_matches_sampling(&hair, &incoming_ray(), &rec);
+        }
+    }
+}
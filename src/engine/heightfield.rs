@@ -0,0 +1,419 @@
+// src/engine/heightfield.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    color,
+    hittable::{HitRecord, Hittable},
+    image::Image,
+    interval::Interval,
+    material::{self, Material},
+    ray::Ray,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+/// a terrain surface over a regular grid of height samples, each cell split into two triangles
+///
+/// the triangles are never stored: rays descend a min-max quadtree over the cells (a min-max
+/// mipmap) and only test the triangles of the few cells whose height range they pass through
+pub struct Heightfield {
+    resolution: [usize; 2],     // number of samples along x and z
+    heights: Vec<f32>,          // x varying fastest, then z
+    levels: Vec<Vec<[f32; 2]>>, // min and max height of each 2^k by 2^k cell block, k = 1, 2, ...
+    corner: Point3,             // world position of sample (0,0) at height zero
+    size: Vec3,                 // extent along x and z, and world height of a unit sample
+    bbox: Aabb,
+    mat: Rc<dyn Material>,
+}
+
+impl Heightfield {
+    /// constructs new `Heightfield` from heights laid out x fastest, then z, stretched from corner
+    /// over size.x by size.z with each height scaled by size.y
+    /// returns an error if there are fewer than 2x2 samples or the data length does not match
+    pub fn new(
+        nx: usize,
+        nz: usize,
+        heights: Vec<f32>,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> io::Result<Heightfield> {
+        if nx < 2 || nz < 2 || heights.len() != nx * nz {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "heightfield resolution {}x{} does not match {} height values",
+                    nx,
+                    nz,
+                    heights.len()
+                ),
+            ));
+        }
+
+        let size = Vec3::new(size.x().abs(), size.y().abs(), size.z().abs());
+        let mut heightfield = Heightfield {
+            resolution: [nx, nz],
+            heights,
+            levels: Vec::new(),
+            corner,
+            size,
+            bbox: Aabb::empty(),
+            mat,
+        };
+        heightfield.build_levels();
+
+        let [min, max] = heightfield.node_range(heightfield.levels.len(), 0, 0);
+        heightfield.bbox = Aabb::from_points(
+            corner + Vec3::new(0.0, min as f64 * size.y(), 0.0),
+            corner + Vec3::new(size.x(), max as f64 * size.y(), size.z()),
+        )
+        .padded();
+        Ok(heightfield)
+    }
+
+    /// constructs new `Heightfield` by evaluating a height function at every sample in [0,1]^2
+    pub fn from_fn<F: Fn(f64, f64) -> f64>(
+        nx: usize,
+        nz: usize,
+        f: F,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> Heightfield {
+        let (nx, nz) = (nx.max(2), nz.max(2));
+        let mut heights = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                heights.push(f(i as f64 / (nx - 1) as f64, j as f64 / (nz - 1) as f64) as f32);
+            }
+        }
+        // sizes are consistent by construction
+        Heightfield::new(nx, nz, heights, corner, size, mat).unwrap()
+    }
+
+    /// constructs new `Heightfield` with one sample per pixel, its height the pixel's luminance
+    /// NOTE: the image's top row lies along corner.z, matching the uv given to hits
+    pub fn from_image(
+        image: &Image,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> io::Result<Heightfield> {
+        let (nx, nz) = (image.width(), image.height());
+        let mut heights = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                heights.push(color::luminance(&image.pixel(i as i64, j as i64)) as f32);
+            }
+        }
+        Heightfield::new(nx, nz, heights, corner, size, mat)
+    }
+
+    /// loads a `Heightfield` from a ppm height map, as in `from_image`
+    pub fn load_ppm<P: AsRef<Path>>(
+        path: P,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> io::Result<Heightfield> {
+        Heightfield::from_image(&Image::load_ppm(path)?, corner, size, mat)
+    }
+
+    /// returns the number of samples along x and z
+    pub fn resolution(&self) -> [usize; 2] {
+        self.resolution
+    }
+
+    /// returns the unscaled height of sample (i,j)
+    pub fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[i + self.resolution[0] * j] as f64
+    }
+
+    /// returns the heightfield's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// returns the number of cells along x and z
+    fn cells(&self) -> [usize; 2] {
+        [self.resolution[0] - 1, self.resolution[1] - 1]
+    }
+
+    /// returns the number of nodes along x and z at quadtree level k
+    fn level_size(&self, k: usize) -> [usize; 2] {
+        let [cx, cz] = self.cells();
+        [cx.div_ceil(1 << k), cz.div_ceil(1 << k)]
+    }
+
+    /// builds the min-max quadtree bottom up until a single node covers the whole grid
+    /// NOTE: single cells are bounded on the fly from their corners, so the tree costs about
+    /// two thirds of a height per cell
+    fn build_levels(&mut self) {
+        while self.level_size(self.levels.len()) != [1, 1] {
+            let k = self.levels.len() + 1;
+            let [w, d] = self.level_size(k);
+            let mut level = Vec::with_capacity(w * d);
+            for j in 0..d {
+                for i in 0..w {
+                    level.push(self.children_range(k, i, j));
+                }
+            }
+            self.levels.push(level);
+        }
+    }
+
+    /// returns the min and max height over the children of node (i,j) at level k
+    fn children_range(&self, k: usize, i: usize, j: usize) -> [f32; 2] {
+        let [w, d] = self.level_size(k - 1);
+        let mut range = [f32::INFINITY, f32::NEG_INFINITY];
+        for cj in (2 * j)..(2 * j + 2).min(d) {
+            for ci in (2 * i)..(2 * i + 2).min(w) {
+                let [min, max] = self.node_range(k - 1, ci, cj);
+                range = [range[0].min(min), range[1].max(max)];
+            }
+        }
+        range
+    }
+
+    /// returns the min and max height of node (i,j) at level k, level 0 being single cells
+    fn node_range(&self, k: usize, i: usize, j: usize) -> [f32; 2] {
+        if k == 0 {
+            let nx = self.resolution[0];
+            let base = i + nx * j;
+            let corners = [
+                self.heights[base],
+                self.heights[base + 1],
+                self.heights[base + nx],
+                self.heights[base + nx + 1],
+            ];
+            return corners
+                .iter()
+                .fold([f32::INFINITY, f32::NEG_INFINITY], |r, &h| {
+                    [r[0].min(h), r[1].max(h)]
+                });
+        }
+        self.levels[k - 1][i + self.level_size(k)[0] * j]
+    }
+
+    /// returns the world position of sample (i,j)
+    fn sample_point(&self, i: usize, j: usize) -> Point3 {
+        let [cx, cz] = self.cells();
+        self.corner
+            + Vec3::new(
+                i as f64 / cx as f64 * self.size.x(),
+                self.height(i, j) * self.size.y(),
+                j as f64 / cz as f64 * self.size.z(),
+            )
+    }
+
+    /// returns the world space box of node (i,j) at level k
+    fn node_box(&self, k: usize, i: usize, j: usize) -> Aabb {
+        let [cx, cz] = self.cells();
+        let [min, max] = self.node_range(k, i, j);
+        let (x0, x1) = ((i << k).min(cx), ((i + 1) << k).min(cx));
+        let (z0, z1) = ((j << k).min(cz), ((j + 1) << k).min(cz));
+        let a = Point3::new(
+            x0 as f64 / cx as f64 * self.size.x(),
+            min as f64 * self.size.y(),
+            z0 as f64 / cz as f64 * self.size.z(),
+        );
+        let b = Point3::new(
+            x1 as f64 / cx as f64 * self.size.x(),
+            max as f64 * self.size.y(),
+            z1 as f64 / cz as f64 * self.size.z(),
+        );
+        // widen slightly so rays grazing a node's faces still reach its triangles
+        Aabb::from_points(self.corner + a, self.corner + b)
+            .padded()
+            .expand(1e-9 * self.size.length())
+    }
+
+    /// intersects the ray with node (i,j) at level k and its descendants, keeping the closest
+    /// accepted hit in rec and shrinking ray_t to it
+    fn hit_node(
+        &self,
+        ray: &Ray,
+        k: usize,
+        i: usize,
+        j: usize,
+        ray_t: &mut Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        if !self.node_box(k, i, j).hit(ray, *ray_t) {
+            return false;
+        }
+        if k == 0 {
+            return self.hit_cell(ray, i, j, ray_t, rec);
+        }
+
+        // visit the children front to back, so later ones are usually culled by the closer hit
+        let [w, d] = self.level_size(k - 1);
+        let flip_x = usize::from(ray.direction().x() < 0.0);
+        let flip_z = usize::from(ray.direction().z() < 0.0);
+        let mut hit_anything = false;
+        for (a, b) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let (ci, cj) = (2 * i + (a ^ flip_x), 2 * j + (b ^ flip_z));
+            if ci < w && cj < d && self.hit_node(ray, k - 1, ci, cj, ray_t, rec) {
+                hit_anything = true;
+            }
+        }
+        hit_anything
+    }
+
+    /// intersects the ray with the two triangles of cell (i,j)
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        i: usize,
+        j: usize,
+        ray_t: &mut Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let p00 = self.sample_point(i, j);
+        let p10 = self.sample_point(i + 1, j);
+        let p01 = self.sample_point(i, j + 1);
+        let p11 = self.sample_point(i + 1, j + 1);
+
+        // both triangles wind so that their normals face +y
+        let mut hit_anything = false;
+        for [a, b, c] in [[p00, p01, p11], [p00, p11, p10]] {
+            let t = match hit_triangle(ray, a, b, c, *ray_t) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            // a cut out hit must not replace a closer accepted one, so fill a scratch record
+            let mut candidate = HitRecord::new();
+            let p = ray.at(t);
+            let u = (p.x() - self.corner.x()) / self.size.x();
+            let v = 1.0 - (p.z() - self.corner.z()) / self.size.z();
+            let normal = vec3::cross(&(b - a), &(c - a));
+            // tangents along the facet, v running against z
+            let dpdu = vec3::cross(&normal, &Vec3::new(0.0, 0.0, self.size.z()));
+            let dpdv = vec3::cross(&normal, &Vec3::new(self.size.x(), 0.0, 0.0));
+            let dpdu = self.size.x() / dpdu.x() * dpdu;
+            let dpdv = -self.size.z() / dpdv.z() * dpdv;
+
+            candidate.set_t(t);
+            candidate.set_p(p);
+            candidate.set_uv(u.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+            candidate.set_tangents(dpdu, dpdv);
+            candidate.set_face_normal(ray, &vec3::unit_vector(normal));
+            candidate.set_mat(self.mat.clone());
+            if material::passes_alpha_test(self.mat.as_ref(), &candidate) {
+                ray_t.max = t;
+                *rec = candidate;
+                hit_anything = true;
+            }
+        }
+        hit_anything
+    }
+}
+
+/// returns the parameter at which the ray crosses triangle abc within ray_t, by Möller–Trumbore
+fn hit_triangle(ray: &Ray, a: Point3, b: Point3, c: Point3, ray_t: Interval) -> Option<f64> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let pvec = vec3::cross(&ray.direction(), &edge2);
+    let determinant = vec3::dot(&edge1, &pvec);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inv_determinant = 1.0 / determinant;
+
+    let tvec = ray.origin() - a;
+    let b1 = vec3::dot(&tvec, &pvec) * inv_determinant;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = vec3::cross(&tvec, &edge1);
+    let b2 = vec3::dot(&ray.direction(), &qvec) * inv_determinant;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = vec3::dot(&edge2, &qvec) * inv_determinant;
+    ray_t.contains(t).then_some(t)
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let mut ray_t = ray_t;
+        let root = self.levels.len();
+        self.hit_node(ray, root, 0, 0, &mut ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{color::Color, material::Lambertian, utils::INFINITY};
+
+    /// returns the closest hit t found by testing every cell's triangles, or None
+    fn brute_force_hit(heightfield: &Heightfield, ray: &Ray) -> Option<f64> {
+        let [nx, nz] = heightfield.resolution();
+        let mut ray_t = Interval::new(0.001, INFINITY);
+        let mut rec = HitRecord::new();
+        let mut hit_anything = false;
+        for j in 0..nz - 1 {
+            for i in 0..nx - 1 {
+                if heightfield.hit_cell(ray, i, j, &mut ray_t, &mut rec) {
+                    hit_anything = true;
+                }
+            }
+        }
+        hit_anything.then_some(rec.t())
+    }
+
+    #[test]
+    fn quadtree_hits_match_brute_force() {
+        // a bumpy 9x7 grid, so the quadtree has partial nodes along both edges
+        let heightfield = Heightfield::from_fn(
+            9,
+            7,
+            |x, z| (7.0 * x).sin() * (5.0 * z).cos() + 0.3 * (13.0 * x * z).sin(),
+            Point3::new(-2.0, 0.0, -1.5),
+            Vec3::new(4.0, 0.5, 3.0),
+            Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+
+        let mut hits = 0;
+        for a in 0..24 {
+            for b in 0..24 {
+                // rays fanning down from above the terrain, some grazing, some missing it
+                let origin = Point3::new(-3.0 + 0.25 * a as f64, 2.0, -2.5 + 0.2 * b as f64);
+                let direction = Vec3::new(
+                    (0.7 * (a + b) as f64).sin(),
+                    -0.3 - 0.2 * (a % 7) as f64,
+                    0.5 * (0.3 * (a * b) as f64).cos(),
+                );
+                let ray = Ray::new(origin, direction);
+
+                let mut rec = HitRecord::new();
+                let hit = heightfield
+                    .hit(&ray, Interval::new(0.001, INFINITY), &mut rec)
+                    .then(|| rec.t());
+                let expected = brute_force_hit(&heightfield, &ray);
+                match (hit, expected) {
+                    (Some(t), Some(expected_t)) => {
+                        assert!(
+                            (t - expected_t).abs() < 1e-9,
+                            "hit {t}, expected {expected_t}"
+                        );
+                        hits += 1;
+                    }
+                    (None, None) => {}
+                    _ => panic!("hit {hit:?} but brute force found {expected:?}"),
+                }
+            }
+        }
+        assert!(hits > 100, "only {hits} rays hit the terrain");
+    }
+}
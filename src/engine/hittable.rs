@@ -1,17 +1,26 @@
 // src/engine/hittable.rs
 
 use crate::engine::{
+    aabb::Aabb,
     interval::Interval,
+    material::Material,
     ray::Ray,
     vec3::{self, Point3, Vec3},
 };
 
+use std::rc::Rc;
+
 #[derive(Clone)]
 pub struct HitRecord {
-    p: Point3,        // 3d point where ray intersects surface
-    normal: Vec3,     // surface normal at intersection point
-    t: f64,           // parametric distance along the ray where intersection occurs
+    p: Point3,                     // 3d point where ray intersects surface
+    normal: Vec3,                  // surface normal at intersection point
+    t: f64,                        // parametric distance along the ray where intersection occurs
+    u: f64,                        // surface u coordinate of the hit point
+    v: f64,                        // surface v coordinate of the hit point
+    dpdu: Vec3,       // partial derivative of the hit point along u, the shading tangent
+    dpdv: Vec3,       // partial derivative of the hit point along v
     front_face: bool, // flag indicating whether ray hit from outside (true) or inside (false)
+    mat: Option<Rc<dyn Material>>, // material of the surface (or medium) that was hit
 }
 
 impl HitRecord {
@@ -21,7 +30,12 @@ impl HitRecord {
             p: Point3::zero(),
             normal: Vec3::zero(),
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::zero(),
+            dpdv: Vec3::zero(),
             front_face: false,
+            mat: None,
         }
     }
 
@@ -39,11 +53,32 @@ impl HitRecord {
     pub fn t(&self) -> f64 {
         self.t
     }
+    /// returns a HitRecord's surface u coordinate
+    pub fn u(&self) -> f64 {
+        self.u
+    }
+    /// returns a HitRecord's surface v coordinate
+    pub fn v(&self) -> f64 {
+        self.v
+    }
+    /// returns a HitRecord's partial derivative of the point along u
+    pub fn dpdu(&self) -> Vec3 {
+        self.dpdu
+    }
+    /// returns a HitRecord's partial derivative of the point along v
+    pub fn dpdv(&self) -> Vec3 {
+        self.dpdv
+    }
     /// returns a HitRecord's front face
     pub fn front_face(&self) -> bool {
         self.front_face
     }
 
+    /// returns a HitRecord's material
+    pub fn mat(&self) -> Option<Rc<dyn Material>> {
+        self.mat.clone()
+    }
+
     /// setter for HitRecord's point p
     pub fn set_p(&mut self, p: Point3) {
         self.p = p;
@@ -57,16 +92,34 @@ impl HitRecord {
         self.t = t;
     }
 
+    /// setter for HitRecord's surface coordinates
+    pub fn set_uv(&mut self, u: f64, v: f64) {
+        self.u = u;
+        self.v = v;
+    }
+
+    /// setter for HitRecord's partial derivatives of the point along u and v
+    /// NOTE: surfaces without a parameterization leave these zero
+    pub fn set_tangents(&mut self, dpdu: Vec3, dpdv: Vec3) {
+        self.dpdu = dpdu;
+        self.dpdv = dpdv;
+    }
+
     /// setter for HitRecord's front face
     pub fn set_front_face(&mut self, front_face: bool) {
         self.front_face = front_face;
     }
 
+    /// setter for HitRecord's material
+    pub fn set_mat(&mut self, mat: Rc<dyn Material>) {
+        self.mat = Some(mat);
+    }
+
     /// sets the hit record's normal vector
     /// NOTE: outward_normal parameter is assumed to have unit length
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) {
         // determine if ray hit from outside (front face)
-        self.set_front_face(vec3::dot(&ray.direction(), &outward_normal) < 0.0);
+        self.set_front_face(vec3::dot(&ray.direction(), outward_normal) < 0.0);
 
         // ensure surface normal always points against the ray
         self.set_normal(if self.front_face() {
@@ -77,6 +130,46 @@ impl HitRecord {
     }
 }
 
+impl Default for HitRecord {
+    fn default() -> Self {
+        HitRecord::new()
+    }
+}
+
 pub trait Hittable {
     fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+
+    /// intersects like `hit`, but keeps the parts that partially transparent materials cut out,
+    /// so the result doesn't depend on random alpha tests
+    /// NOTE: objects that can be sampled as lights override this; the rest fall back to `hit`
+    fn hit_ignoring_alpha(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.hit(ray, ray_t, rec)
+    }
+
+    /// returns the axis-aligned box enclosing the object over the whole shutter interval
+    fn bounding_box(&self) -> Aabb;
+
+    /// returns the solid angle pdf of sampling the given direction from origin towards this object
+    /// at given time
+    /// NOTE: objects that cannot be sampled as lights return zero
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3, _time: f64) -> f64 {
+        0.0
+    }
+
+    /// returns a random direction from origin towards this object at given time
+    fn random(&self, _origin: &Point3, _time: f64) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// returns the fraction of light passing through the object along the ray within ray_t
+    /// NOTE: surfaces block all of it wherever the ray hits them; participating media override
+    /// this with an unbiased estimate in between
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+        let mut rec = HitRecord::new();
+        if self.hit(ray, ray_t, &mut rec) {
+            0.0
+        } else {
+            1.0
+        }
+    }
 }
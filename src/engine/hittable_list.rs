@@ -1,8 +1,11 @@
 // src/engine/hittable_list.rs
 
 use crate::engine::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     ray::Ray,
+    utils,
+    vec3::{Point3, Vec3},
 };
 
 use std::rc::Rc;
@@ -11,6 +14,7 @@ use super::interval::Interval;
 
 pub struct HittableList {
     objects: Vec<Rc<dyn Hittable>>,
+    bbox: Aabb, // box enclosing every object in the list
 }
 
 impl HittableList {
@@ -18,28 +22,46 @@ impl HittableList {
     pub fn new() -> HittableList {
         HittableList {
             objects: Vec::new(),
+            bbox: Aabb::empty(),
         }
     }
 
     /// adds object to the list
     pub fn add(&mut self, object: Rc<dyn Hittable>) {
+        self.bbox = Aabb::surrounding(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
 
     /// clears all objects from list
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = Aabb::empty();
     }
-}
 
-impl Hittable for HittableList {
-    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+    /// returns the objects in the list
+    pub fn objects(&self) -> &[Rc<dyn Hittable>] {
+        &self.objects
+    }
+
+    /// returns whether the list holds no objects
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// finds the closest hit among the objects, applying their alpha tests if alpha_test is set
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, alpha_test: bool) -> bool {
         let mut temp_rec = HitRecord::new();
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max;
 
         for object in &self.objects {
-            if object.hit(ray, Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
+            let object_t = Interval::new(ray_t.min, closest_so_far);
+            let hit = if alpha_test {
+                object.hit(ray, object_t, &mut temp_rec)
+            } else {
+                object.hit_ignoring_alpha(ray, object_t, &mut temp_rec)
+            };
+            if hit {
                 hit_anything = true;
                 closest_so_far = temp_rec.t();
                 *rec = temp_rec.clone();
@@ -49,3 +71,58 @@ impl Hittable for HittableList {
         hit_anything
     }
 }
+
+impl Default for HittableList {
+    fn default() -> Self {
+        HittableList::new()
+    }
+}
+
+impl Hittable for HittableList {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, true)
+    }
+
+    fn hit_ignoring_alpha(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, false)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// returns the average pdf of the objects, matching uniform selection in `random`
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction, time))
+            .sum()
+    }
+
+    /// returns a random direction towards a uniformly chosen object
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+
+        let size = self.objects.len();
+        let index = ((utils::random_0_to_1() * size as f64) as usize).min(size - 1);
+        self.objects[index].random(origin, time)
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+        let mut transmittance = 1.0;
+        for object in &self.objects {
+            transmittance *= object.transmittance(ray, ray_t);
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+        transmittance
+    }
+}
@@ -0,0 +1,168 @@
+// src/engine/image.rs
+
+use crate::engine::color::Color;
+
+use std::io;
+use std::path::Path;
+
+/// an rgb image held in memory with components in [0,1]
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>, // row-major, top row first
+}
+
+impl Image {
+    /// constructs new `Image` of given size with every pixel set to color
+    pub fn new(width: usize, height: usize, color: Color) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![color; width * height],
+        }
+    }
+
+    /// loads an `Image` from a binary (P6) or plain (P3) ppm file
+    pub fn load_ppm<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+        let bytes = std::fs::read(path)?;
+        Image::from_ppm_bytes(&bytes)
+    }
+
+    /// parses an `Image` from the contents of a binary (P6) or plain (P3) ppm file
+    pub fn from_ppm_bytes(bytes: &[u8]) -> io::Result<Image> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        // read the four whitespace separated header fields, skipping comments
+        let mut pos = 0;
+        let mut fields = Vec::new();
+        while fields.len() < 4 {
+            while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#') {
+                if bytes[pos] == b'#' {
+                    while pos < bytes.len() && bytes[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid("truncated ppm header"));
+            }
+            fields.push(String::from_utf8_lossy(&bytes[start..pos]).to_string());
+        }
+
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| invalid("malformed number in ppm header"))
+        };
+        let width = number(&fields[1])?;
+        let height = number(&fields[2])?;
+        let max_value = number(&fields[3])?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid("unsupported ppm max value"));
+        }
+
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(|| invalid("ppm dimensions are too large"))?;
+        let samples: Vec<usize> = match fields[0].as_str() {
+            "P6" => {
+                // a single whitespace byte separates the header from the raster
+                let raster = &bytes[(pos + 1).min(bytes.len())..];
+                if max_value < 256 {
+                    raster.iter().take(count).map(|&b| b as usize).collect()
+                } else {
+                    raster
+                        .chunks_exact(2)
+                        .take(count)
+                        .map(|c| u16::from_be_bytes([c[0], c[1]]) as usize)
+                        .collect()
+                }
+            }
+            "P3" => String::from_utf8_lossy(&bytes[pos..])
+                .split_ascii_whitespace()
+                .take(count)
+                .map(number)
+                .collect::<io::Result<_>>()?,
+            _ => return Err(invalid("unsupported ppm magic number")),
+        };
+        if samples.len() != count {
+            return Err(invalid("ppm raster is shorter than its header"));
+        }
+
+        let scale = 1.0 / max_value as f64;
+        let pixels = samples
+            .chunks_exact(3)
+            .map(|c| {
+                Color::new(
+                    c[0] as f64 * scale,
+                    c[1] as f64 * scale,
+                    c[2] as f64 * scale,
+                )
+            })
+            .collect();
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// returns the image width in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// returns the image height in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// returns the color of pixel (x,y), clamping coordinates to the image
+    pub fn pixel(&self, x: i64, y: i64) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new(1.0, 0.0, 1.0); // solid magenta flags a missing image
+        }
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let y = y.clamp(0, self.height as i64 - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    /// sets the color of pixel (x,y), ignoring coordinates outside the image
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    /// returns the nearest pixel at texture coordinates (u,v) in [0,1], v = 0 at the bottom
+    pub fn sample_nearest(&self, u: f64, v: f64) -> Color {
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+        let x = (u * self.width as f64) as i64;
+        let y = (v * self.height as f64) as i64;
+        self.pixel(x, y)
+    }
+
+    /// returns the bilinearly filtered color at texture coordinates (u,v) in [0,1], v = 0 at the bottom
+    pub fn sample_bilinear(&self, u: f64, v: f64) -> Color {
+        let x = u.clamp(0.0, 1.0) * self.width as f64 - 0.5;
+        let y = (1.0 - v.clamp(0.0, 1.0)) * self.height as f64 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = (1.0 - tx) * self.pixel(x0, y0) + tx * self.pixel(x0 + 1, y0);
+        let bottom = (1.0 - tx) * self.pixel(x0, y0 + 1) + tx * self.pixel(x0 + 1, y0 + 1);
+        (1.0 - ty) * top + ty * bottom
+    }
+}
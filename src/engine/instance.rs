@@ -0,0 +1,308 @@
+// src/engine/instance.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    utils,
+    vec3::{Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a similarity transform: uniform scale, then rotation, then translation
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    translation: Vec3, // offset applied last
+    rotation: Vec3, // euler angles in degrees about the x, y and z axes (applied x, then y, then z)
+    scale: f64,     // uniform scale factor applied first
+}
+
+impl Transform {
+    /// constructs new `Transform` from given translation, euler rotation (degrees) and uniform scale
+    pub fn new(translation: Vec3, rotation: Vec3, scale: f64) -> Transform {
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// constructs the identity `Transform`
+    pub fn identity() -> Transform {
+        Transform::new(Vec3::zero(), Vec3::zero(), 1.0)
+    }
+
+    /// constructs a `Transform` that only translates
+    pub fn translate(offset: Vec3) -> Transform {
+        Transform::new(offset, Vec3::zero(), 1.0)
+    }
+
+    /// returns the transform's translation
+    pub fn translation(&self) -> Vec3 {
+        self.translation
+    }
+
+    /// returns the transform's euler rotation in degrees
+    pub fn rotation(&self) -> Vec3 {
+        self.rotation
+    }
+
+    /// returns the transform's uniform scale
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// linearly interpolates each component between two transforms, t in [0,1]
+    pub fn lerp(a: &Transform, b: &Transform, t: f64) -> Transform {
+        Transform {
+            translation: (1.0 - t) * a.translation + t * b.translation,
+            rotation: (1.0 - t) * a.rotation + t * b.rotation,
+            scale: (1.0 - t) * a.scale + t * b.scale,
+        }
+    }
+
+    /// returns the rotation matrix Rz * Ry * Rx in row-major order
+    fn rotation_matrix(&self) -> [[f64; 3]; 3] {
+        let (sx, cx) = utils::deg_to_rad(self.rotation.x()).sin_cos();
+        let (sy, cy) = utils::deg_to_rad(self.rotation.y()).sin_cos();
+        let (sz, cz) = utils::deg_to_rad(self.rotation.z()).sin_cos();
+
+        [
+            [cz * cy, cz * sy * sx - sz * cx, cz * sy * cx + sz * sx],
+            [sz * cy, sz * sy * sx + cz * cx, sz * sy * cx - cz * sx],
+            [-sy, cy * sx, cy * cx],
+        ]
+    }
+
+    /// applies the transform to a point from object space to world space
+    pub fn point_to_world(&self, p: Point3) -> Point3 {
+        self.vector_to_world(p) + self.translation
+    }
+
+    /// applies the transform to a direction from object space to world space
+    pub fn vector_to_world(&self, v: Vec3) -> Vec3 {
+        let m = self.rotation_matrix();
+        self.scale * mat_mul(&m, v)
+    }
+
+    /// applies the transform to a surface normal from object space to world space
+    pub fn normal_to_world(&self, n: Vec3) -> Vec3 {
+        // uniform scale keeps normals perpendicular, so only the rotation applies
+        mat_mul(&self.rotation_matrix(), n)
+    }
+
+    /// applies the inverse transform to a point from world space to object space
+    pub fn point_to_local(&self, p: Point3) -> Point3 {
+        self.vector_to_local(p - self.translation)
+    }
+
+    /// applies the inverse transform to a direction from world space to object space
+    pub fn vector_to_local(&self, v: Vec3) -> Vec3 {
+        let m = self.rotation_matrix();
+        mat_mul_transpose(&m, v) / self.scale
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+/// returns m * v
+fn mat_mul(m: &[[f64; 3]; 3], v: Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+        m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+        m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+    )
+}
+
+/// returns transpose(m) * v, the inverse rotation for an orthonormal m
+fn mat_mul_transpose(m: &[[f64; 3]; 3], v: Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * v.x() + m[1][0] * v.y() + m[2][0] * v.z(),
+        m[0][1] * v.x() + m[1][1] * v.y() + m[2][1] * v.z(),
+        m[0][2] * v.x() + m[1][2] * v.y() + m[2][2] * v.z(),
+    )
+}
+
+/// a transform pinned to a moment in time
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub transform: Transform,
+}
+
+impl Keyframe {
+    /// constructs new `Keyframe` from given time and transform
+    pub fn new(time: f64, transform: Transform) -> Keyframe {
+        Keyframe { time, transform }
+    }
+}
+
+/// places a shared object in the world through a (possibly animated) transform
+pub struct Instance {
+    object: Rc<dyn Hittable>,
+    keyframes: Vec<Keyframe>, // sorted by time, never empty
+}
+
+impl Instance {
+    /// constructs new static `Instance` of an object under a single transform
+    pub fn new(object: Rc<dyn Hittable>, transform: Transform) -> Instance {
+        Instance {
+            object,
+            keyframes: vec![Keyframe::new(0.0, transform)],
+        }
+    }
+
+    /// constructs new animated `Instance` whose transform is interpolated between keyframes
+    /// NOTE: an empty keyframe list results in the identity transform
+    pub fn new_animated(object: Rc<dyn Hittable>, keyframes: Vec<Keyframe>) -> Instance {
+        let mut instance = Instance {
+            object,
+            keyframes: Vec::new(),
+        };
+        for keyframe in keyframes {
+            instance.add_keyframe(keyframe);
+        }
+        if instance.keyframes.is_empty() {
+            instance
+                .keyframes
+                .push(Keyframe::new(0.0, Transform::identity()));
+        }
+        instance
+    }
+
+    /// inserts a keyframe, keeping keyframes sorted by time
+    pub fn add_keyframe(&mut self, keyframe: Keyframe) {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    /// returns the instance's keyframes
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// returns the transform at the given time, holding the first/last keyframe outside their range
+    pub fn transform_at(&self, time: f64) -> Transform {
+        let first = self.keyframes[0];
+        let last = self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return first.transform;
+        }
+        if time >= last.time {
+            return last.transform;
+        }
+
+        // find the pair of keyframes bracketing the requested time
+        let index = self.keyframes.partition_point(|k| k.time <= time);
+        let k0 = self.keyframes[index - 1];
+        let k1 = self.keyframes[index];
+        let t = (time - k0.time) / (k1.time - k0.time);
+        Transform::lerp(&k0.transform, &k1.transform, t)
+    }
+
+    /// intersects the ray with the transformed object, applying its alpha tests if alpha_test is
+    /// set
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, alpha_test: bool) -> bool {
+        let transform = self.transform_at(ray.time());
+
+        // move the ray into object space; the affine map keeps t unchanged
+        let local_ray = Ray::new_with_time(
+            transform.point_to_local(ray.origin()),
+            transform.vector_to_local(ray.direction()),
+            ray.time(),
+        );
+
+        let hit = if alpha_test {
+            self.object.hit(&local_ray, ray_t, rec)
+        } else {
+            self.object.hit_ignoring_alpha(&local_ray, ray_t, rec)
+        };
+        if !hit {
+            return false;
+        }
+
+        // move the intersection back into world space
+        rec.set_p(transform.point_to_world(rec.p()));
+        rec.set_normal(transform.normal_to_world(rec.normal()));
+        rec.set_tangents(
+            transform.vector_to_world(rec.dpdu()),
+            transform.vector_to_world(rec.dpdv()),
+        );
+        true
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, true)
+    }
+
+    fn hit_ignoring_alpha(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, false)
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+        let transform = self.transform_at(ray.time());
+        let local_ray = Ray::new_with_time(
+            transform.point_to_local(ray.origin()),
+            transform.vector_to_local(ray.direction()),
+            ray.time(),
+        );
+        self.object.transmittance(&local_ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let object_box = self.object.bounding_box();
+        if object_box.is_empty() {
+            return object_box; // nothing to bound
+        }
+
+        if let [keyframe] = self.keyframes.as_slice() {
+            // a single transform maps the box's corners exactly
+            return object_box
+                .corners()
+                .iter()
+                .fold(Aabb::empty(), |bbox, corner| {
+                    let p = keyframe.transform.point_to_world(*corner);
+                    Aabb::surrounding(&bbox, &Aabb::from_points(p, p))
+                });
+        }
+
+        // interpolated rotations can swing the object outside the keyframes' boxes, so bound it
+        // by the ball around the object space origin that any rotation keeps it within; the
+        // translation and scale are interpolated linearly, so the keyframes' balls bound the rest
+        let reach = object_box
+            .corners()
+            .iter()
+            .map(|corner| corner.length())
+            .fold(0.0, f64::max);
+        self.keyframes.iter().fold(Aabb::empty(), |bbox, keyframe| {
+            let center = keyframe.transform.translation();
+            let r = keyframe.transform.scale().abs() * reach;
+            let half = Vec3::new(r, r, r);
+            Aabb::surrounding(&bbox, &Aabb::from_points(center - half, center + half))
+        })
+    }
+
+    /// NOTE: similarity transforms preserve solid angles, so the object space pdf carries over
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let transform = self.transform_at(time);
+        self.object.pdf_value(
+            &transform.point_to_local(*origin),
+            &transform.vector_to_local(*direction),
+            time,
+        )
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let transform = self.transform_at(time);
+        let local_direction = self.object.random(&transform.point_to_local(*origin), time);
+        transform.vector_to_world(local_direction)
+    }
+}
@@ -11,7 +11,7 @@ pub struct Interval {
 impl Interval {
     /// creates a new interval of given values
     pub fn new(min: f64, max: f64) -> Interval {
-        Interval { min: min, max: max }
+        Interval { min, max }
     }
 
     /// creates an interval of no values
@@ -30,6 +30,14 @@ impl Interval {
         }
     }
 
+    /// creates the smallest interval enclosing both given intervals
+    pub fn enclosing(a: &Interval, b: &Interval) -> Interval {
+        Interval {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
+
     /// returns the size of an interval
     pub fn size(&self) -> f64 {
         self.max - self.min
@@ -45,6 +53,11 @@ impl Interval {
         self.min < x && x < self.max
     }
 
+    /// returns the interval grown by delta on each side
+    pub fn expand(&self, delta: f64) -> Interval {
+        Interval::new(self.min - delta, self.max + delta)
+    }
+
     /// clamps input value within bounds
     pub fn clamp(&self, x: f64) -> f64 {
         x.clamp(self.min, self.max)
@@ -0,0 +1,468 @@
+// src/engine/lens_system.rs
+
+// a camera lens made of spherical glass elements, traced ray by ray as in pbrt's realistic
+// camera, so vignetting, distortion and bokeh come from the lens design itself
+//
+// lens space puts the film at the origin with the optical axis along z and the scene towards -z
+
+use crate::engine::{
+    microfacet, polynomial,
+    ray::Ray,
+    utils::{self, INFINITY},
+    vec3::{self, Point3, Vec3},
+};
+
+use std::io;
+use std::path::Path;
+
+// film radius intervals over which the exit pupil is bounded
+const PUPIL_BINS: usize = 64;
+// rays traced along each side of the grid that bounds the exit pupil of an interval
+const PUPIL_GRID: usize = 64;
+
+/// one interface of a lens prescription: a spherical surface between two media, or the aperture
+/// stop when its curvature radius is zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensElement {
+    curvature_radius: f64, // signed, positive when the center of curvature lies towards the film
+    thickness: f64,        // distance along the axis to the next interface, or to the film
+    eta: f64,              // index of refraction of the medium behind the interface
+    aperture_radius: f64,  // radius of the clear aperture
+}
+
+impl LensElement {
+    /// constructs new `LensElement` from its curvature radius, thickness, index of refraction of
+    /// the medium behind it (0 or 1 for air) and aperture radius
+    pub fn new(
+        curvature_radius: f64,
+        thickness: f64,
+        eta: f64,
+        aperture_radius: f64,
+    ) -> LensElement {
+        LensElement {
+            curvature_radius,
+            thickness: thickness.max(0.0),
+            eta: if eta > 0.0 { eta } else { 1.0 },
+            aperture_radius: aperture_radius.abs(),
+        }
+    }
+
+    /// returns the element's signed curvature radius, zero for the aperture stop
+    pub fn curvature_radius(&self) -> f64 {
+        self.curvature_radius
+    }
+
+    /// returns the distance along the axis to the next interface
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    /// returns the index of refraction of the medium behind the element
+    pub fn eta(&self) -> f64 {
+        self.eta
+    }
+
+    /// returns the radius of the element's clear aperture
+    pub fn aperture_radius(&self) -> f64 {
+        self.aperture_radius
+    }
+
+    /// returns whether the element is the aperture stop
+    pub fn is_stop(&self) -> bool {
+        self.curvature_radius == 0.0
+    }
+}
+
+/// the region of the rear element that rays from a range of film points can leave through
+#[derive(Debug, Clone, Copy)]
+struct PupilBounds {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl PupilBounds {
+    /// returns the bounds' area, zero when empty
+    fn area(&self) -> f64 {
+        (self.max.0 - self.min.0).max(0.0) * (self.max.1 - self.min.1).max(0.0)
+    }
+}
+
+/// a sequence of lens elements focused onto a film of given diagonal
+#[derive(Debug, Clone)]
+pub struct LensSystem {
+    elements: Vec<LensElement>, // from the front, facing the scene, to the rear, facing the film
+    film_diagonal: f64,
+    pupils: Vec<PupilBounds>, // exit pupil of each film radius interval, for sampling rays
+    normalization: f64,       // weight that gives the film center a weight of one on average
+}
+
+impl LensSystem {
+    /// constructs new `LensSystem` from its elements, front to rear, with the aperture stop
+    /// opened to given diameter and the film moved to focus at focus_distance
+    /// NOTE: the last element's thickness is replaced by the focused distance to the film
+    /// returns an error if there are no elements or the lens cannot focus at that distance
+    pub fn new(
+        elements: Vec<LensElement>,
+        aperture_diameter: f64,
+        focus_distance: f64,
+        film_diagonal: f64,
+    ) -> io::Result<LensSystem> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+        if elements.is_empty() {
+            return Err(invalid("lens system has no elements"));
+        }
+
+        // the stop opens to the requested diameter, but no wider than the lens allows
+        let mut elements = elements;
+        for element in elements.iter_mut().filter(|element| element.is_stop()) {
+            element.aperture_radius = element.aperture_radius.min(0.5 * aperture_diameter.abs());
+        }
+
+        let mut lens = LensSystem {
+            elements,
+            film_diagonal: film_diagonal.abs(),
+            pupils: Vec::new(),
+            normalization: 1.0,
+        };
+        let film_distance = lens
+            .focus_thick_lens(focus_distance.abs())
+            .ok_or_else(|| invalid("lens system cannot focus at the given distance"))?;
+        lens.elements.last_mut().unwrap().thickness = film_distance;
+
+        lens.bound_exit_pupils();
+        Ok(lens)
+    }
+
+    /// constructs new `LensSystem` from the rows of a prescription table, each a curvature
+    /// radius, thickness, index of refraction and aperture diameter in millimeters, as in `new`
+    /// NOTE: the aperture and film diagonal are also in millimeters, the focus distance is in
+    /// scene units, taken as meters
+    pub fn from_prescription(
+        rows: &[[f64; 4]],
+        aperture_diameter: f64,
+        focus_distance: f64,
+        film_diagonal: f64,
+    ) -> io::Result<LensSystem> {
+        let elements = rows
+            .iter()
+            .map(|&[radius, thickness, eta, diameter]| {
+                LensElement::new(
+                    0.001 * radius,
+                    0.001 * thickness,
+                    eta,
+                    0.001 * 0.5 * diameter,
+                )
+            })
+            .collect();
+        LensSystem::new(
+            elements,
+            0.001 * aperture_diameter,
+            focus_distance,
+            0.001 * film_diagonal,
+        )
+    }
+
+    /// loads a `LensSystem` from a prescription file of whitespace separated rows of four
+    /// numbers, with `#` starting comments, as in `from_prescription`
+    pub fn load_prescription<P: AsRef<Path>>(
+        path: P,
+        aperture_diameter: f64,
+        focus_distance: f64,
+        film_diagonal: f64,
+    ) -> io::Result<LensSystem> {
+        let text = std::fs::read_to_string(path)?;
+        let mut rows = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let values = line
+                .split_whitespace()
+                .map(|s| s.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("lens line {}: malformed number", number + 1),
+                    )
+                })?;
+            match values.len() {
+                0 => {}
+                4 => rows.push([values[0], values[1], values[2], values[3]]),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("lens line {}: expected four values", number + 1),
+                    ));
+                }
+            }
+        }
+        LensSystem::from_prescription(&rows, aperture_diameter, focus_distance, film_diagonal)
+    }
+
+    /// returns the lens elements, front to rear
+    pub fn elements(&self) -> &[LensElement] {
+        &self.elements
+    }
+
+    /// returns the diagonal of the film
+    pub fn film_diagonal(&self) -> f64 {
+        self.film_diagonal
+    }
+
+    /// returns the width and height of a film of given aspect ratio that fits the diagonal
+    pub fn film_size(&self, aspect_ratio: f64) -> (f64, f64) {
+        let height = self.film_diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+        (aspect_ratio * height, height)
+    }
+
+    /// returns the effective focal length, from the thick lens approximation
+    pub fn focal_length(&self) -> Option<f64> {
+        let (principal, focal) = self.thick_lens()?;
+        Some((focal[0] - principal[0]).abs())
+    }
+
+    /// returns a lens space ray leaving the front of the lens for the image point x right and y
+    /// up of the film center, and the weight of the radiance it carries, one on average at the
+    /// center of an unvignetted lens
+    /// returns None if the lens blocks the sampled path
+    pub fn sample_ray(&self, x: f64, y: f64) -> Option<(Ray, f64)> {
+        // the lens flips the image, so the film point for the upper right is at the lower left
+        let film = Point3::new(-x, -y, 0.0);
+        let film_radius = (x * x + y * y).sqrt();
+
+        let bin = ((film_radius / (0.5 * self.film_diagonal)) * PUPIL_BINS as f64) as usize;
+        let bounds = self.pupils[bin.min(PUPIL_BINS - 1)];
+        if bounds.area() <= 0.0 {
+            return None;
+        }
+
+        // pupils were bounded for film points on the +x axis, so rotate to the film point's angle
+        let (u1, u2) = utils::random_2d();
+        let px = bounds.min.0 + u1 * (bounds.max.0 - bounds.min.0);
+        let py = bounds.min.1 + u2 * (bounds.max.1 - bounds.min.1);
+        let (sin, cos) = if film_radius > 0.0 {
+            (film.y() / film_radius, film.x() / film_radius)
+        } else {
+            (0.0, 1.0)
+        };
+        let rear = Point3::new(cos * px - sin * py, sin * px + cos * py, -self.rear_z());
+
+        let direction = rear - film;
+        let ray = self.trace_from_film(&Ray::new(film, direction))?;
+
+        // radiance falls off with the fourth power of the cosine to the axis, natural vignetting
+        let cos_theta = -direction.z() / direction.length();
+        let weight = cos_theta.powi(4) * bounds.area() * self.normalization;
+        Some((ray, weight))
+    }
+
+    /// returns the distance from the film to the rear element
+    fn rear_z(&self) -> f64 {
+        self.elements
+            .last()
+            .map_or(0.0, |element| element.thickness)
+    }
+
+    /// returns the distance from the film to the front element
+    fn front_z(&self) -> f64 {
+        self.elements.iter().map(|element| element.thickness).sum()
+    }
+
+    /// traces a lens space ray from the film out through the elements, rear to front
+    /// returns the ray leaving the front element, or None if an aperture blocks it or it is
+    /// totally internally reflected
+    fn trace_from_film(&self, ray: &Ray) -> Option<Ray> {
+        let mut ray = *ray;
+        let mut element_z = 0.0;
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            element_z -= element.thickness;
+            let eta_behind = element.eta;
+            let eta_front = if i > 0 { self.elements[i - 1].eta } else { 1.0 };
+            ray = self.cross_interface(&ray, element, element_z, eta_behind, eta_front)?;
+        }
+        Some(ray)
+    }
+
+    /// traces a lens space ray from the scene in through the elements, front to rear
+    /// returns the ray leaving the rear element, or None if it is blocked
+    fn trace_from_scene(&self, ray: &Ray) -> Option<Ray> {
+        let mut ray = *ray;
+        let mut element_z = -self.front_z();
+        for i in 0..self.elements.len() {
+            let element = &self.elements[i];
+            let eta_front = if i > 0 { self.elements[i - 1].eta } else { 1.0 };
+            ray = self.cross_interface(&ray, element, element_z, eta_front, element.eta)?;
+            element_z += element.thickness;
+        }
+        Some(ray)
+    }
+
+    /// returns the ray continuing past the interface at element_z, refracted from the medium of
+    /// index eta_i into the medium of index eta_t, or None if it misses the aperture
+    fn cross_interface(
+        &self,
+        ray: &Ray,
+        element: &LensElement,
+        element_z: f64,
+        eta_i: f64,
+        eta_t: f64,
+    ) -> Option<Ray> {
+        let (t, normal) = if element.is_stop() {
+            if ray.direction().z() == 0.0 {
+                return None;
+            }
+            ((element_z - ray.origin().z()) / ray.direction().z(), None)
+        } else {
+            let radius = element.curvature_radius;
+            let (t, normal) = hit_spherical_element(ray, radius, element_z + radius)?;
+            (t, Some(normal))
+        };
+        if t <= 0.0 {
+            return None;
+        }
+
+        let p = ray.at(t);
+        if p.x() * p.x() + p.y() * p.y() > element.aperture_radius * element.aperture_radius {
+            return None;
+        }
+        let direction = match normal {
+            None => ray.direction(),
+            Some(normal) => {
+                let w = -vec3::unit_vector(ray.direction());
+                microfacet::refract(&w, &normal, eta_t / eta_i)?
+            }
+        };
+        Some(Ray::new(p, direction))
+    }
+
+    /// returns the z of the principal planes and focal points, scene side first, found by tracing
+    /// rays parallel to the axis through the lens from either side
+    fn thick_lens(&self) -> Option<([f64; 2], [f64; 2])> {
+        let x = 0.001 * self.film_diagonal;
+
+        let from_scene = Ray::new(
+            Point3::new(x, 0.0, -self.front_z() - 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        let (p0, f0) = cardinal_points(&from_scene, &self.trace_from_scene(&from_scene)?)?;
+
+        let from_film = Ray::new(
+            Point3::new(x, 0.0, -self.rear_z() + 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        let (p1, f1) = cardinal_points(&from_film, &self.trace_from_film(&from_film)?)?;
+
+        Some(([p0, p1], [f0, f1]))
+    }
+
+    /// returns the distance from the rear element to the film that brings the plane at
+    /// focus_distance in front of the film into focus, by the thick lens approximation
+    fn focus_thick_lens(&self, focus_distance: f64) -> Option<f64> {
+        let (principal, focal) = self.thick_lens()?;
+        let f = focal[0] - principal[0];
+        let z = -focus_distance;
+        let c = (principal[1] - z - principal[0]) * (principal[1] - z - 4.0 * f - principal[0]);
+        if c <= 0.0 {
+            return None;
+        }
+        let delta = 0.5 * (principal[1] - z + principal[0] - c.sqrt());
+        let film_distance = self.rear_z() + delta;
+        (film_distance > 0.0).then_some(film_distance)
+    }
+
+    /// bounds the rear element region that rays through the lens leave from, for each film
+    /// radius interval, and the weight that normalizes the film center
+    fn bound_exit_pupils(&mut self) {
+        let rear_radius = self.elements.last().map_or(0.0, |e| e.aperture_radius);
+        let extent = 1.5 * rear_radius;
+        let cell = 2.0 * extent / PUPIL_GRID as f64;
+        let max_radius = 0.5 * self.film_diagonal;
+
+        let mut pupils = Vec::with_capacity(PUPIL_BINS);
+        for bin in 0..PUPIL_BINS {
+            let r0 = bin as f64 / PUPIL_BINS as f64 * max_radius;
+            let r1 = (bin + 1) as f64 / PUPIL_BINS as f64 * max_radius;
+            let mut bounds = PupilBounds {
+                min: (INFINITY, INFINITY),
+                max: (-INFINITY, -INFINITY),
+            };
+            for gy in 0..PUPIL_GRID {
+                for gx in 0..PUPIL_GRID {
+                    let film_x = r0 + (r1 - r0) * utils::random_0_to_1();
+                    let px = -extent + (gx as f64 + utils::random_0_to_1()) * cell;
+                    let py = -extent + (gy as f64 + utils::random_0_to_1()) * cell;
+                    let film = Point3::new(film_x, 0.0, 0.0);
+                    let rear = Point3::new(px, py, -self.rear_z());
+                    if self.trace_from_film(&Ray::new(film, rear - film)).is_some() {
+                        bounds.min = (bounds.min.0.min(px), bounds.min.1.min(py));
+                        bounds.max = (bounds.max.0.max(px), bounds.max.1.max(py));
+                    }
+                }
+            }
+            // grow by a grid cell, since the samples only approach the true boundary
+            if bounds.area() > 0.0 {
+                bounds.min = (bounds.min.0 - cell, bounds.min.1 - cell);
+                bounds.max = (bounds.max.0 + cell, bounds.max.1 + cell);
+            }
+            pupils.push(bounds);
+        }
+        self.pupils = pupils;
+
+        // estimate the mean cosine falloff of rays from the film center through its bounds,
+        // counting blocked rays as zero
+        let center = self.pupils[0];
+        let trials = PUPIL_GRID * PUPIL_GRID;
+        let falloff: f64 = (0..trials)
+            .map(|_| {
+                let px = center.min.0 + utils::random_0_to_1() * (center.max.0 - center.min.0);
+                let py = center.min.1 + utils::random_0_to_1() * (center.max.1 - center.min.1);
+                let rear = Point3::new(px, py, -self.rear_z());
+                match self.trace_from_film(&Ray::new(Point3::zero(), rear)) {
+                    Some(_) => (-rear.z() / rear.length()).powi(4),
+                    None => 0.0,
+                }
+            })
+            .sum();
+        self.normalization = if falloff > 0.0 {
+            trials as f64 / (falloff * center.area())
+        } else {
+            0.0
+        };
+    }
+}
+
+/// returns the ray parameter and the unit normal facing back along the ray where it meets the
+/// sphere of given signed radius centered on the axis at z_center, on the side of the lens
+/// surface, or None if it misses
+fn hit_spherical_element(ray: &Ray, radius: f64, z_center: f64) -> Option<(f64, Vec3)> {
+    let o = ray.origin() - Point3::new(0.0, 0.0, z_center);
+    let d = ray.direction();
+    let (t0, t1) = polynomial::solve_quadratic(
+        d.length_squared(),
+        2.0 * vec3::dot(&d, &o),
+        o.length_squared() - radius * radius,
+    )?;
+
+    // the surface is the half of the sphere facing away from its center along the axis
+    let use_closer = (d.z() > 0.0) != (radius < 0.0);
+    let t = if use_closer { t0.min(t1) } else { t0.max(t1) };
+    if t < 0.0 {
+        return None;
+    }
+
+    let n = vec3::unit_vector(o + t * d);
+    let n = if vec3::dot(&n, &-d) < 0.0 { -n } else { n };
+    Some((t, n))
+}
+
+/// returns the z of the principal plane and focal point of a lens from a ray entering it parallel
+/// to the axis and the ray leaving it, or None if the leaving ray is parallel too
+fn cardinal_points(r_in: &Ray, r_out: &Ray) -> Option<(f64, f64)> {
+    let dx = r_out.direction().x();
+    if dx == 0.0 {
+        return None;
+    }
+    let tf = -r_out.origin().x() / dx;
+    let tp = (r_in.origin().x() - r_out.origin().x()) / dx;
+    Some((r_out.at(tp).z(), r_out.at(tf).z()))
+}
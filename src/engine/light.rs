@@ -0,0 +1,212 @@
+// src/engine/light.rs
+
+use crate::engine::{
+    color::Color,
+    onb::Onb,
+    utils::{self, INFINITY, PI},
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a direction towards a light with the radiance it delivers along it
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub direction: Vec3, // unit vector from the shaded point towards the light
+    pub distance: f64,   // distance to the light along direction, INFINITY for distant lights
+    pub radiance: Color, // incident radiance (or intensity after falloff for delta lights)
+    pub pdf: f64,        // solid angle pdf of the direction, 1 for delta lights
+}
+
+/// a light source without geometry, reached only by explicit shadow rays
+pub trait Light {
+    /// samples a direction from point p towards the light
+    /// returns None if the light does not illuminate p
+    fn sample_li(&self, p: &Point3) -> Option<LightSample>;
+}
+
+/// an infinitesimal light emitting equally in all directions
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    position: Point3,
+    intensity: Color, // radiant intensity, attenuated by the inverse square of the distance
+}
+
+impl PointLight {
+    /// constructs new `PointLight` from given position and intensity
+    pub fn new(position: Point3, intensity: Color) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: self.intensity / distance_squared,
+            pdf: 1.0,
+        })
+    }
+}
+
+/// a point light restricted to a cone, fading smoothly between the falloff start and the cone edge
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,        // unit axis of the cone
+    intensity: Color,       // radiant intensity along the axis
+    cos_total_width: f64,   // cosine of the angle at which the light reaches zero
+    cos_falloff_start: f64, // cosine of the angle at which the light starts fading
+}
+
+impl SpotLight {
+    /// constructs new `SpotLight` at position, aimed at target, with full intensity inside
+    /// falloff_start degrees of the axis fading to zero at total_width degrees
+    pub fn new(
+        position: Point3,
+        target: Point3,
+        intensity: Color,
+        total_width: f64,
+        falloff_start: f64,
+    ) -> SpotLight {
+        let falloff_start = falloff_start.min(total_width);
+        SpotLight {
+            position,
+            direction: vec3::unit_vector(target - position),
+            intensity,
+            cos_total_width: utils::deg_to_rad(total_width).cos(),
+            cos_falloff_start: utils::deg_to_rad(falloff_start).cos(),
+        }
+    }
+
+    /// returns the fraction of the intensity emitted at the given cosine from the cone axis
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if self.cos_falloff_start <= self.cos_total_width {
+            return if cos_theta >= self.cos_total_width {
+                1.0
+            } else {
+                0.0
+            };
+        }
+        utils::smoothstep(self.cos_total_width, self.cos_falloff_start, cos_theta)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+
+        let falloff = self.falloff(vec3::dot(&-direction, &self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: falloff * self.intensity / distance_squared,
+            pdf: 1.0,
+        })
+    }
+}
+
+/// a light infinitely far away, such as the sun, illuminating the scene from one direction
+/// a non-zero angular diameter spreads it over a cone of directions, giving soft shadows
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    direction: Vec3,    // unit vector pointing from the scene towards the light
+    irradiance: Color,  // irradiance on a surface facing the light
+    cos_theta_max: f64, // cosine of the cone half angle
+}
+
+impl DirectionalLight {
+    /// constructs new `DirectionalLight` shining from the given direction with given irradiance,
+    /// and angular diameter in degrees (0 for perfectly sharp shadows; the sun is about 0.53)
+    pub fn new(direction: Vec3, irradiance: Color, angular_diameter: f64) -> DirectionalLight {
+        let half_angle = utils::deg_to_rad(angular_diameter.clamp(0.0, 180.0) / 2.0);
+        DirectionalLight {
+            direction: vec3::unit_vector(direction),
+            irradiance,
+            cos_theta_max: half_angle.cos(),
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _p: &Point3) -> Option<LightSample> {
+        let solid_angle = 2.0 * PI * (1.0 - self.cos_theta_max);
+        if solid_angle <= 0.0 {
+            return Some(LightSample {
+                direction: self.direction,
+                distance: INFINITY,
+                radiance: self.irradiance,
+                pdf: 1.0,
+            });
+        }
+
+        // uniformly sample a direction within the cone subtended by the light
+        let (u1, u2) = utils::random_2d();
+        let z = 1.0 - u1 * (1.0 - self.cos_theta_max);
+        let phi = 2.0 * PI * u2;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let uvw = Onb::new(&self.direction);
+        let direction = uvw.transform(&Vec3::new(r * phi.cos(), r * phi.sin(), z));
+
+        Some(LightSample {
+            direction,
+            distance: INFINITY,
+            radiance: self.irradiance / solid_angle,
+            pdf: 1.0 / solid_angle,
+        })
+    }
+}
+
+/// a collection of punctual lights
+#[derive(Default, Clone)]
+pub struct LightList {
+    lights: Vec<Rc<dyn Light>>,
+}
+
+impl LightList {
+    /// creates a new empty LightList
+    pub fn new() -> LightList {
+        LightList { lights: Vec::new() }
+    }
+
+    /// adds light to the list
+    pub fn add(&mut self, light: Rc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    /// clears all lights from list
+    pub fn clear(&mut self) {
+        self.lights.clear();
+    }
+
+    /// returns whether the list holds no lights
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// returns an iterator over the lights in the list
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<dyn Light>> {
+        self.lights.iter()
+    }
+}
@@ -0,0 +1,671 @@
+// src/engine/material.rs
+
+use crate::engine::{
+    color::Color,
+    hittable::HitRecord,
+    microfacet::{self, TrowbridgeReitz},
+    onb::Onb,
+    ray::Ray,
+    texture::{SolidColor, Texture},
+    utils::{self, PI},
+    vec3::{self, Vec3},
+};
+
+use std::rc::Rc;
+
+pub trait Material {
+    /// scatters an incoming ray off a surface (or within a medium)
+    /// returns false if the ray is absorbed, otherwise fills in the attenuation and scattered ray
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool;
+
+    /// returns the radiance emitted from the hit point back along the incoming ray
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        Color::zero()
+    }
+
+    /// evaluates the bsdf times the cosine term for scattering the incoming ray into the scattered direction
+    /// NOTE: `scatter` returns this value divided by `scattering_pdf` as its attenuation
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> Color {
+        Color::zero()
+    }
+
+    /// returns the solid angle pdf of `scatter` producing the scattered direction
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    /// returns whether the material scatters into a discrete set of directions, in which case
+    /// explicit light sampling is skipped and emission is gathered by the scattered ray instead
+    /// NOTE: materials without `eval` and `scattering_pdf` must stay specular
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    /// returns the surface's opacity in [0,1] at the hit point, where zero cuts the surface out
+    /// NOTE: evaluated by `Hittable::hit` after filling in the hit's surface coordinates
+    fn opacity(&self, _rec: &HitRecord) -> f64 {
+        1.0
+    }
+}
+
+/// returns whether a hit on a surface of the given material is kept, accepting partially
+/// transparent hits with probability equal to their opacity
+pub fn passes_alpha_test(mat: &dyn Material, rec: &HitRecord) -> bool {
+    let opacity = mat.opacity(rec);
+    if opacity >= 1.0 {
+        return true;
+    }
+    utils::random_0_to_1() < opacity
+}
+
+/// an ideal diffuse surface
+#[derive(Clone)]
+pub struct Lambertian {
+    tex: Rc<dyn Texture>,
+}
+
+impl Lambertian {
+    /// constructs new `Lambertian` material from given albedo
+    pub fn new(albedo: Color) -> Lambertian {
+        Lambertian::from_texture(Rc::new(SolidColor::new(albedo)))
+    }
+
+    /// constructs new `Lambertian` material whose albedo is looked up from a texture
+    pub fn from_texture(tex: Rc<dyn Texture>) -> Lambertian {
+        Lambertian { tex }
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let mut scatter_direction = rec.normal() + vec3::random_unit_vector();
+
+        // catch degenerate scatter direction
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal();
+        }
+
+        *scattered = Ray::new_with_time(rec.p(), scatter_direction, r_in.time());
+        *attenuation = self.tex.value(rec.u(), rec.v(), &rec.p());
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.tex.value(rec.u(), rec.v(), &rec.p()) * self.scattering_pdf(r_in, rec, scattered)
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = vec3::dot(&rec.normal(), &vec3::unit_vector(scattered.direction()));
+        (cos_theta / PI).max(0.0)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+}
+
+/// a phase function scattering uniformly in all directions, used inside participating media
+#[derive(Debug, Clone, Copy)]
+pub struct Isotropic {
+    albedo: Color,
+}
+
+impl Isotropic {
+    /// constructs new `Isotropic` phase function from given single-scattering albedo
+    pub fn new(albedo: Color) -> Isotropic {
+        Isotropic { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        *scattered = Ray::new_with_time(rec.p(), vec3::random_unit_vector(), r_in.time());
+        *attenuation = self.albedo;
+        true
+    }
+
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> Color {
+        self.albedo / (4.0 * PI)
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+}
+
+/// the Henyey-Greenstein phase function, used inside participating media
+/// g in (-1,1) controls anisotropy: g > 0 scatters forward, g < 0 backward, g = 0 is isotropic
+#[derive(Debug, Clone, Copy)]
+pub struct HenyeyGreenstein {
+    albedo: Color,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    /// constructs new `HenyeyGreenstein` phase function from given albedo and asymmetry parameter
+    pub fn new(albedo: Color, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein {
+            albedo,
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+
+    /// returns the phase function's asymmetry parameter
+    pub fn g(&self) -> f64 {
+        self.g
+    }
+
+    /// evaluates the phase function for the cosine of the angle between the incoming travel
+    /// direction and the scattered direction
+    pub fn evaluate(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    /// samples the cosine of the scattering angle proportionally to the phase function
+    fn sample_cos_theta(&self) -> f64 {
+        let g = self.g;
+        let u = utils::random_0_to_1();
+        if g.abs() < 1e-3 {
+            return 1.0 - 2.0 * u;
+        }
+        let sqr_term = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+        ((1.0 + g * g - sqr_term * sqr_term) / (2.0 * g)).clamp(-1.0, 1.0)
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let cos_theta = self.sample_cos_theta();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * utils::random_0_to_1();
+
+        // build the scattered direction around the incoming travel direction
+        let uvw = Onb::new(&r_in.direction());
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+
+        *scattered = Ray::new_with_time(rec.p(), uvw.transform(&local), r_in.time());
+        *attenuation = self.albedo;
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.albedo * self.scattering_pdf(r_in, rec, scattered)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, _rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = vec3::dot(
+            &vec3::unit_vector(r_in.direction()),
+            &vec3::unit_vector(scattered.direction()),
+        );
+        self.evaluate(cos_theta)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+}
+
+/// a diffuse area light emitting uniformly from the front face of its surface
+#[derive(Debug, Clone, Copy)]
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    /// constructs new `DiffuseLight` from given emitted radiance
+    pub fn new(emit: Color) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _attenuation: &mut Color,
+        _scattered: &mut Ray,
+    ) -> bool {
+        false
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        if !rec.front_face() {
+            return Color::zero();
+        }
+        self.emit
+    }
+}
+
+/// returns the shading frame around the hit normal, aligned with the surface tangent dpdu,
+/// and the outgoing direction within it
+/// NOTE: the hit normal always faces the incoming ray, so the outgoing direction has z >= 0
+fn shading_frame(r_in: &Ray, rec: &HitRecord) -> (Onb, Vec3) {
+    let uvw = Onb::from_normal_tangent(&rec.normal(), &rec.dpdu());
+    let wo = uvw.to_local(&-vec3::unit_vector(r_in.direction()));
+    (uvw, wo)
+}
+
+/// a metal described by its complex index of refraction, with GGX microfacet roughness
+#[derive(Debug, Clone, Copy)]
+pub struct Conductor {
+    eta: Color, // real part of the ior per channel
+    k: Color,   // imaginary part (absorption) of the ior per channel
+    distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    /// constructs new `Conductor` from given complex ior (eta + i k) and perceptual roughness in [0,1]
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::from_roughness(roughness),
+        }
+    }
+
+    /// constructs new gold `Conductor`
+    pub fn gold(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    /// constructs new silver `Conductor`
+    pub fn silver(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(0.155, 0.117, 0.138),
+            Color::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+
+    /// constructs new copper `Conductor`
+    pub fn copper(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    /// constructs new aluminium `Conductor`
+    pub fn aluminium(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    /// constructs new chromium `Conductor`
+    pub fn chromium(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(3.105, 3.180, 2.323),
+            Color::new(3.339, 3.330, 3.137),
+            roughness,
+        )
+    }
+
+    /// constructs new iron `Conductor`
+    pub fn iron(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(2.912, 2.950, 2.585),
+            Color::new(3.081, 2.936, 2.770),
+            roughness,
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let (uvw, wo) = shading_frame(r_in, rec);
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        if self.distribution.effectively_smooth() {
+            let wi = Vec3::new(-wo.x(), -wo.y(), wo.z());
+            *scattered = Ray::new_with_time(rec.p(), uvw.transform(&wi), r_in.time());
+            *attenuation = microfacet::fresnel_conductor(wo.z(), &self.eta, &self.k);
+            return true;
+        }
+
+        // sample a visible microfacet normal and reflect about it
+        let (u1, u2) = utils::random_2d();
+        let wm = self.distribution.sample_wm(&wo, u1, u2);
+        let wi = microfacet::reflect(&wo, &wm);
+        if wi.z() <= 0.0 {
+            return false;
+        }
+
+        let fresnel = microfacet::fresnel_conductor(vec3::dot(&wo, &wm), &self.eta, &self.k);
+        *scattered = Ray::new_with_time(rec.p(), uvw.transform(&wi), r_in.time());
+        *attenuation = fresnel * self.distribution.g(&wo, &wi) / self.distribution.g1(&wo);
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        if self.distribution.effectively_smooth() {
+            return Color::zero();
+        }
+        let (uvw, wo) = shading_frame(r_in, rec);
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::zero();
+        }
+        let wm = wo + wi;
+        if wm.near_zero() {
+            return Color::zero();
+        }
+        let wm = vec3::unit_vector(wm);
+
+        let fresnel = microfacet::fresnel_conductor(vec3::dot(&wo, &wm), &self.eta, &self.k);
+        fresnel * self.distribution.d(&wm) * self.distribution.g(&wo, &wi) / (4.0 * wo.z())
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        if self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+        let (uvw, wo) = shading_frame(r_in, rec);
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let wm = wo + wi;
+        if wm.near_zero() {
+            return 0.0;
+        }
+        let wm = vec3::unit_vector(wm);
+
+        self.distribution.d_visible(&wo, &wm) / (4.0 * vec3::dot(&wo, &wm).abs())
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.effectively_smooth()
+    }
+}
+
+/// a glass-like interface with GGX microfacet roughness, both reflecting and refracting
+#[derive(Debug, Clone, Copy)]
+pub struct RoughDielectric {
+    ior: f64, // index of refraction of the inside over the outside medium
+    distribution: TrowbridgeReitz,
+}
+
+impl RoughDielectric {
+    /// constructs new `RoughDielectric` from given index of refraction and perceptual roughness in [0,1]
+    pub fn new(ior: f64, roughness: f64) -> RoughDielectric {
+        RoughDielectric {
+            ior,
+            distribution: TrowbridgeReitz::from_roughness(roughness),
+        }
+    }
+
+    /// returns the relative ior across the interface in the direction the ray travels
+    fn relative_ior(&self, rec: &HitRecord) -> f64 {
+        if rec.front_face() {
+            self.ior
+        } else {
+            1.0 / self.ior
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let (uvw, wo) = shading_frame(r_in, rec);
+        let eta = self.relative_ior(rec);
+        *attenuation = Color::new(1.0, 1.0, 1.0);
+
+        if self.distribution.effectively_smooth() {
+            let normal = Vec3::new(0.0, 0.0, 1.0);
+            let reflectance = microfacet::fresnel_dielectric(wo.z(), eta);
+            let wi = match microfacet::refract(&wo, &normal, eta) {
+                Some(wt) if utils::random_0_to_1() >= reflectance => wt,
+                _ => microfacet::reflect(&wo, &normal),
+            };
+            *scattered = Ray::new_with_time(rec.p(), uvw.transform(&wi), r_in.time());
+            return true;
+        }
+
+        // sample a visible microfacet normal, then choose reflection or refraction by fresnel
+        let (u1, u2) = utils::random_2d();
+        let wm = self.distribution.sample_wm(&wo, u1, u2);
+        let reflectance = microfacet::fresnel_dielectric(vec3::dot(&wo, &wm), eta);
+
+        let wi = if utils::random_0_to_1() < reflectance {
+            let wr = microfacet::reflect(&wo, &wm);
+            if wr.z() <= 0.0 {
+                return false;
+            }
+            wr
+        } else {
+            match microfacet::refract(&wo, &wm, eta) {
+                Some(wt) if wt.z() < 0.0 => wt,
+                _ => return false,
+            }
+        };
+
+        *scattered = Ray::new_with_time(rec.p(), uvw.transform(&wi), r_in.time());
+        *attenuation *= self.distribution.g(&wo, &wi) / self.distribution.g1(&wo);
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        if self.distribution.effectively_smooth() {
+            return Color::zero();
+        }
+        let (uvw, wo) = shading_frame(r_in, rec);
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return Color::zero();
+        }
+        let eta = self.relative_ior(rec);
+        let wm = match microfacet::half_vector(&wo, &wi, eta) {
+            Some(wm) => wm,
+            None => return Color::zero(),
+        };
+
+        let reflectance = microfacet::fresnel_dielectric(vec3::dot(&wo, &wm), eta);
+        let d = self.distribution.d(&wm);
+        let g = self.distribution.g(&wo, &wi);
+
+        let value = if wi.z() > 0.0 {
+            d * reflectance * g / (4.0 * wo.z())
+        } else {
+            let denom = vec3::dot(&wi, &wm) + vec3::dot(&wo, &wm) / eta;
+            let denom = denom * denom;
+            d * (1.0 - reflectance) * g * (vec3::dot(&wi, &wm) * vec3::dot(&wo, &wm)).abs()
+                / (wo.z() * denom)
+        };
+        Color::new(value, value, value)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        if self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+        let (uvw, wo) = shading_frame(r_in, rec);
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return 0.0;
+        }
+        let eta = self.relative_ior(rec);
+        let wm = match microfacet::half_vector(&wo, &wi, eta) {
+            Some(wm) => wm,
+            None => return 0.0,
+        };
+
+        let reflectance = microfacet::fresnel_dielectric(vec3::dot(&wo, &wm), eta);
+        let visible = self.distribution.d_visible(&wo, &wm);
+
+        if wi.z() > 0.0 {
+            visible / (4.0 * vec3::dot(&wo, &wm).abs()) * reflectance
+        } else {
+            visible * microfacet::refraction_jacobian(&wo, &wi, &wm, eta) * (1.0 - reflectance)
+        }
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.effectively_smooth()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::engine::vec3::Point3;
+
+    /// returns a hit at the origin on a surface facing +z, with its u tangent along +x and given
+    /// v coordinate
+    pub fn hit_record(front_face: bool, v: f64) -> HitRecord {
+        let mut rec = HitRecord::new();
+        rec.set_p(Point3::zero());
+        rec.set_normal(Vec3::new(0.0, 0.0, 1.0));
+        rec.set_front_face(front_face);
+        rec.set_uv(0.5, v);
+        rec.set_tangents(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        rec
+    }
+
+    /// returns a ray arriving at the origin from above, off the normal
+    pub fn incoming_ray() -> Ray {
+        Ray::new(Point3::new(0.4, -0.3, 1.0), Vec3::new(-0.4, 0.3, -1.0))
+    }
+
+    /// checks that every scattered ray's attenuation equals `eval` over `scattering_pdf`
+    pub fn assert_attenuation_matches_eval(mat: &dyn Material, r_in: &Ray, rec: &HitRecord) {
+        let mut scattered_count = 0;
+        for _ in 0..1000 {
+            let mut attenuation = Color::zero();
+            let mut scattered = Ray::default();
+            if !mat.scatter(r_in, rec, &mut attenuation, &mut scattered) {
+                continue;
+            }
+            scattered_count += 1;
+
+            let pdf = mat.scattering_pdf(r_in, rec, &scattered);
+            assert!(pdf > 0.0, "sampled direction has zero pdf");
+            let expected = mat.eval(r_in, rec, &scattered) / pdf;
+            for axis in 0..3 {
+                let tolerance = 1e-6 * expected[axis].abs().max(1.0);
+                assert!(
+                    (attenuation[axis] - expected[axis]).abs() < tolerance,
+                    "attenuation {:?} does not match eval / pdf {:?}",
+                    attenuation,
+                    expected
+                );
+            }
+        }
+        assert!(
+            scattered_count > 500,
+            "only {scattered_count} of 1000 rays scattered"
+        );
+    }
+
+    /// returns the fraction of incoming rays that `scatter` does not absorb
+    pub fn scatter_fraction(mat: &dyn Material, r_in: &Ray, rec: &HitRecord) -> f64 {
+        let n = 20000;
+        let scattered_count = (0..n)
+            .filter(|_| {
+                let mut attenuation = Color::zero();
+                let mut scattered = Ray::default();
+                mat.scatter(r_in, rec, &mut attenuation, &mut scattered)
+            })
+            .count();
+        scattered_count as f64 / n as f64
+    }
+
+    /// checks that `scattering_pdf` integrates to the fraction of rays `scatter` samples a
+    /// direction for, so it describes the same distribution
+    pub fn assert_pdf_matches_sampling(mat: &dyn Material, r_in: &Ray, rec: &HitRecord) {
+        let integral = integrate_pdf(mat, r_in, rec);
+        let fraction = scatter_fraction(mat, r_in, rec);
+        assert!(
+            (integral - fraction).abs() < 0.01,
+            "pdf integrates to {integral} but {fraction} of rays scatter"
+        );
+    }
+
+    /// integrates `scattering_pdf` over the sphere of directions on a fine grid
+    pub fn integrate_pdf(mat: &dyn Material, r_in: &Ray, rec: &HitRecord) -> f64 {
+        let (n_z, n_phi) = (1000, 400);
+        let cell = (2.0 / n_z as f64) * (2.0 * PI / n_phi as f64);
+        let mut integral = 0.0;
+        for i in 0..n_z {
+            let z = -1.0 + 2.0 * (i as f64 + 0.5) / n_z as f64;
+            let r = (1.0 - z * z).sqrt();
+            for j in 0..n_phi {
+                let phi = 2.0 * PI * (j as f64 + 0.5) / n_phi as f64;
+                let direction = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                let scattered = Ray::new(rec.p(), direction);
+                integral += mat.scattering_pdf(r_in, rec, &scattered) * cell;
+            }
+        }
+        integral
+    }
+
+    #[test]
+    fn rough_conductor_samples_match_eval_and_pdf() {
+        let conductor = Conductor::gold(0.5);
+        let rec = hit_record(true, 0.5);
+        assert_attenuation_matches_eval(&conductor, &incoming_ray(), &rec);
+        assert_pdf_matches_sampling(&conductor, &incoming_ray(), &rec);
+    }
+
+    #[test]
+    fn rough_dielectric_samples_match_eval_and_pdf() {
+        let dielectric = RoughDielectric::new(1.5, 0.5);
+        for front_face in [true, false] {
+            let rec = hit_record(front_face, 0.5);
+            assert_attenuation_matches_eval(&dielectric, &incoming_ray(), &rec);
+            assert_pdf_matches_sampling(&dielectric, &incoming_ray(), &rec);
+        }
+    }
+}
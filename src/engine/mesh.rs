@@ -0,0 +1,396 @@
+// src/engine/mesh.rs
+
+use crate::engine::{
+    bvh::BvhNode,
+    hittable::Hittable,
+    material::Material,
+    texture::Texture,
+    triangle::Triangle,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+/// an indexed triangle mesh with per-vertex texture coordinates and normals, which can be
+/// tessellated and displaced before it is turned into triangles
+#[derive(Clone)]
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    uvs: Vec<(f64, f64)>,     // texture coordinates of each vertex
+    normals: Vec<Vec3>,       // unit normal of each vertex
+    indices: Vec<[usize; 3]>, // vertices of each triangle, counter-clockwise winding faces front
+    mat: Rc<dyn Material>,
+}
+
+impl TriangleMesh {
+    /// constructs new `TriangleMesh` from vertex positions, their texture coordinates and the
+    /// vertex indices of each triangle, with area weighted vertex normals
+    /// NOTE: empty texture coordinates put every vertex at (0,0)
+    /// returns an error if the texture coordinates do not match the positions or an index is out
+    /// of range
+    pub fn new(
+        positions: Vec<Point3>,
+        uvs: Vec<(f64, f64)>,
+        indices: Vec<[usize; 3]>,
+        mat: Rc<dyn Material>,
+    ) -> io::Result<TriangleMesh> {
+        let uvs = if uvs.is_empty() {
+            vec![(0.0, 0.0); positions.len()]
+        } else {
+            uvs
+        };
+        if uvs.len() != positions.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "mesh has {} texture coordinates for {} vertices",
+                    uvs.len(),
+                    positions.len()
+                ),
+            ));
+        }
+        if let Some(index) = indices.iter().flatten().find(|&&i| i >= positions.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "mesh index {} out of range for {} vertices",
+                    index,
+                    positions.len()
+                ),
+            ));
+        }
+
+        let mut mesh = TriangleMesh {
+            normals: vec![Vec3::zero(); positions.len()],
+            positions,
+            uvs,
+            indices,
+            mat,
+        };
+        mesh.compute_normals();
+        Ok(mesh)
+    }
+
+    /// loads a `TriangleMesh` from the vertices, texture coordinates and faces of a wavefront obj
+    /// file, splitting polygons into triangle fans
+    /// NOTE: normals in the file are ignored in favor of ones computed from the faces
+    pub fn load_obj<P: AsRef<Path>>(path: P, mat: Rc<dyn Material>) -> io::Result<TriangleMesh> {
+        let text = std::fs::read_to_string(path)?;
+        TriangleMesh::from_obj_str(&text, mat)
+    }
+
+    /// parses a `TriangleMesh` from the contents of a wavefront obj file, as in `load_obj`
+    pub fn from_obj_str(text: &str, mat: Rc<dyn Material>) -> io::Result<TriangleMesh> {
+        let obj = parse_obj(text)?;
+
+        // a vertex of the mesh for each distinct pair of position and texture coordinate
+        let mut vertices: HashMap<(usize, Option<usize>), usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+
+        for face in obj.faces() {
+            let face: Vec<usize> = face
+                .iter()
+                .map(|&(p, t)| {
+                    *vertices.entry((p, t)).or_insert_with(|| {
+                        positions.push(obj.positions()[p]);
+                        uvs.push(t.map_or((0.0, 0.0), |t| obj.uvs()[t]));
+                        positions.len() - 1
+                    })
+                })
+                .collect();
+            for k in 1..face.len() - 1 {
+                indices.push([face[0], face[k], face[k + 1]]);
+            }
+        }
+
+        TriangleMesh::new(positions, uvs, indices, mat)
+    }
+
+    /// returns the mesh's vertex positions
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    /// returns the mesh's vertex texture coordinates
+    pub fn uvs(&self) -> &[(f64, f64)] {
+        &self.uvs
+    }
+
+    /// returns the mesh's unit vertex normals
+    pub fn normals(&self) -> &[Vec3] {
+        &self.normals
+    }
+
+    /// returns the vertex indices of each of the mesh's triangles
+    pub fn indices(&self) -> &[[usize; 3]] {
+        &self.indices
+    }
+
+    /// returns the mesh's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// recomputes the vertex normals as the area weighted average of the normals of the triangles
+    /// around each position
+    /// NOTE: vertices split along texture seams share a position, so they are averaged together
+    pub fn compute_normals(&mut self) {
+        let key = |p: &Point3| [p.x().to_bits(), p.y().to_bits(), p.z().to_bits()];
+
+        // the unnormalized cross product of two edges is the face normal scaled by twice its area
+        let mut sums: HashMap<[u64; 3], Vec3> = HashMap::new();
+        for &[a, b, c] in &self.indices {
+            let (pa, pb, pc) = (self.positions[a], self.positions[b], self.positions[c]);
+            let n = vec3::cross(&(pb - pa), &(pc - pa));
+            for p in [pa, pb, pc] {
+                *sums.entry(key(&p)).or_default() += n;
+            }
+        }
+
+        self.normals = self
+            .positions
+            .iter()
+            .map(|p| match sums.get(&key(p)) {
+                Some(n) if !n.near_zero() => vec3::unit_vector(*n),
+                _ => Vec3::zero(),
+            })
+            .collect();
+    }
+
+    /// splits every triangle into four, given number of times
+    /// NOTE: new vertices lie on the flat triangles, with normals interpolated for displacement
+    pub fn subdivide(&mut self, levels: usize) {
+        for _ in 0..levels {
+            self.split_edges(|_, _| true);
+        }
+    }
+
+    /// splits every edge longer than max_edge_length, repeating up to max_levels times or until
+    /// no edge is too long
+    /// NOTE: triangles with only some of their edges split are fanned so the mesh stays closed
+    pub fn subdivide_adaptive(&mut self, max_edge_length: f64, max_levels: usize) {
+        for _ in 0..max_levels {
+            if !self.split_edges(|p, q| (*q - *p).length() > max_edge_length) {
+                break;
+            }
+        }
+    }
+
+    /// moves every vertex along its normal by the displacement texture's scalar value times
+    /// scale, then recomputes the normals of the displaced surface
+    /// NOTE: tessellate first, since only vertices move; vertices split along texture seams
+    /// move apart if the texture differs across the seam
+    pub fn displace(&mut self, map: &dyn Texture, scale: f64) {
+        for i in 0..self.positions.len() {
+            let (u, v) = self.uvs[i];
+            let height = map.scalar(u, v, &self.positions[i]);
+            self.positions[i] += scale * height * self.normals[i];
+        }
+        self.compute_normals();
+    }
+
+    /// returns the mesh's triangles, smooth shaded with the vertex normals
+    pub fn triangles(&self) -> Vec<Triangle> {
+        self.indices
+            .iter()
+            .map(|&[a, b, c]| {
+                let mut triangle = Triangle::with_uvs(
+                    [self.positions[a], self.positions[b], self.positions[c]],
+                    [self.uvs[a], self.uvs[b], self.uvs[c]],
+                    self.mat.clone(),
+                );
+                triangle.set_vertex_normals([self.normals[a], self.normals[b], self.normals[c]]);
+                triangle
+            })
+            .collect()
+    }
+
+    /// returns a bvh over the mesh's triangles
+    pub fn bvh(&self) -> BvhNode {
+        BvhNode::new(
+            self.triangles()
+                .into_iter()
+                .map(|triangle| Rc::new(triangle) as Rc<dyn Hittable>)
+                .collect(),
+        )
+    }
+
+    /// splits the edges selected by should_split at their midpoints and retriangulates
+    /// returns whether any edge was split
+    fn split_edges<F: Fn(&Point3, &Point3) -> bool>(&mut self, should_split: F) -> bool {
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut indices = Vec::with_capacity(self.indices.len() * 4);
+
+        for corners in std::mem::take(&mut self.indices) {
+            // midpoint of the edge from each corner to the next, if it is split
+            let mut mids = [None; 3];
+            for (k, mid) in mids.iter_mut().enumerate() {
+                let (a, b) = (corners[k], corners[(k + 1) % 3]);
+                if should_split(&self.positions[a], &self.positions[b]) {
+                    *mid = Some(self.midpoint(a, b, &mut midpoints));
+                }
+            }
+
+            // rotate the corners to put the split edges first, keeping the winding
+            let split_count = mids.iter().flatten().count();
+            let first = match split_count {
+                1 => (0..3).find(|&k| mids[k].is_some()).unwrap(),
+                2 => (0..3).find(|&k| mids[k].is_none()).unwrap() + 1,
+                _ => 0,
+            } % 3;
+            let [v0, v1, v2] = [0, 1, 2].map(|k| corners[(k + first) % 3]);
+            let [m0, m1, m2] = [0, 1, 2].map(|k| mids[(k + first) % 3]);
+
+            match (m0, m1, m2) {
+                (Some(m0), Some(m1), Some(m2)) => {
+                    indices.extend([[v0, m0, m2], [m0, v1, m1], [m2, m1, v2], [m0, m1, m2]])
+                }
+                (Some(m0), Some(m1), None) => {
+                    indices.push([m0, v1, m1]);
+                    // cut the remaining quad along its shorter diagonal
+                    let p = &self.positions;
+                    if (p[m1] - p[v0]).length_squared() <= (p[v2] - p[m0]).length_squared() {
+                        indices.extend([[v0, m0, m1], [v0, m1, v2]]);
+                    } else {
+                        indices.extend([[v0, m0, v2], [m0, m1, v2]]);
+                    }
+                }
+                (Some(m0), None, None) => indices.extend([[v0, m0, v2], [m0, v1, v2]]),
+                _ => indices.push([v0, v1, v2]),
+            }
+        }
+
+        self.indices = indices;
+        !midpoints.is_empty()
+    }
+
+    /// returns the vertex at the midpoint of the edge between vertices a and b, adding it if the
+    /// edge has not been split yet
+    fn midpoint(
+        &mut self,
+        a: usize,
+        b: usize,
+        midpoints: &mut HashMap<(usize, usize), usize>,
+    ) -> usize {
+        *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            let (uva, uvb) = (self.uvs[a], self.uvs[b]);
+            let n = self.normals[a] + self.normals[b];
+            self.positions
+                .push(0.5 * (self.positions[a] + self.positions[b]));
+            self.uvs
+                .push((0.5 * (uva.0 + uvb.0), 0.5 * (uva.1 + uvb.1)));
+            self.normals.push(if n.near_zero() {
+                self.normals[a]
+            } else {
+                vec3::unit_vector(n)
+            });
+            self.positions.len() - 1
+        })
+    }
+}
+
+/// the vertex positions, texture coordinates and polygons of a wavefront obj file
+pub struct ObjPolygons {
+    positions: Vec<Point3>,
+    uvs: Vec<(f64, f64)>,
+    faces: Vec<Vec<(usize, Option<usize>)>>, // position and optional uv index of each corner
+}
+
+impl ObjPolygons {
+    /// returns the file's vertex positions
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    /// returns the file's texture coordinates
+    pub fn uvs(&self) -> &[(f64, f64)] {
+        &self.uvs
+    }
+
+    /// returns the position index and optional texture coordinate index of each polygon corner
+    pub fn faces(&self) -> &[Vec<(usize, Option<usize>)>] {
+        &self.faces
+    }
+}
+
+/// parses the vertex positions, texture coordinates and polygons of a wavefront obj file
+/// returns an error on malformed numbers, out of range indices or faces with under three corners
+pub fn parse_obj(text: &str) -> io::Result<ObjPolygons> {
+    let invalid = |line: usize, msg: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("obj line {}: {}", line, msg),
+        )
+    };
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut faces = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let mut fields = line.split_whitespace();
+        let numbers = |fields: std::str::SplitWhitespace, count: usize| {
+            let values: Vec<f64> = fields
+                .take(count)
+                .map(|s| s.parse::<f64>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(number, "malformed number"))?;
+            if values.len() < count {
+                return Err(invalid(number, "too few components"));
+            }
+            Ok(values)
+        };
+
+        match fields.next() {
+            Some("v") => {
+                let xyz = numbers(fields, 3)?;
+                positions.push(Point3::new(xyz[0], xyz[1], xyz[2]));
+            }
+            Some("vt") => {
+                let uv = numbers(fields, 2)?;
+                uvs.push((uv[0], uv[1]));
+            }
+            Some("f") => {
+                // resolve 1-based or negative, relative indices
+                let resolve = |s: &str, count: usize| {
+                    let i = s
+                        .parse::<i64>()
+                        .map_err(|_| invalid(number, "malformed face index"))?;
+                    let i = if i < 0 { count as i64 + i } else { i - 1 };
+                    if i < 0 || i >= count as i64 {
+                        return Err(invalid(number, "face index out of range"));
+                    }
+                    Ok(i as usize)
+                };
+
+                let mut face = Vec::new();
+                for corner in fields {
+                    let mut parts = corner.split('/');
+                    let p = resolve(parts.next().unwrap_or(""), positions.len())?;
+                    let t = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve(s, uvs.len())?),
+                        _ => None,
+                    };
+                    face.push((p, t));
+                }
+                if face.len() < 3 {
+                    return Err(invalid(number, "face with fewer than three vertices"));
+                }
+                faces.push(face);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(ObjPolygons {
+        positions,
+        uvs,
+        faces,
+    })
+}
@@ -0,0 +1,230 @@
+// src/engine/microfacet.rs
+
+// all directions below are in a local shading frame where the surface normal is +z
+
+use crate::engine::{
+    color::Color,
+    utils::PI,
+    vec3::{self, Vec3},
+};
+
+/// the Trowbridge-Reitz (GGX) microfacet distribution with Smith masking-shadowing
+#[derive(Debug, Clone, Copy)]
+pub struct TrowbridgeReitz {
+    alpha_x: f64, // roughness along the tangent
+    alpha_y: f64, // roughness along the bitangent
+}
+
+impl TrowbridgeReitz {
+    /// constructs new `TrowbridgeReitz` distribution from given alpha along each tangent axis
+    pub fn new(alpha_x: f64, alpha_y: f64) -> TrowbridgeReitz {
+        let mut distribution = TrowbridgeReitz { alpha_x, alpha_y };
+        if !distribution.effectively_smooth() {
+            // avoid numerical trouble with extremely narrow lobes
+            distribution.alpha_x = alpha_x.max(1e-4);
+            distribution.alpha_y = alpha_y.max(1e-4);
+        }
+        distribution
+    }
+
+    /// constructs new isotropic `TrowbridgeReitz` distribution from a perceptual roughness in [0,1]
+    pub fn from_roughness(roughness: f64) -> TrowbridgeReitz {
+        let alpha = TrowbridgeReitz::roughness_to_alpha(roughness);
+        TrowbridgeReitz::new(alpha, alpha)
+    }
+
+    /// maps perceptual (pbr) roughness to the distribution's alpha parameter
+    pub fn roughness_to_alpha(roughness: f64) -> f64 {
+        let r = roughness.clamp(0.0, 1.0);
+        r * r
+    }
+
+    /// returns whether the distribution is narrow enough to be treated as a perfect mirror
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    /// returns the density of microfacet normal wm
+    pub fn d(&self, wm: &Vec3) -> f64 {
+        let cos2 = wm.z() * wm.z();
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+        let e = (wm.x() * wm.x() / (self.alpha_x * self.alpha_x)
+            + wm.y() * wm.y() / (self.alpha_y * self.alpha_y))
+            / cos2;
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos2 * cos2 * (1.0 + e) * (1.0 + e))
+    }
+
+    /// returns the Smith auxiliary function for direction w
+    fn lambda(&self, w: &Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+        let alpha2_tan2 = (w.x() * w.x() * self.alpha_x * self.alpha_x
+            + w.y() * w.y() * self.alpha_y * self.alpha_y)
+            / cos2;
+        ((1.0 + alpha2_tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// returns the fraction of microfacets visible from direction w
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// returns the fraction of microfacets visible from both wo and wi
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// returns the density of visible normal wm as seen from wo
+    pub fn d_visible(&self, wo: &Vec3, wm: &Vec3) -> f64 {
+        if wo.z() == 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) / wo.z().abs() * self.d(wm) * vec3::dot(wo, wm).abs()
+    }
+
+    /// samples a microfacet normal visible from wo, with pdf `d_visible(wo, wm)`
+    pub fn sample_wm(&self, wo: &Vec3, u1: f64, u2: f64) -> Vec3 {
+        // transform wo to the hemispherical configuration
+        let mut wh = vec3::unit_vector(Vec3::new(
+            self.alpha_x * wo.x(),
+            self.alpha_y * wo.y(),
+            wo.z(),
+        ));
+        if wh.z() < 0.0 {
+            wh = -wh;
+        }
+
+        // find an orthonormal basis for the visible normal sampling space
+        let t1 = if wh.z() < 0.99999 {
+            vec3::unit_vector(vec3::cross(&Vec3::new(0.0, 0.0, 1.0), &wh))
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vec3::cross(&wh, &t1);
+
+        // sample a uniform disk and warp it onto the visible half of the projected hemisphere
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let px = r * phi.cos();
+        let mut py = r * phi.sin();
+        let h = (1.0 - px * px).sqrt();
+        let s = (1.0 + wh.z()) / 2.0;
+        py = (1.0 - s) * h + s * py;
+
+        // reproject onto the hemisphere and transform the normal back to the ellipsoid configuration
+        let pz = (1.0 - px * px - py * py).max(0.0).sqrt();
+        let nh = px * t1 + py * t2 + pz * wh;
+        vec3::unit_vector(Vec3::new(
+            self.alpha_x * nh.x(),
+            self.alpha_y * nh.y(),
+            nh.z().max(1e-6),
+        ))
+    }
+}
+
+/// returns the unpolarized fresnel reflectance of a dielectric interface
+/// NOTE: eta is the ratio of the transmitted side's ior over the incident side's ior
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let mut cos_i = cos_theta_i.clamp(-1.0, 1.0);
+    let mut eta = eta;
+    // flip the interface orientation when arriving from the other side
+    if cos_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_i = -cos_i;
+    }
+
+    let sin2_i = 1.0 - cos_i * cos_i;
+    let sin2_t = sin2_i / (eta * eta);
+    if sin2_t >= 1.0 {
+        // total internal reflection
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).max(0.0).sqrt();
+
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parl * r_parl + r_perp * r_perp) / 2.0
+}
+
+/// returns the unpolarized fresnel reflectance of a conductor with complex ior eta + i k
+fn fresnel_complex(cos_theta_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_i = cos_theta_i.clamp(0.0, 1.0);
+    let cos2_i = cos_i * cos_i;
+    let sin2_i = 1.0 - cos2_i;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2_i;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2_i;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2_i * a2_plus_b2 + sin2_i * sin2_i;
+    let t4 = t2 * sin2_i;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    (rp + rs) / 2.0
+}
+
+/// returns the per channel fresnel reflectance of a conductor with complex ior eta + i k
+pub fn fresnel_conductor(cos_theta_i: f64, eta: &Color, k: &Color) -> Color {
+    Color::new(
+        fresnel_complex(cos_theta_i, eta.x(), k.x()),
+        fresnel_complex(cos_theta_i, eta.y(), k.y()),
+        fresnel_complex(cos_theta_i, eta.z(), k.z()),
+    )
+}
+
+/// returns the mirror direction of w about normal n
+pub fn reflect(w: &Vec3, n: &Vec3) -> Vec3 {
+    -*w + 2.0 * vec3::dot(w, n) * *n
+}
+
+/// returns the direction w refracts into through normal n (on w's side) with relative ior eta,
+/// or None on total internal reflection
+pub fn refract(w: &Vec3, n: &Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = vec3::dot(n, w);
+    let sin2_i = (1.0 - cos_i * cos_i).max(0.0);
+    let sin2_t = sin2_i / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*w / eta + (cos_i / eta - cos_t) * *n)
+}
+
+/// returns the generalized half vector of a reflected (wi.z > 0) or refracted (wi.z < 0) pair,
+/// facing +z, or None when the configuration is degenerate
+/// NOTE: eta is the relative ior across the interface as seen from wo
+pub fn half_vector(wo: &Vec3, wi: &Vec3, eta: f64) -> Option<Vec3> {
+    let wm = if wi.z() > 0.0 {
+        *wo + *wi
+    } else {
+        *wi * eta + *wo
+    };
+    if wm.near_zero() {
+        return None;
+    }
+    let mut wm = vec3::unit_vector(wm);
+    if wm.z() < 0.0 {
+        wm = -wm;
+    }
+
+    // discard microfacets that face away from either direction
+    if vec3::dot(&wm, wi) * wi.z() < 0.0 || vec3::dot(&wm, wo) * wo.z() < 0.0 {
+        return None;
+    }
+    Some(wm)
+}
+
+/// returns the jacobian |d wm / d wi| of the refraction half vector mapping
+pub fn refraction_jacobian(wo: &Vec3, wi: &Vec3, wm: &Vec3, eta: f64) -> f64 {
+    let denom = vec3::dot(wi, wm) + vec3::dot(wo, wm) / eta;
+    vec3::dot(wi, wm).abs() / (denom * denom)
+}
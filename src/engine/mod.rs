@@ -1,13 +1,48 @@
 // src/engine/mod.rs
 
 // declare submodules
+pub mod aabb;
+pub mod alpha_mask;
+pub mod aperture;
+pub mod bvh;
 pub mod camera;
+pub mod capsule;
 pub mod color;
+pub mod cone;
+pub mod constant_medium;
+pub mod csg;
+pub mod curve;
+pub mod cylinder;
+pub mod film;
+pub mod filter;
+pub mod grid_medium;
+pub mod hair;
+pub mod heightfield;
 pub mod hittable;
 pub mod hittable_list;
+pub mod image;
+pub mod instance;
 pub mod interval;
+pub mod lens_system;
+pub mod light;
+pub mod material;
+pub mod mesh;
+pub mod microfacet;
+pub mod normal_map;
+pub mod onb;
+pub mod polynomial;
+pub mod principled;
+pub mod quad;
 pub mod ray;
+pub mod ray_marched;
+pub mod sampler;
+pub mod sdf;
+pub mod sky;
 pub mod sphere;
+pub mod subdivision;
+pub mod texture;
+pub mod torus;
+pub mod triangle;
 pub mod utils;
 pub mod vec3;
 
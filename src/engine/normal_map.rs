@@ -0,0 +1,279 @@
+// src/engine/normal_map.rs
+
+// material modifiers that perturb the shading normal of a hit before handing it to another material
+
+use crate::engine::{
+    color::Color,
+    hittable::HitRecord,
+    material::Material,
+    ray::Ray,
+    texture::Texture,
+    vec3::{self, Vec3},
+};
+
+use std::rc::Rc;
+
+// step in surface coordinates used to take finite differences of a height texture
+const BUMP_DELTA: f64 = 1e-3;
+
+/// returns the outward facing normal of a hit, undoing the flip towards the incoming ray
+fn outward_normal(rec: &HitRecord) -> Vec3 {
+    if rec.front_face() {
+        rec.normal()
+    } else {
+        -rec.normal()
+    }
+}
+
+/// returns a copy of the hit record shaded with the given outward normal, keeping it facing the
+/// incoming ray and re-orthogonalizing the tangents against it
+fn with_shading_normal(rec: &HitRecord, outward: Vec3) -> HitRecord {
+    let mut shading = rec.clone();
+    if outward.near_zero() {
+        return shading;
+    }
+    let n = vec3::unit_vector(outward);
+    shading.set_normal(if rec.front_face() { n } else { -n });
+
+    let dpdu = rec.dpdu() - vec3::dot(&rec.dpdu(), &n) * n;
+    let dpdv = rec.dpdv() - vec3::dot(&rec.dpdv(), &n) * n;
+    shading.set_tangents(dpdu, dpdv);
+    shading
+}
+
+/// a modifier that perturbs the outward normal of a hit
+trait ShadingNormal {
+    /// returns the perturbed outward normal, or None if the hit has no tangent frame
+    fn shading_normal(&self, rec: &HitRecord) -> Option<Vec3>;
+}
+
+/// returns a copy of the hit record shaded with the modifier's normal
+fn shade(modifier: &impl ShadingNormal, rec: &HitRecord) -> HitRecord {
+    match modifier.shading_normal(rec) {
+        Some(n) => with_shading_normal(rec, n),
+        None => rec.clone(),
+    }
+}
+
+/// perturbs the shading normal of a material with a tangent space normal map, where the red,
+/// green and blue channels hold the normal along dpdu, dpdv and the surface normal
+#[derive(Clone)]
+pub struct NormalMap {
+    base: Rc<dyn Material>, // material shaded with the perturbed normal
+    map: Rc<dyn Texture>,   // tangent space normals encoded as color = (n + 1) / 2
+    strength: f64,          // scales the tangential part of the mapped normal
+}
+
+impl NormalMap {
+    /// constructs new `NormalMap` applying the given normal map texture to a base material
+    pub fn new(base: Rc<dyn Material>, map: Rc<dyn Texture>) -> NormalMap {
+        NormalMap::with_strength(base, map, 1.0)
+    }
+
+    /// constructs new `NormalMap` whose tangential deflection is scaled by strength
+    pub fn with_strength(base: Rc<dyn Material>, map: Rc<dyn Texture>, strength: f64) -> NormalMap {
+        NormalMap {
+            base,
+            map,
+            strength: strength.max(0.0),
+        }
+    }
+
+    /// returns the material shaded with the perturbed normal
+    pub fn base(&self) -> Rc<dyn Material> {
+        self.base.clone()
+    }
+
+    /// returns the normal map's strength
+    pub fn strength(&self) -> f64 {
+        self.strength
+    }
+}
+
+impl ShadingNormal for NormalMap {
+    fn shading_normal(&self, rec: &HitRecord) -> Option<Vec3> {
+        let n = vec3::unit_vector(outward_normal(rec));
+        let dpdu = rec.dpdu() - vec3::dot(&rec.dpdu(), &n) * n;
+        if dpdu.near_zero() {
+            return None;
+        }
+        let t = vec3::unit_vector(dpdu);
+
+        // the bitangent follows dpdv, so mirrored texture coordinates flip it
+        let mut b = vec3::cross(&n, &t);
+        if vec3::dot(&b, &rec.dpdv()) < 0.0 {
+            b = -b;
+        }
+
+        let c = self.map.value(rec.u(), rec.v(), &rec.p());
+        let mapped = 2.0 * c - Color::new(1.0, 1.0, 1.0);
+        Some(
+            self.strength * mapped.x() * t
+                + self.strength * mapped.y() * b
+                + mapped.z().max(0.0) * n,
+        )
+    }
+}
+
+/// perturbs the shading normal of a material as if the surface were displaced along its normal
+/// by a scalar height texture, without changing the geometry
+#[derive(Clone)]
+pub struct BumpMap {
+    base: Rc<dyn Material>,  // material shaded with the perturbed normal
+    height: Rc<dyn Texture>, // displacement read from the texture's first channel
+    scale: f64,              // world space displacement of a unit height
+}
+
+impl BumpMap {
+    /// constructs new `BumpMap` displacing a base material by scale times the height texture
+    pub fn new(base: Rc<dyn Material>, height: Rc<dyn Texture>, scale: f64) -> BumpMap {
+        BumpMap {
+            base,
+            height,
+            scale,
+        }
+    }
+
+    /// returns the material shaded with the perturbed normal
+    pub fn base(&self) -> Rc<dyn Material> {
+        self.base.clone()
+    }
+
+    /// returns the world space displacement of a unit height
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+}
+
+impl ShadingNormal for BumpMap {
+    fn shading_normal(&self, rec: &HitRecord) -> Option<Vec3> {
+        let (dpdu, dpdv) = (rec.dpdu(), rec.dpdv());
+        if dpdu.near_zero() || dpdv.near_zero() {
+            return None;
+        }
+        let n = vec3::unit_vector(outward_normal(rec));
+        let (u, v, p) = (rec.u(), rec.v(), rec.p());
+
+        // forward differences of the displacement along u and v
+        let height = |u: f64, v: f64, p: Vec3| self.scale * self.height.scalar(u, v, &p);
+        let h = height(u, v, p);
+        let dhdu = (height(u + BUMP_DELTA, v, p + BUMP_DELTA * dpdu) - h) / BUMP_DELTA;
+        let dhdv = (height(u, v + BUMP_DELTA, p + BUMP_DELTA * dpdv) - h) / BUMP_DELTA;
+
+        // the displaced surface's tangents, ignoring the small change of the normal itself
+        let bumped = vec3::cross(&(dpdu + dhdu * n), &(dpdv + dhdv * n));
+        if bumped.near_zero() {
+            return None;
+        }
+
+        // keep the bumped normal on the same side as the geometric one
+        Some(if vec3::dot(&bumped, &n) < 0.0 {
+            -bumped
+        } else {
+            bumped
+        })
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        self.base
+            .scatter(r_in, &shade(self, rec), attenuation, scattered)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.base.eval(r_in, &shade(self, rec), scattered)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.base.scattering_pdf(r_in, &shade(self, rec), scattered)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.base.opacity(rec)
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        self.base
+            .scatter(r_in, &shade(self, rec), attenuation, scattered)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.base.eval(r_in, &shade(self, rec), scattered)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.base.scattering_pdf(r_in, &shade(self, rec), scattered)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.base.opacity(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        material::{
+            Conductor,
+            tests::{
+                assert_attenuation_matches_eval, assert_pdf_matches_sampling, hit_record,
+                incoming_ray,
+            },
+        },
+        texture,
+    };
+
+    #[test]
+    fn flat_normal_map_keeps_the_normal() {
+        let map = NormalMap::new(
+            Rc::new(Conductor::gold(0.5)),
+            texture::constant(Color::new(0.5, 0.5, 1.0)),
+        );
+        let rec = hit_record(true, 0.5);
+        let n = map.shading_normal(&rec).unwrap();
+        assert!((vec3::unit_vector(n) - rec.normal()).length() < 1e-12);
+    }
+
+    #[test]
+    fn normal_mapped_samples_match_eval_and_pdf() {
+        let map = NormalMap::new(
+            Rc::new(Conductor::gold(0.5)),
+            texture::constant(Color::new(0.65, 0.55, 0.9)),
+        );
+        let rec = hit_record(true, 0.5);
+        assert_attenuation_matches_eval(&map, &incoming_ray(), &rec);
+        assert_pdf_matches_sampling(&map, &incoming_ray(), &rec);
+    }
+}
@@ -0,0 +1,69 @@
+// src/engine/onb.rs
+
+use crate::engine::vec3::{self, Vec3};
+
+/// an orthonormal basis built around a given direction
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    axis: [Vec3; 3],
+}
+
+impl Onb {
+    /// constructs new `Onb` whose w axis points along the given vector
+    pub fn new(n: &Vec3) -> Onb {
+        let w = vec3::unit_vector(*n);
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = vec3::unit_vector(vec3::cross(&w, &a));
+        let u = vec3::cross(&w, &v);
+
+        Onb { axis: [u, v, w] }
+    }
+
+    /// constructs new `Onb` whose w axis points along n and whose u axis follows the tangent t
+    /// projected into the plane orthogonal to n
+    /// NOTE: falls back to an arbitrary u axis when t is zero or parallel to n
+    pub fn from_normal_tangent(n: &Vec3, t: &Vec3) -> Onb {
+        let w = vec3::unit_vector(*n);
+        let projected = *t - vec3::dot(t, &w) * w;
+        if projected.near_zero() {
+            return Onb::new(n);
+        }
+        let u = vec3::unit_vector(projected);
+        let v = vec3::cross(&w, &u);
+
+        Onb { axis: [u, v, w] }
+    }
+
+    /// returns the basis' u axis
+    pub fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+
+    /// returns the basis' v axis
+    pub fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
+
+    /// returns the basis' w axis
+    pub fn w(&self) -> Vec3 {
+        self.axis[2]
+    }
+
+    /// transforms a vector from basis coordinates to world coordinates
+    pub fn transform(&self, v: &Vec3) -> Vec3 {
+        v.x() * self.axis[0] + v.y() * self.axis[1] + v.z() * self.axis[2]
+    }
+
+    /// transforms a vector from world coordinates to basis coordinates
+    pub fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            vec3::dot(v, &self.axis[0]),
+            vec3::dot(v, &self.axis[1]),
+            vec3::dot(v, &self.axis[2]),
+        )
+    }
+}
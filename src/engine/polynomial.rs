@@ -0,0 +1,205 @@
+// src/engine/polynomial.rs
+
+// real root finders for the low order polynomials that ray-surface intersections reduce to
+
+use crate::engine::utils::PI;
+
+/// returns the real roots of a x^2 + b x + c = 0 in ascending order, or None if there are none
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return None;
+        }
+        let root = -c / b;
+        return Some((root, root));
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // avoid cancellation by never subtracting nearly equal terms
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let r0 = q / a;
+    let r1 = if q != 0.0 { c / q } else { r0 };
+    Some((r0.min(r1), r0.max(r1)))
+}
+
+/// returns the real roots of x^3 + a x^2 + b x + c = 0, in no particular order
+pub fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    let offset = a / 3.0;
+
+    if q > 0.0 && r * r <= q * q * q {
+        // three real roots, found trigonometrically, two of them equal when the bound is met
+        let theta = (r / (q * q * q).sqrt()).clamp(-1.0, 1.0).acos();
+        let scale = -2.0 * q.sqrt();
+        return (0..3)
+            .map(|k| scale * ((theta + 2.0 * PI * k as f64) / 3.0).cos() - offset)
+            .collect();
+    }
+
+    // a single real root
+    let big = -r.signum() * (r.abs() + (r * r - q * q * q).sqrt()).cbrt();
+    let small = if big != 0.0 { q / big } else { 0.0 };
+    vec![big + small - offset]
+}
+
+/// returns the real roots of c[4] x^4 + c[3] x^3 + c[2] x^2 + c[1] x + c[0] = 0, in ascending order
+pub fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    if c[4] == 0.0 {
+        return solve_cubic_general(c[3], c[2], c[1], c[0]);
+    }
+
+    // normalize and depress with x = y - a/4 to y^4 + p y^2 + q y + r = 0
+    let (a, b, cc, d) = (c[3] / c[4], c[2] / c[4], c[1] / c[4], c[0] / c[4]);
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = cc - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * cc / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut ys = Vec::with_capacity(4);
+    let mut push_quadratic_roots = |b: f64, c: f64| {
+        if let Some((y0, y1)) = solve_quadratic(1.0, b, c) {
+            ys.push(y0);
+            ys.push(y1);
+        }
+    };
+
+    if q.abs() < 1e-12 {
+        // biquadratic: solve for z = y^2
+        if let Some((z0, z1)) = solve_quadratic(1.0, p, r) {
+            for z in [z0, z1] {
+                if z >= 0.0 {
+                    push_quadratic_roots(0.0, -z);
+                }
+            }
+        }
+    } else {
+        // ferrari: pick m > 0 from the resolvent cubic so both sides become perfect squares
+        let m = solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m <= 0.0 {
+            return Vec::new();
+        }
+        let s = (2.0 * m).sqrt();
+        push_quadratic_roots(-s, p / 2.0 + m + q / (2.0 * s));
+        push_quadratic_roots(s, p / 2.0 + m - q / (2.0 * s));
+    }
+
+    // undo the shift and polish each root against the original polynomial
+    let mut roots: Vec<f64> = ys
+        .into_iter()
+        .map(|y| polish_root(&c, y - a / 4.0))
+        .collect();
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+
+/// returns the real roots of a x^3 + b x^2 + c x + d = 0 in ascending order
+fn solve_cubic_general(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    let mut roots = if a == 0.0 {
+        solve_quadratic(b, c, d).map_or(Vec::new(), |(r0, r1)| vec![r0, r1])
+    } else {
+        solve_cubic(b / a, c / a, d / a)
+    };
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+
+/// refines a root of the polynomial with coefficients c (lowest order first) by newton iteration
+fn polish_root(c: &[f64; 5], mut x: f64) -> f64 {
+    for _ in 0..4 {
+        let (mut f, mut df) = (0.0, 0.0);
+        for &coefficient in c.iter().rev() {
+            df = df * x + f;
+            f = f * x + coefficient;
+        }
+        if df == 0.0 {
+            break;
+        }
+        x -= f / df;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// checks that roots, in any order, match the expected roots in ascending order
+    fn assert_roots(mut roots: Vec<f64>, expected: &[f64], tolerance: f64) {
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(
+            roots.len(),
+            expected.len(),
+            "roots {roots:?}, expected {expected:?}"
+        );
+        for (root, want) in roots.iter().zip(expected) {
+            assert!(
+                (root - want).abs() < tolerance,
+                "roots {roots:?}, expected {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn cubic_with_three_distinct_roots() {
+        // (x - 1)(x - 2)(x - 3)
+        assert_roots(solve_cubic(-6.0, 11.0, -6.0), &[1.0, 2.0, 3.0], 1e-9);
+    }
+
+    #[test]
+    fn cubic_with_one_real_root() {
+        // (x - 2)(x^2 + 1)
+        assert_roots(solve_cubic(-2.0, 1.0, -2.0), &[2.0], 1e-9);
+    }
+
+    #[test]
+    fn cubic_with_repeated_roots() {
+        // (x - 1)^2 (x - 4)
+        assert_roots(solve_cubic(-6.0, 9.0, -4.0), &[1.0, 1.0, 4.0], 1e-9);
+        // (x - 2)^3
+        assert_roots(solve_cubic(-6.0, 12.0, -8.0), &[2.0], 1e-9);
+    }
+
+    #[test]
+    fn quartic_with_four_distinct_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let roots = solve_quartic([24.0, -50.0, 35.0, -10.0, 1.0]);
+        assert_roots(roots, &[1.0, 2.0, 3.0, 4.0], 1e-9);
+    }
+
+    #[test]
+    fn quartic_with_two_real_roots() {
+        // (x - 1)(x + 2)(x^2 + 1)
+        let roots = solve_quartic([-2.0, 1.0, -1.0, 1.0, 1.0]);
+        assert_roots(roots, &[-2.0, 1.0], 1e-9);
+    }
+
+    #[test]
+    fn quartic_without_real_roots() {
+        // x^4 + 1
+        assert_roots(solve_quartic([1.0, 0.0, 0.0, 0.0, 1.0]), &[], 0.0);
+    }
+
+    #[test]
+    fn quartic_with_repeated_roots() {
+        // (x - 1)^2 (x - 3)^2
+        let roots = solve_quartic([9.0, -24.0, 22.0, -8.0, 1.0]);
+        assert_roots(roots, &[1.0, 1.0, 3.0, 3.0], 1e-6);
+        // (x - 2)^2 (x + 1)(x - 5)
+        let roots = solve_quartic([-20.0, 4.0, 15.0, -8.0, 1.0]);
+        assert_roots(roots, &[-1.0, 2.0, 2.0, 5.0], 1e-6);
+    }
+
+    #[test]
+    fn quartic_without_leading_term_is_a_cubic() {
+        // (x - 1)(x - 2)(x - 3)
+        let roots = solve_quartic([-6.0, 11.0, -6.0, 1.0, 0.0]);
+        assert_roots(roots, &[1.0, 2.0, 3.0], 1e-9);
+    }
+}
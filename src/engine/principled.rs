@@ -0,0 +1,369 @@
+// src/engine/principled.rs
+
+use crate::engine::{
+    color::{self, Color},
+    hittable::HitRecord,
+    material::Material,
+    microfacet::{self, TrowbridgeReitz},
+    onb::Onb,
+    ray::Ray,
+    texture::{self, Texture},
+    utils::{self, PI},
+    vec3::{self, Vec3},
+};
+
+use std::rc::Rc;
+
+// roughness of the clearcoat layer's microfacet distribution
+const CLEARCOAT_ROUGHNESS: f64 = 0.1;
+
+/// a principled (Disney-style) uber material layering diffuse, sheen, specular, clearcoat and
+/// transmission lobes, each parameter driven by a texture
+///
+/// scalar parameters are in [0,1] and read from their texture's first channel
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Rc<dyn Texture>, // diffuse albedo, or specular tint for metals
+    pub metallic: Rc<dyn Texture>,   // blends from dielectric to conductor
+    pub roughness: Rc<dyn Texture>,  // perceptual microfacet roughness
+    pub specular: Rc<dyn Texture>,   // dielectric reflectance, 0.5 is 4%
+    pub clearcoat: Rc<dyn Texture>,  // strength of a glossy coat on top
+    pub sheen: Rc<dyn Texture>,      // strength of grazing retro-reflection, for cloth
+    pub transmission: Rc<dyn Texture>, // blends from opaque to refractive
+    pub anisotropic: Rc<dyn Texture>, // stretches highlights along the shading tangent
+    pub opacity: Rc<dyn Texture>,    // alpha mask, zero cuts the surface out
+    pub ior: f64,                    // index of refraction used by transmission
+}
+
+/// the material's parameters resolved at a hit point
+struct Params {
+    base_color: Color,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    clearcoat: f64,
+    sheen: f64,
+    transmission: f64,
+    eta: f64, // relative ior across the interface in the direction the ray travels
+    specular_distribution: TrowbridgeReitz,
+    clearcoat_distribution: TrowbridgeReitz,
+}
+
+impl Params {
+    /// returns the weights of the diffuse, specular, clearcoat and transmission lobes
+    fn lobe_weights(&self) -> [f64; 4] {
+        let dielectric = 1.0 - self.metallic;
+        [
+            dielectric * (1.0 - self.transmission),
+            1.0,
+            0.25 * self.clearcoat,
+            dielectric * self.transmission,
+        ]
+    }
+
+    /// returns the probabilities of sampling each lobe, proportional to their weights
+    fn lobe_probabilities(&self) -> [f64; 4] {
+        let weights = self.lobe_weights();
+        let total: f64 = weights.iter().sum();
+        weights.map(|w| w / total)
+    }
+
+    /// returns the specular color at normal incidence
+    fn specular_f0(&self) -> Color {
+        let dielectric_f0 = 0.08 * self.specular;
+        (1.0 - self.metallic) * Color::new(dielectric_f0, dielectric_f0, dielectric_f0)
+            + self.metallic * self.base_color
+    }
+}
+
+/// returns the Schlick fresnel weight (1 - cos)^5
+fn schlick_weight(cos_theta: f64) -> f64 {
+    let m = (1.0 - cos_theta).clamp(0.0, 1.0);
+    let m2 = m * m;
+    m2 * m2 * m
+}
+
+impl Principled {
+    /// constructs new `Principled` material of given base color, as a rough dielectric
+    pub fn new(base_color: Color) -> Principled {
+        Principled::from_texture(texture::constant(base_color))
+    }
+
+    /// constructs new `Principled` material with a textured base color, as a rough dielectric
+    pub fn from_texture(base_color: Rc<dyn Texture>) -> Principled {
+        Principled {
+            base_color,
+            metallic: texture::constant_value(0.0),
+            roughness: texture::constant_value(0.5),
+            specular: texture::constant_value(0.5),
+            clearcoat: texture::constant_value(0.0),
+            sheen: texture::constant_value(0.0),
+            transmission: texture::constant_value(0.0),
+            anisotropic: texture::constant_value(0.0),
+            opacity: texture::constant_value(1.0),
+            ior: 1.5,
+        }
+    }
+
+    /// looks up every parameter at the hit point
+    fn params(&self, rec: &HitRecord) -> Params {
+        let (u, v, p) = (rec.u(), rec.v(), rec.p());
+        let scalar = |tex: &Rc<dyn Texture>| tex.scalar(u, v, &p).clamp(0.0, 1.0);
+
+        let roughness = scalar(&self.roughness);
+        let anisotropic = scalar(&self.anisotropic);
+
+        // keep lobes rough enough to be evaluated, and stretch them for anisotropy
+        let alpha = TrowbridgeReitz::roughness_to_alpha(roughness).max(1e-3);
+        let aspect = (1.0 - 0.9 * anisotropic).sqrt();
+        let clearcoat_alpha = TrowbridgeReitz::roughness_to_alpha(CLEARCOAT_ROUGHNESS);
+
+        Params {
+            base_color: self.base_color.value(u, v, &p),
+            metallic: scalar(&self.metallic),
+            roughness,
+            specular: scalar(&self.specular),
+            clearcoat: scalar(&self.clearcoat),
+            sheen: scalar(&self.sheen),
+            transmission: scalar(&self.transmission),
+            eta: if rec.front_face() {
+                self.ior
+            } else {
+                1.0 / self.ior
+            },
+            specular_distribution: TrowbridgeReitz::new(alpha / aspect, alpha * aspect),
+            clearcoat_distribution: TrowbridgeReitz::new(clearcoat_alpha, clearcoat_alpha),
+        }
+    }
+
+    /// evaluates the bsdf times the cosine term in the local shading frame
+    fn eval_local(&self, params: &Params, wo: &Vec3, wi: &Vec3) -> Color {
+        let [diffuse_weight, _, clearcoat_weight, transmission_weight] = params.lobe_weights();
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return Color::zero();
+        }
+
+        if wi.z() < 0.0 {
+            // refraction through a rough dielectric interface, tinted by the base color
+            if transmission_weight <= 0.0 {
+                return Color::zero();
+            }
+            let wm = match microfacet::half_vector(wo, wi, params.eta) {
+                Some(wm) => wm,
+                None => return Color::zero(),
+            };
+            let distribution = &params.specular_distribution;
+            let transmittance =
+                1.0 - microfacet::fresnel_dielectric(vec3::dot(wo, &wm), params.eta);
+            let denom = vec3::dot(wi, &wm) + vec3::dot(wo, &wm) / params.eta;
+            let value = distribution.d(&wm)
+                * transmittance
+                * distribution.g(wo, wi)
+                * (vec3::dot(wi, &wm) * vec3::dot(wo, &wm)).abs()
+                / (wo.z() * denom * denom);
+            return transmission_weight * value * params.base_color;
+        }
+
+        let wh = *wo + *wi;
+        if wh.near_zero() {
+            return Color::zero();
+        }
+        let wh = vec3::unit_vector(wh);
+        let cos_d = vec3::dot(wi, &wh);
+        let mut f = Color::zero();
+
+        // burley diffuse with retro-reflection at grazing angles, plus sheen
+        if diffuse_weight > 0.0 {
+            let fd90 = 0.5 + 2.0 * params.roughness * cos_d * cos_d;
+            let fl = schlick_weight(wi.z());
+            let fv = schlick_weight(wo.z());
+            let fd = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+            let sheen = params.sheen * schlick_weight(cos_d);
+            f += diffuse_weight
+                * (params.base_color * (fd / PI) + Color::new(sheen, sheen, sheen))
+                * wi.z();
+        }
+
+        // specular reflection with schlick fresnel between the dielectric and metallic f0
+        let distribution = &params.specular_distribution;
+        let f0 = params.specular_f0();
+        let fresnel = f0 + schlick_weight(vec3::dot(wo, &wh)) * (Color::new(1.0, 1.0, 1.0) - f0);
+        f += fresnel * distribution.d(&wh) * distribution.g(wo, wi) / (4.0 * wo.z());
+
+        // clearcoat layer with a fixed 4% reflectance
+        if clearcoat_weight > 0.0 {
+            let distribution = &params.clearcoat_distribution;
+            let fresnel = 0.04 + 0.96 * schlick_weight(vec3::dot(wo, &wh));
+            let value = clearcoat_weight * fresnel * distribution.d(&wh) * distribution.g(wo, wi)
+                / (4.0 * wo.z());
+            f += Color::new(value, value, value);
+        }
+
+        f
+    }
+
+    /// returns the pdf of sampling wi from wo in the local shading frame
+    fn pdf_local(&self, params: &Params, wo: &Vec3, wi: &Vec3) -> f64 {
+        let [p_diffuse, p_specular, p_clearcoat, p_transmission] = params.lobe_probabilities();
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return 0.0;
+        }
+
+        if wi.z() < 0.0 {
+            if p_transmission <= 0.0 {
+                return 0.0;
+            }
+            return match microfacet::half_vector(wo, wi, params.eta) {
+                Some(wm) => {
+                    p_transmission
+                        * params.specular_distribution.d_visible(wo, &wm)
+                        * microfacet::refraction_jacobian(wo, wi, &wm, params.eta)
+                }
+                None => 0.0,
+            };
+        }
+
+        let wh = *wo + *wi;
+        if wh.near_zero() {
+            return 0.0;
+        }
+        let wh = vec3::unit_vector(wh);
+        let reflect_jacobian = 1.0 / (4.0 * vec3::dot(wo, &wh).abs());
+
+        p_diffuse * wi.z() / PI
+            + p_specular * params.specular_distribution.d_visible(wo, &wh) * reflect_jacobian
+            + p_clearcoat * params.clearcoat_distribution.d_visible(wo, &wh) * reflect_jacobian
+    }
+
+    /// samples an incoming direction in the local shading frame from one of the lobes
+    fn sample_local(&self, params: &Params, wo: &Vec3) -> Option<Vec3> {
+        let [p_diffuse, p_specular, p_clearcoat, _] = params.lobe_probabilities();
+        let u = utils::random_0_to_1();
+        let (u1, u2) = utils::random_2d();
+
+        let wi = if u < p_diffuse {
+            vec3::random_cosine_direction()
+        } else if u < p_diffuse + p_specular {
+            let wm = params.specular_distribution.sample_wm(wo, u1, u2);
+            microfacet::reflect(wo, &wm)
+        } else if u < p_diffuse + p_specular + p_clearcoat {
+            let wm = params.clearcoat_distribution.sample_wm(wo, u1, u2);
+            microfacet::reflect(wo, &wm)
+        } else {
+            let wm = params.specular_distribution.sample_wm(wo, u1, u2);
+            let wt = microfacet::refract(wo, &wm, params.eta)?;
+            if wt.z() >= 0.0 {
+                return None;
+            }
+            return Some(wt);
+        };
+
+        if wi.z() <= 0.0 {
+            return None;
+        }
+        Some(wi)
+    }
+}
+
+impl Material for Principled {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let params = self.params(rec);
+        let uvw = Onb::from_normal_tangent(&rec.normal(), &rec.dpdu());
+        let wo = uvw.to_local(&-vec3::unit_vector(r_in.direction()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        let wi = match self.sample_local(&params, &wo) {
+            Some(wi) => wi,
+            None => return false,
+        };
+
+        // weight by the full mixture of lobes, so every lobe that could produce wi counts
+        let pdf = self.pdf_local(&params, &wo, &wi);
+        if pdf <= 0.0 {
+            return false;
+        }
+
+        *scattered = Ray::new_with_time(rec.p(), uvw.transform(&wi), r_in.time());
+        *attenuation = self.eval_local(&params, &wo, &wi) / pdf;
+        color::luminance(attenuation) > 0.0
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        let uvw = Onb::from_normal_tangent(&rec.normal(), &rec.dpdu());
+        let wo = uvw.to_local(&-vec3::unit_vector(r_in.direction()));
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        self.eval_local(&self.params(rec), &wo, &wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let uvw = Onb::from_normal_tangent(&rec.normal(), &rec.dpdu());
+        let wo = uvw.to_local(&-vec3::unit_vector(r_in.direction()));
+        let wi = uvw.to_local(&vec3::unit_vector(scattered.direction()));
+        self.pdf_local(&self.params(rec), &wo, &wi)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.opacity
+            .scalar(rec.u(), rec.v(), &rec.p())
+            .clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::material::tests::{
+        assert_attenuation_matches_eval, assert_pdf_matches_sampling, hit_record, incoming_ray,
+    };
+
+    /// checks the material's sampling against its eval and pdf on both sides of the surface
+    fn assert_consistent(mat: &Principled) {
+        for front_face in [true, false] {
+            let rec = hit_record(front_face, 0.5);
+            assert_attenuation_matches_eval(mat, &incoming_ray(), &rec);
+            assert_pdf_matches_sampling(mat, &incoming_ray(), &rec);
+        }
+    }
+
+    #[test]
+    fn dielectric_samples_match_eval_and_pdf() {
+        assert_consistent(&Principled::new(Color::new(0.8, 0.3, 0.2)));
+    }
+
+    #[test]
+    fn anisotropic_metal_samples_match_eval_and_pdf() {
+        let mut mat = Principled::new(Color::new(0.9, 0.7, 0.3));
+        mat.metallic = texture::constant_value(1.0);
+        mat.anisotropic = texture::constant_value(0.8);
+        assert_consistent(&mat);
+    }
+
+    #[test]
+    fn coated_cloth_samples_match_eval_and_pdf() {
+        let mut mat = Principled::new(Color::new(0.2, 0.4, 0.8));
+        mat.clearcoat = texture::constant_value(1.0);
+        mat.sheen = texture::constant_value(1.0);
+        mat.roughness = texture::constant_value(0.8);
+        assert_consistent(&mat);
+    }
+
+    #[test]
+    fn transmissive_samples_match_eval_and_pdf() {
+        let mut mat = Principled::new(Color::new(1.0, 1.0, 1.0));
+        mat.transmission = texture::constant_value(1.0);
+        mat.roughness = texture::constant_value(0.4);
+        assert_consistent(&mat);
+    }
+}
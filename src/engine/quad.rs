@@ -0,0 +1,175 @@
+// src/engine/quad.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    material::{self, Material},
+    ray::Ray,
+    utils::{self, INFINITY},
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a planar parallelogram spanned by two edge vectors from a corner
+#[derive(Clone)]
+pub struct Quad {
+    q: Point3,    // starting corner
+    u: Vec3,      // first edge vector
+    v: Vec3,      // second edge vector
+    w: Vec3,      // cached n / dot(n,n), used to find planar coordinates of a hit
+    normal: Vec3, // unit plane normal
+    d: f64,       // plane constant in dot(normal, p) = d
+    area: f64,
+    mat: Rc<dyn Material>,
+}
+
+impl Quad {
+    /// constructs new `Quad` from given corner point, edge vectors and material
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Rc<dyn Material>) -> Quad {
+        let n = vec3::cross(&u, &v);
+        let normal = vec3::unit_vector(n);
+
+        Quad {
+            q,
+            u,
+            v,
+            w: n / vec3::dot(&n, &n),
+            normal,
+            d: vec3::dot(&normal, &q),
+            area: n.length(),
+            mat,
+        }
+    }
+
+    /// returns the quad's starting corner
+    pub fn q(&self) -> Point3 {
+        self.q
+    }
+
+    /// returns the quad's first edge vector
+    pub fn u(&self) -> Vec3 {
+        self.u
+    }
+
+    /// returns the quad's second edge vector
+    pub fn v(&self) -> Vec3 {
+        self.v
+    }
+
+    /// returns the quad's surface area
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// returns whether the planar coordinates (alpha, beta) lie inside the quad
+    fn is_interior(alpha: f64, beta: f64) -> bool {
+        let unit_interval = Interval::new(0.0, 1.0);
+        unit_interval.contains(alpha) && unit_interval.contains(beta)
+    }
+
+    /// intersects the ray with the quad, applying the material's alpha test if alpha_test is set
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, alpha_test: bool) -> bool {
+        let denom = vec3::dot(&self.normal, &ray.direction());
+
+        // no hit if the ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        // no hit if the hit point parameter t is outside the ray interval
+        let t = (self.d - vec3::dot(&self.normal, &ray.origin())) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // determine if the hit point lies within the planar shape using its plane coordinates
+        let intersection = ray.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = vec3::dot(&self.w, &vec3::cross(&planar_hitpt_vector, &self.v));
+        let beta = vec3::dot(&self.w, &vec3::cross(&self.u, &planar_hitpt_vector));
+
+        if !Quad::is_interior(alpha, beta) {
+            return false;
+        }
+
+        rec.set_t(t);
+        rec.set_p(intersection);
+        rec.set_uv(alpha, beta);
+        rec.set_tangents(self.u, self.v);
+        rec.set_face_normal(ray, &self.normal);
+        rec.set_mat(self.mat.clone());
+
+        // cut out parts of the quad let the ray pass through
+        !alpha_test || material::passes_alpha_test(self.mat.as_ref(), rec)
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, true)
+    }
+
+    fn hit_ignoring_alpha(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, false)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let diagonal1 = Aabb::from_points(self.q, self.q + self.u + self.v);
+        let diagonal2 = Aabb::from_points(self.q + self.u, self.q + self.v);
+        Aabb::surrounding(&diagonal1, &diagonal2).padded()
+    }
+
+    /// returns the pdf of sampling the direction by picking a uniform point on the quad's area
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit_ignoring_alpha(
+            &Ray::new_with_time(*origin, *direction, time),
+            Interval::new(0.001, INFINITY),
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+
+        // convert the area measure to solid angle as seen from origin
+        let distance_squared = rec.t() * rec.t() * direction.length_squared();
+        let cosine = (vec3::dot(direction, &rec.normal()) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    /// returns the direction from origin to a uniformly sampled point on the quad
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        let (s, t) = utils::random_2d();
+        let p = self.q + (s * self.u) + (t * self.v);
+        p - *origin
+    }
+}
+
+/// returns the closed box with opposite corners a and b, made of six quads facing outwards
+pub fn make_box(a: Point3, b: Point3, mat: Rc<dyn Material>) -> HittableList {
+    let mut sides = HittableList::new();
+
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    let faces = [
+        (Point3::new(min.x(), min.y(), max.z()), dx, dy), // front
+        (Point3::new(max.x(), min.y(), max.z()), -dz, dy), // right
+        (Point3::new(max.x(), min.y(), min.z()), -dx, dy), // back
+        (Point3::new(min.x(), min.y(), min.z()), dz, dy), // left
+        (Point3::new(min.x(), max.y(), max.z()), dx, -dz), // top
+        (Point3::new(min.x(), min.y(), min.z()), dx, dz), // bottom
+    ];
+    for (q, u, v) in faces {
+        sides.add(Rc::new(Quad::new(q, u, v, mat.clone())));
+    }
+
+    sides
+}
@@ -3,16 +3,26 @@
 use crate::engine::vec3::{Point3, Vec3};
 
 /// a semi-infinite line
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
+    time: f64, // moment within the camera shutter interval the ray exists at
 }
 
 impl Ray {
-    /// constructs new `Ray` from given origin and direction
+    /// constructs new `Ray` from given origin and direction at time zero
     pub fn new(origin: Point3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray::new_with_time(origin, direction, 0.0)
+    }
+
+    /// constructs new `Ray` from given origin and direction at the given time
+    pub fn new_with_time(origin: Point3, direction: Vec3, time: f64) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time,
+        }
     }
 
     /// returns ray's origin point
@@ -25,6 +35,11 @@ impl Ray {
         self.direction
     }
 
+    /// returns ray's time
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
     /// sets ray's origin point
     pub fn set_origin(&mut self, origin: Point3) {
         self.origin = origin;
@@ -35,17 +50,13 @@ impl Ray {
         self.direction = direction;
     }
 
+    /// sets ray's time
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
+    }
+
     /// returns the 3d point on the 2d ray at parameter t
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + t * self.direction
     }
 }
-
-impl Default for Ray {
-    fn default() -> Self {
-        Ray {
-            origin: Point3::zero(),
-            direction: Point3::zero(),
-        }
-    }
-}
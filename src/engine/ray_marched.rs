@@ -0,0 +1,131 @@
+// src/engine/ray_marched.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{self, Material},
+    ray::Ray,
+    sdf::Sdf,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a surface given implicitly by a signed distance field, intersected by sphere tracing
+pub struct RayMarched<S: Sdf> {
+    sdf: S,
+    mat: Rc<dyn Material>,
+    max_steps: usize, // steps taken before giving up on a ray
+    epsilon: f64,     // distance to the surface that counts as a hit
+    step_scale: f64,  // fraction of the distance advanced per step, below 1 for inexact fields
+}
+
+impl<S: Sdf> RayMarched<S> {
+    /// constructs new `RayMarched` surface of a distance field and material
+    pub fn new(sdf: S, mat: Rc<dyn Material>) -> RayMarched<S> {
+        RayMarched {
+            sdf,
+            mat,
+            max_steps: 256,
+            epsilon: 1e-4,
+            step_scale: 1.0,
+        }
+    }
+
+    /// returns the surface's distance field
+    pub fn sdf(&self) -> &S {
+        &self.sdf
+    }
+
+    /// returns the surface's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// sets the number of steps taken before giving up on a ray
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps.max(1);
+    }
+
+    /// sets the distance to the surface that counts as a hit
+    pub fn set_epsilon(&mut self, epsilon: f64) {
+        self.epsilon = epsilon.max(1e-9);
+    }
+
+    /// sets the fraction of the distance advanced per step
+    /// NOTE: fields that only estimate their distance, like fractals, need a value below 1
+    pub fn set_step_scale(&mut self, step_scale: f64) {
+        self.step_scale = step_scale.clamp(1e-3, 1.0);
+    }
+
+    /// returns the field's gradient at p, by central differences on a tetrahedron
+    fn gradient(&self, p: &Point3) -> Vec3 {
+        let h = 0.5 * self.epsilon;
+        [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .fold(Vec3::zero(), |sum, k| {
+            sum + self.sdf.distance(&(*p + h * *k)) * *k
+        })
+    }
+}
+
+impl<S: Sdf> Hittable for RayMarched<S> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // only march the part of the ray inside the field's bounds
+        let span = match self.sdf.bounding_box().hit_span(ray, ray_t) {
+            Some(span) => span,
+            None => return false,
+        };
+        let direction_length = ray.direction().length();
+        if direction_length == 0.0 {
+            return false;
+        }
+
+        // rays leaving a surface start within epsilon of it, so ignore the surface until the
+        // march has moved clear of it; a march clipped to the bounds starts fresh
+        let mut t = span.min;
+        let mut escaping = t <= ray_t.min && self.sdf.distance(&ray.at(t)).abs() < self.epsilon;
+
+        for _ in 0..self.max_steps {
+            if t > span.max {
+                return false;
+            }
+            let p = ray.at(t);
+            let distance = self.sdf.distance(&p).abs();
+
+            if distance < self.epsilon && !escaping {
+                let outward_normal = vec3::unit_vector(self.gradient(&p));
+
+                rec.set_t(t);
+                rec.set_p(p);
+                // distance fields carry no parameterization
+                rec.set_uv(0.0, 0.0);
+                rec.set_tangents(Vec3::zero(), Vec3::zero());
+                rec.set_face_normal(ray, &outward_normal);
+                rec.set_mat(self.mat.clone());
+                if material::passes_alpha_test(self.mat.as_ref(), rec) {
+                    return true;
+                }
+                // a cut out hit is crossed like the start of the ray
+                escaping = true;
+            }
+            if distance >= self.epsilon {
+                escaping = false;
+            }
+
+            t += self.step_scale * distance.max(self.epsilon) / direction_length;
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.sdf.bounding_box()
+    }
+}
@@ -0,0 +1,454 @@
+// src/engine/sampler.rs
+
+// samplers hand out the random numbers of each pixel sample one dimension at a time, so the
+// camera, bsdfs and lights along a path each draw from their own well distributed dimension
+
+use crate::engine::utils;
+
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+/// the largest f64 below one, where samples are clamped so they stay in [0,1)
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// count of prime bases the halton sampler has dimensions for
+const HALTON_DIMENSIONS: usize = 1000;
+
+thread_local! {
+    static SAMPLER: RefCell<Option<Box<dyn Sampler>>> = const { RefCell::new(None) };
+}
+
+/// a source of per-dimension sample values for the samples of each pixel
+pub trait Sampler {
+    /// returns the number of samples per pixel the sampler distributes its points over
+    fn samples_per_pixel(&self) -> u32;
+
+    /// moves to sample index of pixel x,y, starting over at the first dimension
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32);
+
+    /// returns the sample value of the next dimension in [0,1)
+    fn get_1d(&mut self) -> f64;
+
+    /// returns the sample values of the next two dimensions in [0,1)^2
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+/// the kind of sampler a camera draws its pixel samples from
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SamplerType {
+    /// uniform random values, with no correlation between samples
+    #[default]
+    Independent,
+    /// one sample per stratum of each dimension, jittered within it unless jitter is false
+    Stratified { jitter: bool },
+    /// the halton sequence with owen scrambled digits
+    Halton,
+    /// the first two sobol dimensions, owen scrambled and shuffled to pad out every dimension
+    Sobol,
+}
+
+impl SamplerType {
+    /// returns a new sampler of this kind for given samples per pixel, decorrelated by seed
+    pub fn build(&self, samples_per_pixel: u32, seed: u64) -> Box<dyn Sampler> {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        match *self {
+            SamplerType::Independent => Box::new(IndependentSampler::new(samples_per_pixel)),
+            SamplerType::Stratified { jitter } => {
+                // split the pixel's samples into the squarest grid they fill exactly
+                let mut x_samples = (samples_per_pixel as f64).sqrt() as u32;
+                while !samples_per_pixel.is_multiple_of(x_samples) {
+                    x_samples -= 1;
+                }
+                Box::new(StratifiedSampler::new(
+                    x_samples,
+                    samples_per_pixel / x_samples,
+                    jitter,
+                    seed,
+                ))
+            }
+            SamplerType::Halton => Box::new(HaltonSampler::new(samples_per_pixel, seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(samples_per_pixel, seed)),
+        }
+    }
+}
+
+/// installs the sampler the random numbers of this thread are drawn from, or removes it with None
+/// so they come from the thread's random number generator again
+pub fn set_thread_sampler(sampler: Option<Box<dyn Sampler>>) {
+    SAMPLER.with(|s| *s.borrow_mut() = sampler);
+}
+
+/// moves the thread's sampler, if any, to sample index of pixel x,y
+pub fn start_pixel_sample(x: i32, y: i32, index: u32) {
+    SAMPLER.with(|s| {
+        if let Some(sampler) = s.borrow_mut().as_mut() {
+            sampler.start_pixel_sample(x, y, index);
+        }
+    });
+}
+
+/// returns the next dimension of the thread's sampler, or None without one
+pub fn next_1d() -> Option<f64> {
+    SAMPLER.with(|s| s.borrow_mut().as_mut().map(|sampler| sampler.get_1d()))
+}
+
+/// returns the next two dimensions of the thread's sampler, or None without one
+pub fn next_2d() -> Option<(f64, f64)> {
+    SAMPLER.with(|s| s.borrow_mut().as_mut().map(|sampler| sampler.get_2d()))
+}
+
+/// uniform random samples, the baseline every other sampler improves on
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    samples_per_pixel: u32,
+}
+
+impl IndependentSampler {
+    /// constructs new `IndependentSampler` for given samples per pixel
+    pub fn new(samples_per_pixel: u32) -> IndependentSampler {
+        IndependentSampler { samples_per_pixel }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, _x: i32, _y: i32, _index: u32) {}
+
+    fn get_1d(&mut self) -> f64 {
+        utils::random_independent()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (utils::random_independent(), utils::random_independent())
+    }
+}
+
+/// stratified (jittered) samples: each dimension is split into one stratum per sample, a grid of
+/// x_samples by y_samples for 2d dimensions, and every pixel visits its strata in its own order
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    x_samples: u32,
+    y_samples: u32,
+    jitter: bool, // randomizes each sample within its stratum, otherwise takes the center
+    seed: u64,
+    pixel: (i32, i32),
+    index: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    /// constructs new `StratifiedSampler` with a grid of x_samples by y_samples per pixel
+    pub fn new(x_samples: u32, y_samples: u32, jitter: bool, seed: u64) -> StratifiedSampler {
+        StratifiedSampler {
+            x_samples: x_samples.max(1),
+            y_samples: y_samples.max(1),
+            jitter,
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// returns the offset of a sample within its stratum
+    fn delta(&self) -> f64 {
+        if self.jitter {
+            utils::random_independent()
+        } else {
+            0.5
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.x_samples * self.y_samples
+    }
+
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32) {
+        self.pixel = (x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let hash = hash(self.pixel, self.dimension, self.seed);
+        let count = self.samples_per_pixel();
+        let stratum = permutation_element(self.index % count, count, hash as u32);
+        self.dimension += 1;
+
+        (stratum as f64 + self.delta()) / count as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let hash = hash(self.pixel, self.dimension, self.seed);
+        let count = self.samples_per_pixel();
+        let stratum = permutation_element(self.index % count, count, hash as u32);
+        self.dimension += 2;
+
+        let (x, y) = (stratum % self.x_samples, stratum / self.x_samples);
+        (
+            (x as f64 + self.delta()) / self.x_samples as f64,
+            (y as f64 + self.delta()) / self.y_samples as f64,
+        )
+    }
+}
+
+/// the halton sequence, with dimension d in the radical inverse of the d-th prime base, owen
+/// scrambled per pixel so neighbouring pixels don't share their error
+/// NOTE: dimensions past the table of primes fall back to independent samples
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: (i32, i32),
+    index: u32,
+    dimension: u32,
+}
+
+impl HaltonSampler {
+    /// constructs new `HaltonSampler` for given samples per pixel
+    pub fn new(samples_per_pixel: u32, seed: u64) -> HaltonSampler {
+        HaltonSampler {
+            samples_per_pixel,
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// returns the sample of the current dimension and moves on to the next
+    fn sample_dimension(&mut self) -> f64 {
+        let dimension = self.dimension as usize;
+        self.dimension += 1;
+        if dimension >= HALTON_DIMENSIONS {
+            return utils::random_independent();
+        }
+        let hash = hash(self.pixel, dimension as u32, self.seed);
+        owen_scrambled_radical_inverse(primes()[dimension], self.index as u64, hash)
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32) {
+        self.pixel = (x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.sample_dimension()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.sample_dimension(), self.sample_dimension())
+    }
+}
+
+/// owen scrambled sobol samples: every 1d or 2d draw takes the first one or two sobol dimensions,
+/// a (0,2)-sequence, at an index shuffled per pixel and dimension so draws stay uncorrelated
+/// NOTE: stratification is best at power of two samples per pixel
+#[derive(Debug, Clone)]
+pub struct SobolSampler {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: (i32, i32),
+    index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    /// constructs new `SobolSampler` for given samples per pixel
+    pub fn new(samples_per_pixel: u32, seed: u64) -> SobolSampler {
+        SobolSampler {
+            samples_per_pixel,
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// returns the shuffled sobol index of the current draw and the hash scrambling it
+    fn permuted_index(&self) -> (u32, u64) {
+        let hash = hash(self.pixel, self.dimension, self.seed);
+        let count = self.samples_per_pixel;
+        let index = permutation_element(self.index % count, count, hash as u32);
+        (index, hash)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32) {
+        self.pixel = (x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, hash) = self.permuted_index();
+        self.dimension += 1;
+        sobol_sample(0, index, (hash >> 32) as u32)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, hash) = self.permuted_index();
+        self.dimension += 2;
+        (
+            sobol_sample(0, index, hash as u32),
+            sobol_sample(1, index, (hash >> 32) as u32),
+        )
+    }
+}
+
+/// returns the owen scrambled value of sobol dimension 0 or 1 at given index
+fn sobol_sample(dimension: u32, mut index: u32, seed: u32) -> f64 {
+    // dimension 0 is the van der corput sequence, dimension 1 has the pascal matrix mod 2
+    let mut direction = 1u32 << 31;
+    let mut v = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            v ^= direction;
+        }
+        direction = if dimension == 0 {
+            direction >> 1
+        } else {
+            direction ^ (direction >> 1)
+        };
+        index >>= 1;
+    }
+    (fast_owen_scramble(v, seed) as f64 / 4294967296.0).min(ONE_MINUS_EPSILON)
+}
+
+/// returns v with each bit flipped depending on a hash of the bits above it, an owen scrambling
+/// of the binary digits (laine and karras, with the improvements of vegdahl)
+fn fast_owen_scramble(v: u32, seed: u32) -> u32 {
+    let mut v = v.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x05526c56);
+    v ^= v.wrapping_mul(0x53a22864);
+    v.reverse_bits()
+}
+
+/// returns the radical inverse of index in base, with each digit permuted by a hash of the
+/// digits before it
+fn owen_scrambled_radical_inverse(base: u64, mut index: u64, hash: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut reversed_digits = 0u64;
+    let mut inv_base_m = 1.0;
+    // keep adding digits while they still change the f64 result and fit in 64 bits
+    while 1.0 - (base - 1) as f64 * inv_base_m < 1.0 && reversed_digits <= (u64::MAX - base) / base
+    {
+        let next = index / base;
+        let digit = index - next * base;
+        let digit_hash = mix_bits(hash ^ reversed_digits);
+        let digit = permutation_element(digit as u32, base as u32, digit_hash as u32) as u64;
+        reversed_digits = reversed_digits * base + digit;
+        inv_base_m *= inv_base;
+        index = next;
+    }
+    (reversed_digits as f64 * inv_base_m).min(ONE_MINUS_EPSILON)
+}
+
+/// returns the element at index i of a pseudo random permutation of [0,l) chosen by p
+/// NOTE: from kensler's "correlated multi-jittered sampling"
+fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    // cycle walk the permutation of the enclosing power of two until it lands below l
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | (p >> 27));
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    i.wrapping_add(p) % l
+}
+
+/// returns a well mixed 64 bit hash of v
+fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5d329728ea185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81dadef4bc2dd44d);
+    v ^= v >> 33;
+    v
+}
+
+/// returns a hash of a pixel, a sample dimension and a seed
+fn hash(pixel: (i32, i32), dimension: u32, seed: u64) -> u64 {
+    let h = mix_bits(seed ^ mix_bits(dimension as u64));
+    mix_bits(h ^ ((pixel.0 as u32 as u64) << 32 | pixel.1 as u32 as u64))
+}
+
+/// returns the first primes, one base per halton dimension
+fn primes() -> &'static [u64] {
+    static PRIMES: OnceLock<Vec<u64>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut primes: Vec<u64> = Vec::with_capacity(HALTON_DIMENSIONS);
+        let mut n = 2;
+        while primes.len() < HALTON_DIMENSIONS {
+            if primes
+                .iter()
+                .take_while(|&&p| p * p <= n)
+                .all(|&p| n % p != 0)
+            {
+                primes.push(n);
+            }
+            n += 1;
+        }
+        primes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halton_samples_stay_in_unit_interval_past_all_primes() {
+        let mut sampler = SamplerType::Halton.build(16, 0);
+        for index in [0, 1, 7, 15, 1000, u32::MAX] {
+            sampler.start_pixel_sample(3, 5, index);
+            for _ in 0..(HALTON_DIMENSIONS + 50) {
+                let u = sampler.get_1d();
+                assert!((0.0..1.0).contains(&u), "sample {u} out of [0,1)");
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+// src/engine/sdf.rs
+
+// signed distance fields: negative inside a solid, positive outside, zero on its surface
+
+use crate::engine::{
+    aabb::Aabb,
+    vec3::{self, Point3, Vec3},
+};
+
+pub trait Sdf {
+    /// returns the signed distance from p to the surface, or a lower bound on it
+    /// NOTE: sphere tracing relies on the field never overestimating the true distance
+    fn distance(&self, p: &Point3) -> f64;
+
+    /// returns a box enclosing every point where the distance is negative
+    fn bounding_box(&self) -> Aabb;
+}
+
+/// a sphere of given center and radius
+#[derive(Debug, Clone, Copy)]
+pub struct SdfSphere {
+    center: Point3,
+    radius: f64,
+}
+
+impl SdfSphere {
+    /// constructs new `SdfSphere` from given center and radius
+    pub fn new(center: Point3, radius: f64) -> SdfSphere {
+        SdfSphere {
+            center,
+            radius: radius.max(0.0),
+        }
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: &Point3) -> f64 {
+        (*p - self.center).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let half = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::from_points(self.center - half, self.center + half)
+    }
+}
+
+/// an axis-aligned box, optionally with rounded edges
+#[derive(Debug, Clone, Copy)]
+pub struct SdfBox {
+    center: Point3,
+    half_extents: Vec3, // half the box's size along each axis
+    rounding: f64,      // radius of the rounded edges and corners
+}
+
+impl SdfBox {
+    /// constructs new `SdfBox` of given center and half size along each axis
+    pub fn new(center: Point3, half_extents: Vec3) -> SdfBox {
+        SdfBox::rounded(center, half_extents, 0.0)
+    }
+
+    /// constructs new `SdfBox` whose edges and corners are rounded off with given radius,
+    /// keeping its overall size
+    pub fn rounded(center: Point3, half_extents: Vec3, rounding: f64) -> SdfBox {
+        let smallest = half_extents.x().min(half_extents.y()).min(half_extents.z());
+        SdfBox {
+            center,
+            half_extents,
+            rounding: rounding.clamp(0.0, smallest.max(0.0)),
+        }
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: &Point3) -> f64 {
+        let d = *p - self.center;
+        let inner = self.half_extents - Vec3::new(self.rounding, self.rounding, self.rounding);
+        let q = Vec3::new(
+            d.x().abs() - inner.x(),
+            d.y().abs() - inner.y(),
+            d.z().abs() - inner.z(),
+        );
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside - self.rounding
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(
+            self.center - self.half_extents,
+            self.center + self.half_extents,
+        )
+    }
+}
+
+/// a torus lying in the xz plane around its center
+#[derive(Debug, Clone, Copy)]
+pub struct SdfTorus {
+    center: Point3,
+    major_radius: f64, // distance from the center to the middle of the tube
+    minor_radius: f64, // radius of the tube
+}
+
+impl SdfTorus {
+    /// constructs new `SdfTorus` around given center, with the y axis as its axis of symmetry
+    pub fn new(center: Point3, major_radius: f64, minor_radius: f64) -> SdfTorus {
+        SdfTorus {
+            center,
+            major_radius: major_radius.max(0.0),
+            minor_radius: minor_radius.max(0.0),
+        }
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: &Point3) -> f64 {
+        let d = *p - self.center;
+        let ring = (d.x() * d.x() + d.z() * d.z()).sqrt() - self.major_radius;
+        (ring * ring + d.y() * d.y()).sqrt() - self.minor_radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = self.major_radius + self.minor_radius;
+        let half = Vec3::new(extent, self.minor_radius, extent);
+        Aabb::from_points(self.center - half, self.center + half)
+    }
+}
+
+/// every point within a radius of the segment between two end points
+#[derive(Debug, Clone, Copy)]
+pub struct SdfCapsule {
+    start: Point3,
+    end: Point3,
+    radius: f64,
+}
+
+impl SdfCapsule {
+    /// constructs new `SdfCapsule` around the segment from start to end with given radius
+    pub fn new(start: Point3, end: Point3, radius: f64) -> SdfCapsule {
+        SdfCapsule {
+            start,
+            end,
+            radius: radius.max(0.0),
+        }
+    }
+}
+
+impl Sdf for SdfCapsule {
+    fn distance(&self, p: &Point3) -> f64 {
+        let pa = *p - self.start;
+        let ba = self.end - self.start;
+        let length_squared = ba.length_squared();
+        let h = if length_squared > 0.0 {
+            (vec3::dot(&pa, &ba) / length_squared).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (pa - h * ba).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let half = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::surrounding(
+            &Aabb::from_points(self.start - half, self.start + half),
+            &Aabb::from_points(self.end - half, self.end + half),
+        )
+    }
+}
+
+/// the power-n Mandelbulb fractal centered at the origin, reaching out to a radius of about 1.2
+/// NOTE: its distance is only an estimate, so march it with a step scale below one
+#[derive(Debug, Clone, Copy)]
+pub struct Mandelbulb {
+    power: f64,        // exponent of the iterated map, 8 for the classic bulb
+    iterations: usize, // more iterations resolve finer detail at a higher cost
+}
+
+impl Mandelbulb {
+    /// constructs new `Mandelbulb` of given power and iteration count
+    pub fn new(power: f64, iterations: usize) -> Mandelbulb {
+        Mandelbulb {
+            power: power.max(2.0),
+            iterations: iterations.max(1),
+        }
+    }
+}
+
+impl Default for Mandelbulb {
+    fn default() -> Self {
+        Mandelbulb::new(8.0, 12)
+    }
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: &Point3) -> f64 {
+        let mut z = *p;
+        let mut dr = 1.0; // running derivative of the map, for the distance estimate
+        let mut r = z.length();
+
+        for _ in 0..self.iterations {
+            if r > 2.0 {
+                break;
+            }
+
+            // raise z to the power in spherical coordinates, then add the point back
+            let theta = (z.z() / r).clamp(-1.0, 1.0).acos() * self.power;
+            let phi = z.y().atan2(z.x()) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+            let zr = r.powf(self.power);
+            z =
+                zr * Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                ) + *p;
+            r = z.length();
+        }
+
+        if r == 0.0 {
+            return 0.0;
+        }
+        0.5 * r.ln() * r / dr
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(
+            Point3::new(-1.25, -1.25, -1.25),
+            Point3::new(1.25, 1.25, 1.25),
+        )
+    }
+}
+
+/// the points inside either of two fields
+#[derive(Debug, Clone, Copy)]
+pub struct Union<A: Sdf, B: Sdf> {
+    a: A,
+    b: B,
+}
+
+impl<A: Sdf, B: Sdf> Union<A, B> {
+    /// constructs new `Union` of two fields
+    pub fn new(a: A, b: B) -> Union<A, B> {
+        Union { a, b }
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Union<A, B> {
+    fn distance(&self, p: &Point3) -> f64 {
+        self.a.distance(p).min(self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::surrounding(&self.a.bounding_box(), &self.b.bounding_box())
+    }
+}
+
+/// the points inside both of two fields
+#[derive(Debug, Clone, Copy)]
+pub struct Intersection<A: Sdf, B: Sdf> {
+    a: A,
+    b: B,
+}
+
+impl<A: Sdf, B: Sdf> Intersection<A, B> {
+    /// constructs new `Intersection` of two fields
+    pub fn new(a: A, b: B) -> Intersection<A, B> {
+        Intersection { a, b }
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Intersection<A, B> {
+    fn distance(&self, p: &Point3) -> f64 {
+        self.a.distance(p).max(self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::overlap(&self.a.bounding_box(), &self.b.bounding_box())
+    }
+}
+
+/// the points inside the first field but not the second
+#[derive(Debug, Clone, Copy)]
+pub struct Difference<A: Sdf, B: Sdf> {
+    a: A,
+    b: B,
+}
+
+impl<A: Sdf, B: Sdf> Difference<A, B> {
+    /// constructs new `Difference` carving b out of a
+    pub fn new(a: A, b: B) -> Difference<A, B> {
+        Difference { a, b }
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Difference<A, B> {
+    fn distance(&self, p: &Point3) -> f64 {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.a.bounding_box()
+    }
+}
+
+/// the union of two fields with the seam between them filleted over a given blend radius
+#[derive(Debug, Clone, Copy)]
+pub struct SmoothUnion<A: Sdf, B: Sdf> {
+    a: A,
+    b: B,
+    k: f64, // width of the region over which the two surfaces melt together
+}
+
+impl<A: Sdf, B: Sdf> SmoothUnion<A, B> {
+    /// constructs new `SmoothUnion` of two fields blending over radius k
+    pub fn new(a: A, b: B, k: f64) -> SmoothUnion<A, B> {
+        SmoothUnion {
+            a,
+            b,
+            k: k.max(0.0),
+        }
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SmoothUnion<A, B> {
+    fn distance(&self, p: &Point3) -> f64 {
+        let (da, db) = (self.a.distance(p), self.b.distance(p));
+        if self.k <= 0.0 {
+            return da.min(db);
+        }
+        // polynomial smooth minimum, pulling the surface out by at most k/4 along the seam
+        let h = (self.k - (da - db).abs()).max(0.0) / self.k;
+        da.min(db) - 0.25 * h * h * self.k
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::surrounding(&self.a.bounding_box(), &self.b.bounding_box()).expand(0.25 * self.k)
+    }
+}
+
+/// a morph between two fields, interpolating their distances
+#[derive(Debug, Clone, Copy)]
+pub struct Blend<A: Sdf, B: Sdf> {
+    a: A,
+    b: B,
+    t: f64, // 0 gives the first field, 1 the second
+}
+
+impl<A: Sdf, B: Sdf> Blend<A, B> {
+    /// constructs new `Blend` of two fields with weight t in [0,1] towards the second
+    pub fn new(a: A, b: B, t: f64) -> Blend<A, B> {
+        Blend {
+            a,
+            b,
+            t: t.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Blend<A, B> {
+    fn distance(&self, p: &Point3) -> f64 {
+        (1.0 - self.t) * self.a.distance(p) + self.t * self.b.distance(p)
+    }
+
+    /// NOTE: the blend is negative only where one of the fields is, so their boxes bound it
+    fn bounding_box(&self) -> Aabb {
+        Aabb::surrounding(&self.a.bounding_box(), &self.b.bounding_box())
+    }
+}
@@ -0,0 +1,211 @@
+// src/engine/sky.rs
+
+use crate::engine::{
+    color::Color,
+    light::DirectionalLight,
+    utils::{self, PI},
+    vec3::{self, Vec3},
+};
+
+// angular diameter of the sun disk in degrees
+const SUN_ANGULAR_DIAMETER: f64 = 0.53;
+
+// extraterrestrial solar illuminance, in the same kcd/m^2 based units as the sky model
+const SUN_ILLUMINANCE: f64 = 128.0;
+
+/// coefficients of the Perez sky luminance distribution for one channel
+#[derive(Debug, Clone, Copy)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    /// evaluates the distribution for view zenith angle theta and angle gamma to the sun
+    fn eval(&self, cos_theta: f64, gamma: f64) -> f64 {
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+/// the Preetham analytic daylight sky, with a sun disk matching its sun direction
+#[derive(Debug, Clone, Copy)]
+pub struct PreethamSky {
+    sun_direction: Vec3, // unit vector towards the sun, +y is up
+    turbidity: f64,
+    theta_sun: f64,    // sun zenith angle in radians
+    zenith: [f64; 3],  // zenith luminance Y and chromaticity x, y
+    perez: [Perez; 3], // distribution coefficients for Y, x and y
+    scale: f64,        // multiplier bringing sky luminance (kcd/m^2) into render units
+}
+
+impl PreethamSky {
+    /// constructs new `PreethamSky` for the given sun elevation and azimuth in degrees
+    /// and atmospheric turbidity (2 is a very clear sky, 10 is hazy)
+    /// NOTE: azimuth is measured from -z towards +x
+    pub fn new(sun_elevation: f64, sun_azimuth: f64, turbidity: f64) -> PreethamSky {
+        let elevation = utils::deg_to_rad(sun_elevation.clamp(0.0, 90.0));
+        let azimuth = utils::deg_to_rad(sun_azimuth);
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+
+        let t = turbidity.clamp(1.7, 10.0);
+        let theta_sun = PI / 2.0 - elevation;
+
+        // zenith luminance (kcd/m^2) and chromaticity
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_y = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+
+        let th = theta_sun;
+        let th2 = th * th;
+        let th3 = th2 * th;
+        let zenith_x = t * t * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_yc = t * t * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        let perez = [
+            Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+        ];
+
+        PreethamSky {
+            sun_direction,
+            turbidity: t,
+            theta_sun,
+            zenith: [zenith_y, zenith_x, zenith_yc],
+            perez,
+            scale: 0.05,
+        }
+    }
+
+    /// returns the unit vector towards the sun
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    /// returns the sky's turbidity
+    pub fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    /// returns the multiplier from sky luminance to render units
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// sets the multiplier from sky luminance to render units, which also scales the sun
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale.max(0.0);
+    }
+
+    /// returns the sky radiance arriving from the given direction, excluding the sun disk
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let unit_direction = vec3::unit_vector(direction);
+
+        // directions below the horizon see the sky at the horizon
+        let cos_theta = unit_direction.y().max(0.01);
+        let cos_gamma = vec3::dot(&unit_direction, &self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+
+        let mut yxy = [0.0; 3];
+        for (channel, value) in yxy.iter_mut().enumerate() {
+            let perez = &self.perez[channel];
+            *value = self.zenith[channel] * perez.eval(cos_theta, gamma)
+                / perez.eval(1.0, self.theta_sun);
+        }
+
+        self.scale * yxy_to_rgb(yxy[0], yxy[1], yxy[2])
+    }
+
+    /// returns the sun's color after extinction through the atmosphere, as irradiance on a
+    /// surface facing the sun
+    pub fn sun_irradiance(&self) -> Color {
+        // relative optical air mass (Kasten and Young)
+        let zenith_deg = self.theta_sun * 180.0 / PI;
+        let air_mass =
+            1.0 / (self.theta_sun.cos() + 0.50572 * (96.07995 - zenith_deg).powf(-1.6364));
+
+        // rayleigh and aerosol (angstrom) optical depths at roughly 680, 550 and 440 nm
+        let wavelengths = [0.68, 0.55, 0.44];
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let mut transmittance = [0.0; 3];
+        for (channel, t) in transmittance.iter_mut().enumerate() {
+            let lambda: f64 = wavelengths[channel];
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            *t = (-air_mass * (rayleigh + aerosol)).exp();
+        }
+
+        self.scale
+            * SUN_ILLUMINANCE
+            * Color::new(transmittance[0], transmittance[1], transmittance[2])
+    }
+
+    /// returns the sky radiance arriving from the given direction, including the sun disk
+    pub fn radiance_with_sun(&self, direction: Vec3) -> Color {
+        let sky = self.radiance(direction);
+
+        let cos_theta_max = utils::deg_to_rad(SUN_ANGULAR_DIAMETER / 2.0).cos();
+        let cos_gamma = vec3::dot(&vec3::unit_vector(direction), &self.sun_direction);
+        if cos_gamma < cos_theta_max {
+            return sky;
+        }
+
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        sky + self.sun_irradiance() / solid_angle
+    }
+
+    /// returns a directional light matching the sun disk, for sampling the sun with shadow rays
+    pub fn sun_light(&self) -> DirectionalLight {
+        DirectionalLight::new(
+            self.sun_direction,
+            self.sun_irradiance(),
+            SUN_ANGULAR_DIAMETER,
+        )
+    }
+}
+
+/// converts luminance Y and chromaticity x, y to linear srgb
+fn yxy_to_rgb(luminance: f64, x: f64, y: f64) -> Color {
+    if y <= 0.0 {
+        return Color::zero();
+    }
+    let cx = x / y * luminance;
+    let cz = (1.0 - x - y) / y * luminance;
+    let cy = luminance;
+
+    Color::new(
+        (3.2406 * cx - 1.5372 * cy - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * cy + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * cy + 1.0570 * cz).max(0.0),
+    )
+}
@@ -1,31 +1,62 @@
 // src/engine/sphere.rs
 
 use crate::engine::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
+    material::{self, Material},
+    onb::Onb,
     ray::Ray,
-    vec3::{self, Point3},
+    utils::{self, INFINITY, PI},
+    vec3::{self, Point3, Vec3},
 };
 
 use super::interval::Interval;
+use std::rc::Rc;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone)]
 pub struct Sphere {
-    center: Point3,
+    center: Ray, // center path over the shutter interval, from time 0 to time 1
     radius: f64,
+    mat: Rc<dyn Material>,
 }
 
 impl Sphere {
-    /// constructs new `Sphere` from given center point and radius length
-    pub fn new(center: Point3, radius: f64) -> Sphere {
+    /// constructs new stationary `Sphere` from given center point and radius length
+    pub fn new(center: Point3, radius: f64, mat: Rc<dyn Material>) -> Sphere {
         Sphere {
-            center,
+            center: Ray::new(center, Vec3::zero()),
             radius: radius.max(0.0),
+            mat,
         }
     }
 
-    /// returns the sphere's center point
+    /// constructs new moving `Sphere` that travels linearly from center1 at time 0 to center2 at time 1
+    pub fn new_moving(
+        center1: Point3,
+        center2: Point3,
+        radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Sphere {
+        Sphere {
+            center: Ray::new(center1, center2 - center1),
+            radius: radius.max(0.0),
+            mat,
+        }
+    }
+
+    /// returns the sphere's center point at time 0
     pub fn center(&self) -> Point3 {
-        self.center
+        self.center.origin()
+    }
+
+    /// returns the sphere's center point at the given time
+    pub fn center_at(&self, time: f64) -> Point3 {
+        self.center.at(time)
+    }
+
+    /// returns whether the sphere moves over the shutter interval
+    pub fn is_moving(&self) -> bool {
+        self.center.direction() != Vec3::zero()
     }
 
     /// returns the sphere's radius
@@ -33,20 +64,51 @@ impl Sphere {
         self.radius
     }
 
-    /// sets the sphere's center point
+    /// returns the sphere's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// sets the sphere's center point, making it stationary
     pub fn set_center(&mut self, center: Point3) {
-        self.center = center;
+        self.center = Ray::new(center, Vec3::zero());
+    }
+
+    /// sets the sphere's center points at time 0 and time 1
+    pub fn set_motion(&mut self, center1: Point3, center2: Point3) {
+        self.center = Ray::new(center1, center2 - center1);
     }
 
     /// sets the sphere's radius
     pub fn set_radius(&mut self, radius: f64) {
         self.radius = radius;
     }
-}
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let oc = self.center - ray.origin();
+    /// sets the sphere's material
+    pub fn set_mat(&mut self, mat: Rc<dyn Material>) {
+        self.mat = mat;
+    }
+
+    /// returns the partial derivatives of the surface point along u and v at the given outward
+    /// unit normal, matching the parameterization of `get_sphere_uv`
+    fn get_sphere_tangents(&self, n: &Vec3) -> (Vec3, Vec3) {
+        // keep the poles, where the u derivative vanishes, away from a division by zero
+        let sin_theta = (1.0 - n.y() * n.y()).max(0.0).sqrt().max(1e-8);
+        let dpdu = 2.0 * PI * self.radius * Vec3::new(n.z(), 0.0, -n.x());
+        let dpdv = PI
+            * self.radius
+            * Vec3::new(
+                -n.y() * n.x() / sin_theta,
+                sin_theta,
+                -n.y() * n.z() / sin_theta,
+            );
+        (dpdu, dpdv)
+    }
+
+    /// intersects the ray with the sphere, applying the material's alpha test if alpha_test is set
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, alpha_test: bool) -> bool {
+        let current_center = self.center_at(ray.time());
+        let oc = current_center - ray.origin();
         let a = ray.direction().length_squared();
         let h = vec3::dot(&ray.direction(), &oc);
         let c = oc.length_squared() - self.radius * self.radius;
@@ -57,28 +119,103 @@ impl Hittable for Sphere {
         }
         let sqrt_discriminant = discriminant.sqrt();
 
-        // find nearest root that lies in acceptable range
-        let root1 = (h - sqrt_discriminant) / a;
-        if ray_t.contains(root1) {
-            // check smaller root first
-            rec.set_t(root1);
+        // find nearest root that lies in acceptable range, checking the smaller root first and
+        // moving on to the larger one when the smaller is out of range or cut out
+        for root in [(h - sqrt_discriminant) / a, (h + sqrt_discriminant) / a] {
+            if !ray_t.contains(root) {
+                continue;
+            }
+            rec.set_t(root);
             rec.set_p(ray.at(rec.t()));
-            let outward_normal = (rec.p() - self.center()) / self.radius();
+            let outward_normal = (rec.p() - current_center) / self.radius();
             rec.set_face_normal(ray, &outward_normal);
-            return true;
+            let (u, v) = get_sphere_uv(&outward_normal);
+            rec.set_uv(u, v);
+            let (dpdu, dpdv) = self.get_sphere_tangents(&outward_normal);
+            rec.set_tangents(dpdu, dpdv);
+            rec.set_mat(self.mat.clone());
+            if !alpha_test || material::passes_alpha_test(self.mat.as_ref(), rec) {
+                return true;
+            }
         }
 
-        // if smaller root is invalid, check larger root
-        let root2 = (h + sqrt_discriminant) / a;
-        if ray_t.contains(root2) {
-            rec.set_t(root2);
-            rec.set_p(ray.at(rec.t()));
-            let outward_normal = (rec.p() - self.center()) / self.radius();
-            rec.set_face_normal(ray, &outward_normal);
-            return true;
+        // neither root is in valid interval
+        false
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, true)
+    }
+
+    fn hit_ignoring_alpha(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, false)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let half = Vec3::new(self.radius, self.radius, self.radius);
+        let start = self.center_at(0.0);
+        let end = self.center_at(1.0);
+        Aabb::surrounding(
+            &Aabb::from_points(start - half, start + half),
+            &Aabb::from_points(end - half, end + half),
+        )
+    }
+
+    /// returns the pdf of sampling the direction within the cone the sphere subtends from origin
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit_ignoring_alpha(
+            &Ray::new_with_time(*origin, *direction, time),
+            Interval::new(0.001, INFINITY),
+            &mut rec,
+        ) {
+            return 0.0;
         }
 
-        // neither root is in valid interval
-        return false;
+        let dist_squared = (self.center_at(time) - *origin).length_squared();
+        if dist_squared <= self.radius * self.radius {
+            // origin is inside the sphere, which then covers the whole sphere of directions
+            return 1.0 / (4.0 * PI);
+        }
+
+        let cos_theta_max = (1.0 - self.radius * self.radius / dist_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    /// returns a random direction within the cone the sphere subtends from origin
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let direction = self.center_at(time) - *origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return vec3::random_unit_vector();
+        }
+
+        let uvw = Onb::new(&direction);
+        uvw.transform(&random_to_sphere(self.radius, distance_squared))
     }
 }
+
+/// returns the (u,v) coordinates in [0,1] of a point on the unit sphere centered at the origin
+/// u: angle around the y axis from x = -1, v: angle from y = -1 to y = +1
+fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+    let theta = (-p.y()).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+
+    (phi / (2.0 * PI), theta / PI)
+}
+
+/// returns a random direction, about +z, towards a sphere of given radius at given squared distance
+fn random_to_sphere(radius: f64, distance_squared: f64) -> Vec3 {
+    let (r1, r2) = utils::random_2d();
+    let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * (1.0 - z * z).sqrt();
+    let y = phi.sin() * (1.0 - z * z).sqrt();
+
+    Vec3::new(x, y, z)
+}
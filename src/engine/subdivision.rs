@@ -0,0 +1,639 @@
+// src/engine/subdivision.rs
+
+// subdivision surfaces: a coarse polygon cage refined into a smooth triangle mesh, following the
+// semi-sharp crease rules of DeRose, Kass and Truong
+
+use crate::engine::{
+    bvh::BvhNode,
+    material::Material,
+    mesh::{self, TriangleMesh},
+    vec3::{Point3, Vec3},
+};
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+/// the refinement rules applied to a cage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubdivisionScheme {
+    Loop,         // for triangle cages, other polygons are split into triangle fans first
+    CatmullClark, // for quad cages, turning any polygon into quads after one level
+}
+
+/// a smooth surface given by a polygon cage, its creased edges and a number of refinement levels
+#[derive(Clone)]
+pub struct SubdivisionSurface {
+    cage: Cage,
+    scheme: SubdivisionScheme,
+    levels: usize,
+    mat: Rc<dyn Material>,
+}
+
+impl SubdivisionSurface {
+    /// constructs new `SubdivisionSurface` from cage vertex positions, the counter-clockwise
+    /// position indices of each polygon and the texture coordinates at each polygon corner,
+    /// refined twice with Loop rules if every polygon is a triangle and Catmull–Clark otherwise
+    /// NOTE: empty texture coordinates put every corner at (0,0)
+    /// returns an error if a polygon has under three corners, an index is out of range or the
+    /// texture coordinates do not match the polygons
+    pub fn new(
+        positions: Vec<Point3>,
+        faces: Vec<Vec<usize>>,
+        face_uvs: Vec<Vec<(f64, f64)>>,
+        mat: Rc<dyn Material>,
+    ) -> io::Result<SubdivisionSurface> {
+        let face_uvs = if face_uvs.is_empty() {
+            faces
+                .iter()
+                .map(|face| vec![(0.0, 0.0); face.len()])
+                .collect()
+        } else {
+            face_uvs
+        };
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        if face_uvs.len() != faces.len()
+            || faces.iter().zip(&face_uvs).any(|(f, t)| f.len() != t.len())
+        {
+            return Err(invalid(
+                "cage texture coordinates do not match its polygons".to_string(),
+            ));
+        }
+        if faces.iter().any(|face| face.len() < 3) {
+            return Err(invalid(
+                "cage polygon with fewer than three vertices".to_string(),
+            ));
+        }
+        if let Some(index) = faces.iter().flatten().find(|&&i| i >= positions.len()) {
+            return Err(invalid(format!(
+                "cage index {} out of range for {} vertices",
+                index,
+                positions.len()
+            )));
+        }
+
+        let scheme = if faces.iter().all(|face| face.len() == 3) {
+            SubdivisionScheme::Loop
+        } else {
+            SubdivisionScheme::CatmullClark
+        };
+        Ok(SubdivisionSurface {
+            cage: Cage {
+                positions,
+                faces,
+                face_uvs,
+                creases: HashMap::new(),
+            },
+            scheme,
+            levels: 2,
+            mat,
+        })
+    }
+
+    /// loads a `SubdivisionSurface` cage from the polygons of a wavefront obj file
+    pub fn load_obj<P: AsRef<Path>>(
+        path: P,
+        mat: Rc<dyn Material>,
+    ) -> io::Result<SubdivisionSurface> {
+        let text = std::fs::read_to_string(path)?;
+        SubdivisionSurface::from_obj_str(&text, mat)
+    }
+
+    /// parses a `SubdivisionSurface` cage from the contents of a wavefront obj file
+    pub fn from_obj_str(text: &str, mat: Rc<dyn Material>) -> io::Result<SubdivisionSurface> {
+        let obj = mesh::parse_obj(text)?;
+        let faces = obj
+            .faces()
+            .iter()
+            .map(|face| face.iter().map(|&(p, _)| p).collect())
+            .collect();
+        let face_uvs = obj
+            .faces()
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .map(|&(_, t)| t.map_or((0.0, 0.0), |t| obj.uvs()[t]))
+                    .collect()
+            })
+            .collect();
+        SubdivisionSurface::new(obj.positions().to_vec(), faces, face_uvs, mat)
+    }
+
+    /// returns the cage's vertex positions
+    pub fn positions(&self) -> &[Point3] {
+        &self.cage.positions
+    }
+
+    /// returns the position indices of each of the cage's polygons
+    pub fn faces(&self) -> &[Vec<usize>] {
+        &self.cage.faces
+    }
+
+    /// returns the surface's refinement rules
+    pub fn scheme(&self) -> SubdivisionScheme {
+        self.scheme
+    }
+
+    /// returns the number of times the cage is refined
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+
+    /// returns the sharpness of the edge between cage vertices a and b, zero if it is smooth
+    pub fn crease(&self, a: usize, b: usize) -> f64 {
+        self.cage.sharpness(a, b)
+    }
+
+    /// returns the surface's material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// sets the surface's refinement rules
+    pub fn set_scheme(&mut self, scheme: SubdivisionScheme) {
+        self.scheme = scheme;
+    }
+
+    /// sets the number of times the cage is refined, each level quadrupling the face count
+    pub fn set_levels(&mut self, levels: usize) {
+        self.levels = levels;
+    }
+
+    /// creases the edge between cage vertices a and b with given sharpness: the edge stays sharp
+    /// for that many levels before smoothing out, fractions blend in between
+    /// NOTE: an infinite sharpness keeps the edge sharp at every level; boundary edges always are
+    pub fn set_crease(&mut self, a: usize, b: usize, sharpness: f64) {
+        let sharpness = sharpness.max(0.0);
+        if sharpness > 0.0 {
+            self.cage.creases.insert(edge_key(a, b), sharpness);
+        } else {
+            self.cage.creases.remove(&edge_key(a, b));
+        }
+    }
+
+    /// returns the refined surface as a triangle mesh, with texture coordinates interpolated
+    /// linearly across each cage polygon
+    /// NOTE: the refined vertices approach the limit surface with every level without reaching it
+    pub fn refine(&self) -> TriangleMesh {
+        let mut cage = match self.scheme {
+            SubdivisionScheme::Loop => self.cage.triangulated(),
+            SubdivisionScheme::CatmullClark => self.cage.clone(),
+        };
+        for _ in 0..self.levels {
+            cage = match self.scheme {
+                SubdivisionScheme::Loop => cage.loop_level(),
+                SubdivisionScheme::CatmullClark => cage.catmull_clark_level(),
+            };
+        }
+        cage.triangulated().mesh(self.mat.clone())
+    }
+
+    /// returns a bvh over the triangles of the refined surface
+    pub fn bvh(&self) -> BvhNode {
+        self.refine().bvh()
+    }
+}
+
+/// returns the key of the edge between vertices a and b, independent of direction
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// returns the texture coordinate halfway between two others
+fn mid_uv(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (0.5 * (a.0 + b.0), 0.5 * (a.1 + b.1))
+}
+
+/// returns the smooth rule's point moved towards the crease rule's point by the crease's
+/// sharpness, evaluating the smooth rule only when it contributes
+fn blend<F: FnOnce() -> Point3>(smooth: F, sharp: Point3, sharpness: f64) -> Point3 {
+    if sharpness >= 1.0 {
+        sharp
+    } else if sharpness <= 0.0 {
+        smooth()
+    } else {
+        (1.0 - sharpness) * smooth() + sharpness * sharp
+    }
+}
+
+/// a polygon mesh at one level of refinement
+#[derive(Clone)]
+struct Cage {
+    positions: Vec<Point3>,
+    faces: Vec<Vec<usize>>, // position indices of each polygon, counter-clockwise
+    face_uvs: Vec<Vec<(f64, f64)>>, // texture coordinates at each polygon corner
+    creases: HashMap<(usize, usize), f64>, // sharpness of the creased edges
+}
+
+/// an edge of a cage and the polygons on either side of it
+struct Edge {
+    ends: (usize, usize),
+    faces: Vec<usize>,
+    sharpness: f64, // infinite along boundaries
+}
+
+/// the adjacency of a cage's vertices, edges and polygons
+struct Topology {
+    edges: Vec<Edge>,
+    edge_index: HashMap<(usize, usize), usize>,
+    vertex_edges: Vec<Vec<usize>>, // edges around each vertex
+    vertex_faces: Vec<Vec<usize>>, // polygons around each vertex
+}
+
+impl Cage {
+    /// returns the sharpness of the edge between vertices a and b, zero if it is smooth
+    fn sharpness(&self, a: usize, b: usize) -> f64 {
+        self.creases.get(&edge_key(a, b)).copied().unwrap_or(0.0)
+    }
+
+    /// returns the adjacency of the cage
+    fn topology(&self) -> Topology {
+        let mut topology = Topology {
+            edges: Vec::new(),
+            edge_index: HashMap::new(),
+            vertex_edges: vec![Vec::new(); self.positions.len()],
+            vertex_faces: vec![Vec::new(); self.positions.len()],
+        };
+
+        for (f, face) in self.faces.iter().enumerate() {
+            for (k, &a) in face.iter().enumerate() {
+                let b = face[(k + 1) % face.len()];
+                topology.vertex_faces[a].push(f);
+
+                let key = edge_key(a, b);
+                let e = *topology.edge_index.entry(key).or_insert_with(|| {
+                    topology.edges.push(Edge {
+                        ends: key,
+                        faces: Vec::new(),
+                        sharpness: self.sharpness(a, b),
+                    });
+                    topology.vertex_edges[a].push(topology.edges.len() - 1);
+                    topology.vertex_edges[b].push(topology.edges.len() - 1);
+                    topology.edges.len() - 1
+                });
+                topology.edges[e].faces.push(f);
+            }
+        }
+
+        // edges without a polygon on both sides are kept sharp
+        for edge in topology.edges.iter_mut() {
+            if edge.faces.len() != 2 {
+                edge.sharpness = f64::INFINITY;
+            }
+        }
+        topology
+    }
+
+    /// returns the position of vertex v under the crease rules and its sharpness, or None if
+    /// fewer than two creased edges meet at it and the smooth rule applies
+    fn sharp_vertex(&self, topology: &Topology, v: usize) -> Option<(Point3, f64)> {
+        let creased: Vec<&Edge> = topology.vertex_edges[v]
+            .iter()
+            .map(|&e| &topology.edges[e])
+            .filter(|edge| edge.sharpness > 0.0)
+            .collect();
+        if creased.len() < 2 {
+            return None;
+        }
+
+        let sharpness =
+            creased.iter().map(|edge| edge.sharpness).sum::<f64>() / creased.len() as f64;
+        let p = self.positions[v];
+        if creased.len() > 2 {
+            // corners stay put
+            return Some((p, sharpness));
+        }
+        // a crease refines like a cubic b-spline curve along its two edges
+        let other = |edge: &Edge| {
+            let (a, b) = edge.ends;
+            self.positions[if a == v { b } else { a }]
+        };
+        Some((
+            (other(creased[0]) + 6.0 * p + other(creased[1])) / 8.0,
+            sharpness,
+        ))
+    }
+
+    /// returns the cage with every polygon split into a triangle fan
+    fn triangulated(&self) -> Cage {
+        let mut faces = Vec::new();
+        let mut face_uvs = Vec::new();
+        for (face, uvs) in self.faces.iter().zip(&self.face_uvs) {
+            for k in 1..face.len() - 1 {
+                faces.push(vec![face[0], face[k], face[k + 1]]);
+                face_uvs.push(vec![uvs[0], uvs[k], uvs[k + 1]]);
+            }
+        }
+        Cage {
+            positions: self.positions.clone(),
+            faces,
+            face_uvs,
+            creases: self.creases.clone(),
+        }
+    }
+
+    /// returns the creases of the next level, where each creased edge is split at new vertex
+    /// edge_base + its index and both halves lose one unit of sharpness
+    fn child_creases(&self, topology: &Topology, edge_base: usize) -> HashMap<(usize, usize), f64> {
+        let mut creases = HashMap::new();
+        for (e, edge) in topology.edges.iter().enumerate() {
+            let sharpness = self.sharpness(edge.ends.0, edge.ends.1) - 1.0;
+            if sharpness > 0.0 {
+                creases.insert(edge_key(edge.ends.0, edge_base + e), sharpness);
+                creases.insert(edge_key(edge_base + e, edge.ends.1), sharpness);
+            }
+        }
+        creases
+    }
+
+    /// returns the cage refined once with Loop's rules, each triangle split into four
+    fn loop_level(&self) -> Cage {
+        let topology = self.topology();
+        let n_vertices = self.positions.len();
+        let mut positions = Vec::with_capacity(n_vertices + topology.edges.len());
+
+        // vertices move towards a weighted average of their neighbors
+        for v in 0..n_vertices {
+            let smooth = || {
+                let p = self.positions[v];
+                let n = topology.vertex_edges[v].len();
+                if n == 0 {
+                    return p;
+                }
+                let neighbors = topology.vertex_edges[v]
+                    .iter()
+                    .map(|&e| {
+                        let (a, b) = topology.edges[e].ends;
+                        self.positions[if a == v { b } else { a }]
+                    })
+                    .fold(Vec3::zero(), |sum, q| sum + q);
+                let beta = if n == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * n as f64)
+                };
+                (1.0 - n as f64 * beta) * p + beta * neighbors
+            };
+            positions.push(match self.sharp_vertex(&topology, v) {
+                Some((sharp, sharpness)) => blend(smooth, sharp, sharpness),
+                None => smooth(),
+            });
+        }
+
+        // edge points weigh the edge's ends and the vertices opposite it
+        for edge in &topology.edges {
+            let (a, b) = edge.ends;
+            let (pa, pb) = (self.positions[a], self.positions[b]);
+            let smooth = || {
+                let opposite = edge
+                    .faces
+                    .iter()
+                    .flat_map(|&f| self.faces[f].iter())
+                    .filter(|&&v| v != a && v != b)
+                    .fold(Vec3::zero(), |sum, &v| sum + self.positions[v]);
+                0.375 * (pa + pb) + 0.125 * opposite
+            };
+            positions.push(blend(smooth, 0.5 * (pa + pb), edge.sharpness));
+        }
+
+        let mut faces = Vec::with_capacity(4 * self.faces.len());
+        let mut face_uvs = Vec::with_capacity(4 * self.faces.len());
+        for (face, uvs) in self.faces.iter().zip(&self.face_uvs) {
+            let mid = |k: usize| {
+                let key = edge_key(face[k], face[(k + 1) % 3]);
+                n_vertices + topology.edge_index[&key]
+            };
+            let mid_uv = |k: usize| mid_uv(uvs[k], uvs[(k + 1) % 3]);
+            let [m0, m1, m2] = [mid(0), mid(1), mid(2)];
+            let [t0, t1, t2] = [mid_uv(0), mid_uv(1), mid_uv(2)];
+
+            faces.extend([
+                vec![face[0], m0, m2],
+                vec![m0, face[1], m1],
+                vec![m2, m1, face[2]],
+                vec![m0, m1, m2],
+            ]);
+            face_uvs.extend([
+                vec![uvs[0], t0, t2],
+                vec![t0, uvs[1], t1],
+                vec![t2, t1, uvs[2]],
+                vec![t0, t1, t2],
+            ]);
+        }
+
+        Cage {
+            creases: self.child_creases(&topology, n_vertices),
+            positions,
+            faces,
+            face_uvs,
+        }
+    }
+
+    /// returns the cage refined once with Catmull–Clark's rules, each polygon split into a quad
+    /// per corner
+    fn catmull_clark_level(&self) -> Cage {
+        let topology = self.topology();
+        let n_vertices = self.positions.len();
+        let face_base = n_vertices + topology.edges.len();
+
+        let face_points: Vec<Point3> = self
+            .faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .fold(Vec3::zero(), |sum, &v| sum + self.positions[v])
+                    / face.len() as f64
+            })
+            .collect();
+
+        let mut positions = Vec::with_capacity(face_base + self.faces.len());
+
+        // vertices move towards the average of the face points and edge midpoints around them
+        for v in 0..n_vertices {
+            let smooth = || {
+                let p = self.positions[v];
+                let n = topology.vertex_edges[v].len();
+                let faces = &topology.vertex_faces[v];
+                if n == 0 || faces.is_empty() {
+                    return p;
+                }
+                let q = faces
+                    .iter()
+                    .fold(Vec3::zero(), |sum, &f| sum + face_points[f])
+                    / faces.len() as f64;
+                let r = topology.vertex_edges[v]
+                    .iter()
+                    .map(|&e| {
+                        let (a, b) = topology.edges[e].ends;
+                        0.5 * (self.positions[a] + self.positions[b])
+                    })
+                    .fold(Vec3::zero(), |sum, m| sum + m)
+                    / n as f64;
+                (q + 2.0 * r + (n as f64 - 3.0) * p) / n as f64
+            };
+            positions.push(match self.sharp_vertex(&topology, v) {
+                Some((sharp, sharpness)) => blend(smooth, sharp, sharpness),
+                None => smooth(),
+            });
+        }
+
+        // edge points average the edge's ends and the face points on either side
+        for edge in &topology.edges {
+            let (pa, pb) = (self.positions[edge.ends.0], self.positions[edge.ends.1]);
+            let smooth = || {
+                let sides = edge
+                    .faces
+                    .iter()
+                    .fold(Vec3::zero(), |sum, &f| sum + face_points[f]);
+                (pa + pb + sides) / (2 + edge.faces.len()) as f64
+            };
+            positions.push(blend(smooth, 0.5 * (pa + pb), edge.sharpness));
+        }
+
+        positions.extend(face_points);
+
+        let mut faces = Vec::new();
+        let mut face_uvs = Vec::new();
+        for (f, (face, uvs)) in self.faces.iter().zip(&self.face_uvs).enumerate() {
+            let n = face.len();
+            let mid = |k: usize| {
+                let key = edge_key(face[k % n], face[(k + 1) % n]);
+                n_vertices + topology.edge_index[&key]
+            };
+            let mid_uv = |k: usize| mid_uv(uvs[k % n], uvs[(k + 1) % n]);
+            let center_uv = uvs.iter().fold((0.0, 0.0), |sum, uv| {
+                (sum.0 + uv.0 / n as f64, sum.1 + uv.1 / n as f64)
+            });
+
+            for k in 0..n {
+                let previous = k + n - 1;
+                faces.push(vec![face[k], mid(k), face_base + f, mid(previous)]);
+                face_uvs.push(vec![uvs[k], mid_uv(k), center_uv, mid_uv(previous)]);
+            }
+        }
+
+        Cage {
+            creases: self.child_creases(&topology, n_vertices),
+            positions,
+            faces,
+            face_uvs,
+        }
+    }
+
+    /// returns the triangle mesh of a triangulated cage, with a mesh vertex for each distinct
+    /// pair of position and texture coordinate
+    fn mesh(&self, mat: Rc<dyn Material>) -> TriangleMesh {
+        let mut vertices: HashMap<(usize, [u64; 2]), usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::with_capacity(self.faces.len());
+
+        for (face, face_uvs) in self.faces.iter().zip(&self.face_uvs) {
+            let mut triangle = [0; 3];
+            for (corner, (&p, &uv)) in triangle.iter_mut().zip(face.iter().zip(face_uvs)) {
+                *corner = *vertices
+                    .entry((p, [uv.0.to_bits(), uv.1.to_bits()]))
+                    .or_insert_with(|| {
+                        positions.push(self.positions[p]);
+                        uvs.push(uv);
+                        positions.len() - 1
+                    });
+            }
+            indices.push(triangle);
+        }
+
+        // indices and texture coordinates are consistent by construction
+        TriangleMesh::new(positions, uvs, indices, mat).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{color::Color, material::Lambertian};
+
+    /// returns a regular tetrahedron with every face wound outwards
+    fn tetrahedron() -> SubdivisionSurface {
+        let positions = vec![
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(1.0, -1.0, -1.0),
+            Point3::new(-1.0, 1.0, -1.0),
+            Point3::new(-1.0, -1.0, 1.0),
+        ];
+        let faces = vec![vec![0, 1, 2], vec![0, 3, 1], vec![0, 2, 3], vec![1, 3, 2]];
+        let mat = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        SubdivisionSurface::new(positions, faces, Vec::new(), mat).unwrap()
+    }
+
+    /// returns the cube spanning [-1,1] on every axis with every face wound outwards
+    fn cube() -> SubdivisionSurface {
+        let positions = (0..8)
+            .map(|i| {
+                let coord = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+                Point3::new(coord(1), coord(2), coord(4))
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        let mat = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        SubdivisionSurface::new(positions, faces, Vec::new(), mat).unwrap()
+    }
+
+    fn assert_near(p: Point3, expected: Point3) {
+        assert!(
+            (p - expected).length() < 1e-12,
+            "expected {expected:?}, got {p:?}"
+        );
+    }
+
+    #[test]
+    fn loop_level_splits_each_triangle_in_four() {
+        let surface = tetrahedron();
+        assert_eq!(surface.scheme(), SubdivisionScheme::Loop);
+
+        // one vertex per cage vertex and edge, four triangles per cage triangle
+        let refined = surface.cage.loop_level();
+        assert_eq!(refined.positions.len(), 4 + 6);
+        assert_eq!(refined.faces.len(), 4 * 4);
+        assert!(refined.faces.iter().all(|face| face.len() == 3));
+
+        // a valence three vertex keeps 7/16 of itself and 3/16 of each neighbor
+        assert_near(refined.positions[0], Point3::new(0.25, 0.25, 0.25));
+
+        let mut surface = surface;
+        surface.set_levels(1);
+        let mesh = surface.refine();
+        assert_eq!(mesh.positions().len(), 10);
+        assert_eq!(mesh.indices().len(), 16);
+    }
+
+    #[test]
+    fn catmull_clark_level_splits_each_quad_in_four() {
+        let surface = cube();
+        assert_eq!(surface.scheme(), SubdivisionScheme::CatmullClark);
+
+        // one vertex per cage vertex, edge and face, a quad per corner of each cage face
+        let refined = surface.cage.catmull_clark_level();
+        assert_eq!(refined.positions.len(), 8 + 12 + 6);
+        assert_eq!(refined.faces.len(), 6 * 4);
+        assert!(refined.faces.iter().all(|face| face.len() == 4));
+
+        // a cube corner moves to (Q + 2R) / 3 from its face points Q and edge midpoints R
+        let corner = 5.0 / 9.0;
+        assert_near(refined.positions[7], Point3::new(corner, corner, corner));
+
+        let mut surface = surface;
+        surface.set_levels(1);
+        let mesh = surface.refine();
+        assert_eq!(mesh.positions().len(), 26);
+        assert_eq!(mesh.indices().len(), 2 * 24);
+    }
+}
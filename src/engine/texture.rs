@@ -0,0 +1,126 @@
+// src/engine/texture.rs
+
+use crate::engine::{color::Color, image::Image, vec3::Point3};
+
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+pub trait Texture {
+    /// returns the texture's color at surface coordinates (u,v) and point p
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+
+    /// returns the texture's value as a scalar, read from its first channel
+    fn scalar(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        self.value(u, v, p).x()
+    }
+}
+
+/// a texture of a single constant color
+#[derive(Debug, Clone, Copy)]
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    /// constructs new `SolidColor` texture from given color
+    pub fn new(albedo: Color) -> SolidColor {
+        SolidColor { albedo }
+    }
+
+    /// constructs new `SolidColor` texture from given color components
+    pub fn from_rgb(red: f64, green: f64, blue: f64) -> SolidColor {
+        SolidColor::new(Color::new(red, green, blue))
+    }
+
+    /// constructs new `SolidColor` texture holding a scalar value in every channel
+    pub fn from_value(value: f64) -> SolidColor {
+        SolidColor::new(Color::new(value, value, value))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// a 3d checkerboard alternating between two textures
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Rc<dyn Texture>,
+    odd: Rc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    /// constructs new `CheckerTexture` with cells of given size alternating between two textures
+    pub fn new(scale: f64, even: Rc<dyn Texture>, odd: Rc<dyn Texture>) -> CheckerTexture {
+        CheckerTexture {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    /// constructs new `CheckerTexture` with cells of given size alternating between two colors
+    pub fn from_colors(scale: f64, c1: Color, c2: Color) -> CheckerTexture {
+        CheckerTexture::new(
+            scale,
+            Rc::new(SolidColor::new(c1)),
+            Rc::new(SolidColor::new(c2)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+
+        if (x + y + z).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// a texture looked up from an image by surface coordinates
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    /// constructs new `ImageTexture` from an image held in memory
+    pub fn new(image: Image) -> ImageTexture {
+        ImageTexture { image }
+    }
+
+    /// constructs new `ImageTexture` from a ppm file
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ImageTexture> {
+        Ok(ImageTexture::new(Image::load_ppm(path)?))
+    }
+
+    /// returns the texture's image
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        self.image.sample_bilinear(u, v)
+    }
+}
+
+/// returns a shared constant texture of the given color, for parameters that accept textures
+pub fn constant(color: Color) -> Rc<dyn Texture> {
+    Rc::new(SolidColor::new(color))
+}
+
+/// returns a shared constant texture holding a scalar value
+pub fn constant_value(value: f64) -> Rc<dyn Texture> {
+    Rc::new(SolidColor::from_value(value))
+}
@@ -0,0 +1,168 @@
+// src/engine/torus.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{self, Material},
+    onb::Onb,
+    polynomial,
+    ray::Ray,
+    utils::PI,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a ring torus swept by a tube of minor radius around a circle of major radius
+#[derive(Clone)]
+pub struct Torus {
+    center: Point3,
+    frame: Onb,        // local frame whose w axis is the torus' axis of symmetry
+    major_radius: f64, // distance from the center to the middle of the tube
+    minor_radius: f64, // radius of the tube
+    mat: Rc<dyn Material>,
+}
+
+impl Torus {
+    /// constructs new `Torus` around given center and axis of symmetry
+    pub fn new(
+        center: Point3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Torus {
+        Torus {
+            center,
+            frame: Onb::new(&axis),
+            major_radius: major_radius.max(0.0),
+            minor_radius: minor_radius.max(0.0),
+            mat,
+        }
+    }
+
+    /// returns the torus' center
+    pub fn center(&self) -> Point3 {
+        self.center
+    }
+
+    /// returns the torus' axis of symmetry
+    pub fn axis(&self) -> Vec3 {
+        self.frame.w()
+    }
+
+    /// returns the distance from the center to the middle of the tube
+    pub fn major_radius(&self) -> f64 {
+        self.major_radius
+    }
+
+    /// returns the radius of the tube
+    pub fn minor_radius(&self) -> f64 {
+        self.minor_radius
+    }
+
+    /// returns the torus' material
+    pub fn mat(&self) -> Rc<dyn Material> {
+        self.mat.clone()
+    }
+
+    /// returns the local space outward normal, uv and tangents at a local point on the surface
+    /// u runs around the axis and v around the tube, starting from its outer equator
+    fn surface_at(&self, p: &Point3) -> (Vec3, (f64, f64), Vec3, Vec3) {
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+        let ring = (p.x() * p.x() + p.y() * p.y()).sqrt();
+
+        // gradient of the implicit surface, pointing away from the tube's center circle
+        let k = p.length_squared() + big_r * big_r - small_r * small_r;
+        let normal = vec3::unit_vector(k * *p - 2.0 * big_r * big_r * Vec3::new(p.x(), p.y(), 0.0));
+
+        let phi = p.y().atan2(p.x());
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let theta = p.z().atan2(ring - big_r);
+        let theta = if theta < 0.0 { theta + 2.0 * PI } else { theta };
+        let uv = (phi / (2.0 * PI), theta / (2.0 * PI));
+
+        let dpdu = 2.0 * PI * Vec3::new(-p.y(), p.x(), 0.0);
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let dpdv =
+            2.0 * PI * small_r * Vec3::new(-sin_theta * cos_phi, -sin_theta * sin_phi, cos_theta);
+        (normal, uv, dpdu, dpdv)
+    }
+
+    /// returns the box enclosing the torus in its local frame
+    fn local_bounding_box(&self) -> Aabb {
+        let extent = self.major_radius + self.minor_radius;
+        Aabb::from_points(
+            Point3::new(-extent, -extent, -self.minor_radius),
+            Point3::new(extent, extent, self.minor_radius),
+        )
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if self.minor_radius <= 0.0 {
+            return false;
+        }
+
+        // move the ray into the local frame with a unit direction, so distances are comparable
+        let direction_length = ray.direction().length();
+        let o = self.frame.to_local(&(ray.origin() - self.center));
+        let d = self.frame.to_local(&ray.direction()) / direction_length;
+
+        // restart the ray where it enters the bounding box, keeping the quartic's roots small
+        // and well conditioned even for distant origins
+        let local_ray = Ray::new(o, d);
+        let span = match self.local_bounding_box().hit_span(
+            &local_ray,
+            Interval::new(ray_t.min * direction_length, ray_t.max * direction_length),
+        ) {
+            Some(span) => span,
+            None => return false,
+        };
+        let start = span.min;
+        let o = o + start * d;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2) along p = o + s d
+        let big_r2 = self.major_radius * self.major_radius;
+        let n = vec3::dot(&o, &d);
+        let k = o.length_squared() + big_r2 - self.minor_radius * self.minor_radius;
+        let planar_dd = d.x() * d.x() + d.y() * d.y();
+        let planar_od = o.x() * d.x() + o.y() * d.y();
+        let planar_oo = o.x() * o.x() + o.y() * o.y();
+        let roots = polynomial::solve_quartic([
+            k * k - 4.0 * big_r2 * planar_oo,
+            4.0 * n * k - 8.0 * big_r2 * planar_od,
+            4.0 * n * n + 2.0 * k - 4.0 * big_r2 * planar_dd,
+            4.0 * n,
+            1.0,
+        ]);
+
+        for s in roots {
+            let t = (start + s) / direction_length;
+            if !ray_t.contains(t) {
+                continue;
+            }
+            let local_p = o + s * d;
+            let (normal, (u, v), dpdu, dpdv) = self.surface_at(&local_p);
+
+            rec.set_t(t);
+            rec.set_p(ray.at(t));
+            rec.set_uv(u, v);
+            rec.set_tangents(self.frame.transform(&dpdu), self.frame.transform(&dpdv));
+            rec.set_face_normal(ray, &self.frame.transform(&normal));
+            rec.set_mat(self.mat.clone());
+            if material::passes_alpha_test(self.mat.as_ref(), rec) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_disc(self.center, self.frame.w(), self.major_radius).expand(self.minor_radius)
+    }
+}
@@ -0,0 +1,216 @@
+// src/engine/triangle.rs
+
+use crate::engine::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{self, Material},
+    ray::Ray,
+    utils::{self, INFINITY},
+    vec3::{self, Point3, Vec3},
+};
+
+use std::rc::Rc;
+
+/// a single triangle with optional per-vertex texture coordinates and shading normals
+#[derive(Clone)]
+pub struct Triangle {
+    vertices: [Point3; 3],
+    uvs: [(f64, f64); 3],              // texture coordinates at each vertex
+    vertex_normals: Option<[Vec3; 3]>, // shading normals at each vertex, for smooth shading
+    normal: Vec3,                      // unit normal, counter-clockwise winding faces front
+    area: f64,
+    mat: Rc<dyn Material>,
+}
+
+impl Triangle {
+    /// constructs new `Triangle` from given vertices and material, with texture coordinates
+    /// (0,0), (1,0) and (0,1) at the three vertices
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Rc<dyn Material>) -> Triangle {
+        let n = vec3::cross(&(b - a), &(c - a));
+
+        Triangle {
+            vertices: [a, b, c],
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            vertex_normals: None,
+            normal: vec3::unit_vector(n),
+            area: 0.5 * n.length(),
+            mat,
+        }
+    }
+
+    /// constructs new `Triangle` from given vertices, texture coordinates at each vertex and material
+    pub fn with_uvs(
+        vertices: [Point3; 3],
+        uvs: [(f64, f64); 3],
+        mat: Rc<dyn Material>,
+    ) -> Triangle {
+        let mut triangle = Triangle::new(vertices[0], vertices[1], vertices[2], mat);
+        triangle.uvs = uvs;
+        triangle
+    }
+
+    /// returns the triangle's vertices
+    pub fn vertices(&self) -> [Point3; 3] {
+        self.vertices
+    }
+
+    /// returns the triangle's texture coordinates at each vertex
+    pub fn uvs(&self) -> [(f64, f64); 3] {
+        self.uvs
+    }
+
+    /// returns the triangle's shading normals at each vertex, if it is smooth shaded
+    pub fn vertex_normals(&self) -> Option<[Vec3; 3]> {
+        self.vertex_normals
+    }
+
+    /// sets the shading normals at each vertex, interpolated across hits in place of the flat
+    /// normal
+    pub fn set_vertex_normals(&mut self, normals: [Vec3; 3]) {
+        self.vertex_normals = Some(normals);
+    }
+
+    /// returns the triangle's unit normal
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    /// returns the triangle's surface area
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// returns the partial derivatives of the surface point along the texture coordinates
+    /// NOTE: degenerate texture coordinates fall back to the triangle's edges
+    fn tangents(&self) -> (Vec3, Vec3) {
+        let [p0, p1, p2] = self.vertices;
+        let [uv0, uv1, uv2] = self.uvs;
+        let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+        let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let (dp02, dp12) = (p0 - p2, p1 - p2);
+
+        let determinant = du02 * dv12 - dv02 * du12;
+        if determinant.abs() < 1e-12 {
+            return (p1 - p0, p2 - p0);
+        }
+        let inv = 1.0 / determinant;
+        (
+            (dv12 * dp02 - dv02 * dp12) * inv,
+            (du02 * dp12 - du12 * dp02) * inv,
+        )
+    }
+
+    /// intersects the ray with the triangle, applying the material's alpha test if alpha_test
+    /// is set
+    fn intersect(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord, alpha_test: bool) -> bool {
+        let [p0, p1, p2] = self.vertices;
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+
+        // no hit if the ray is parallel to the triangle's plane
+        let pvec = vec3::cross(&ray.direction(), &edge2);
+        let determinant = vec3::dot(&edge1, &pvec);
+        if determinant.abs() < 1e-12 {
+            return false;
+        }
+        let inv_determinant = 1.0 / determinant;
+
+        // barycentric coordinates of the hit point with respect to p1 and p2
+        let tvec = ray.origin() - p0;
+        let b1 = vec3::dot(&tvec, &pvec) * inv_determinant;
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+        let qvec = vec3::cross(&tvec, &edge1);
+        let b2 = vec3::dot(&ray.direction(), &qvec) * inv_determinant;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+
+        let t = vec3::dot(&edge2, &qvec) * inv_determinant;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // interpolate the texture coordinates across the triangle
+        let b0 = 1.0 - b1 - b2;
+        let [uv0, uv1, uv2] = self.uvs;
+        let u = b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0;
+        let v = b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1;
+
+        rec.set_t(t);
+        rec.set_p(ray.at(t));
+        rec.set_uv(u, v);
+        let (dpdu, dpdv) = self.tangents();
+        rec.set_tangents(dpdu, dpdv);
+        rec.set_face_normal(ray, &self.normal);
+        if let Some([n0, n1, n2]) = self.vertex_normals {
+            // shade with the interpolated normal, kept on the side the ray came from
+            let shading = b0 * n0 + b1 * n1 + b2 * n2;
+            if !shading.near_zero() {
+                let n = vec3::unit_vector(shading);
+                let n = if vec3::dot(&n, &rec.normal()) < 0.0 {
+                    -n
+                } else {
+                    n
+                };
+                rec.set_normal(n);
+                rec.set_tangents(
+                    dpdu - vec3::dot(&dpdu, &n) * n,
+                    dpdv - vec3::dot(&dpdv, &n) * n,
+                );
+            }
+        }
+        rec.set_mat(self.mat.clone());
+
+        // cut out parts of the triangle let the ray pass through
+        !alpha_test || material::passes_alpha_test(self.mat.as_ref(), rec)
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, true)
+    }
+
+    fn hit_ignoring_alpha(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.intersect(ray, ray_t, rec, false)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [p0, p1, p2] = self.vertices;
+        Aabb::surrounding(&Aabb::from_points(p0, p1), &Aabb::from_points(p2, p2)).padded()
+    }
+
+    /// returns the pdf of sampling the direction by picking a uniform point on the triangle's area
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit_ignoring_alpha(
+            &Ray::new_with_time(*origin, *direction, time),
+            Interval::new(0.001, INFINITY),
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+
+        // convert the area measure to solid angle as seen from origin
+        let distance_squared = rec.t() * rec.t() * direction.length_squared();
+        let cosine = (vec3::dot(direction, &self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    /// returns the direction from origin to a uniformly sampled point on the triangle
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        // fold the unit square onto the triangle
+        let (mut b1, mut b2) = utils::random_2d();
+        if b1 + b2 > 1.0 {
+            b1 = 1.0 - b1;
+            b2 = 1.0 - b2;
+        }
+        let [p0, p1, p2] = self.vertices;
+        let p = p0 + b1 * (p1 - p0) + b2 * (p2 - p0);
+        p - *origin
+    }
+}
@@ -1,3 +1,5 @@
+use crate::engine::sampler;
+
 use rand::Rng;
 use std::cell::RefCell;
 use std::f64;
@@ -18,20 +20,65 @@ pub fn deg_to_rad(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
-/// returns a random real number in [0,1)
+/// returns a random real number in [0,1), the next dimension of the thread's sampler if one is
+/// installed
 #[inline(always)]
 pub fn random_0_to_1() -> f64 {
-    RNG.with(|rng| {
-        let mut rng = rng.borrow_mut();
-        rng.random_range(0.0..1.0)
-    })
+    sampler::next_1d().unwrap_or_else(random_independent)
 }
 
-/// returns a random real number in [0,1)
+/// returns a random point in [0,1)^2, the next two dimensions of the thread's sampler if one is
+/// installed
+/// NOTE: prefer this over two `random_0_to_1` calls for 2d samples, which samplers stratify jointly
+#[inline(always)]
+pub fn random_2d() -> (f64, f64) {
+    sampler::next_2d().unwrap_or_else(|| (random_independent(), random_independent()))
+}
+
+/// returns a random real number in [min,max)
 #[inline(always)]
 pub fn random_min_to_max(min: f64, max: f64) -> f64 {
+    min + (max - min) * random_0_to_1()
+}
+
+/// returns a random real number in [0,1) from the thread's random number generator, bypassing
+/// any sampler
+#[inline(always)]
+pub fn random_independent() -> f64 {
     RNG.with(|rng| {
         let mut rng = rng.borrow_mut();
-        rng.random_range(min..max)
+        rng.random_range(0.0..1.0)
     })
 }
+
+/// returns the balance heuristic weight of a sample drawn from the strategy with pdf_f,
+/// when the strategy with pdf_g could also have produced it
+#[inline(always)]
+pub fn balance_heuristic(pdf_f: f64, pdf_g: f64) -> f64 {
+    if pdf_f + pdf_g <= 0.0 {
+        return 0.0;
+    }
+    pdf_f / (pdf_f + pdf_g)
+}
+
+/// returns the power heuristic (exponent 2) weight of a sample drawn from the strategy with pdf_f,
+/// when the strategy with pdf_g could also have produced it
+#[inline(always)]
+pub fn power_heuristic(pdf_f: f64, pdf_g: f64) -> f64 {
+    let f = pdf_f * pdf_f;
+    let g = pdf_g * pdf_g;
+    if f + g <= 0.0 {
+        return 0.0;
+    }
+    f / (f + g)
+}
+
+/// returns 0 below edge0, 1 above edge1 and a smooth hermite ramp in between
+#[inline(always)]
+pub fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge0 == edge1 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
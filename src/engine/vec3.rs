@@ -5,7 +5,7 @@ use std::ops::{
     Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
 };
 
-use crate::engine::utils::{random_0_to_1, random_min_to_max};
+use crate::engine::utils::{PI, random_0_to_1, random_2d, random_min_to_max};
 
 /// a three-dimensional vector of f64
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -78,6 +78,12 @@ impl Vec3 {
     pub fn length(&self) -> f64 {
         self.length_squared().sqrt()
     }
+
+    /// returns true if the vector is close to zero in all dimensions
+    pub fn near_zero(&self) -> bool {
+        let s = 1e-8;
+        self.e[0].abs() < s && self.e[1].abs() < s && self.e[2].abs() < s
+    }
 }
 
 // `Point3` is just an alias for `Vec3`
@@ -252,11 +258,24 @@ pub fn unit_vector(v: Vec3) -> Vec3 {
 /// creates and returns a random unit vector
 #[inline(always)]
 pub fn random_unit_vector() -> Vec3 {
-    loop {
-        let p = Vec3::random_in_range(-1.0, 1.0);
-        let lensq = p.length_squared();
-        if 1e-160 < lensq && lensq <= 1.0 {
-            return p / lensq.sqrt();
-        }
-    }
+    // map one 2d sample onto the sphere, rather than rejection sampling a variable count of them
+    let (r1, r2) = random_2d();
+    let z = 1.0 - 2.0 * r1;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * r2;
+
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// returns a random direction about +z with a cosine weighted distribution
+#[inline(always)]
+pub fn random_cosine_direction() -> Vec3 {
+    let (r1, r2) = random_2d();
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+    let z = (1.0 - r2).sqrt();
+
+    Vec3::new(x, y, z)
 }
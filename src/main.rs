@@ -3,15 +3,32 @@
 #![allow(dead_code)]
 
 use raytracing_rs::engine::{
-    camera::Camera, hittable_list::HittableList, sphere::Sphere, vec3::Point3,
+    camera::Camera, color::Color, hittable_list::HittableList, light::LightList,
+    material::Lambertian, sphere::Sphere, vec3::Point3,
 };
 use std::rc::Rc;
 
 fn main() {
+    // create materials
+    let material_ground = Rc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_center = Rc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+
     // create world
     let mut world = HittableList::new();
-    world.add(Rc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5))); // gradient "sky"
-    world.add(Rc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0))); // green grass
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 0.0, -1.0),
+        0.5,
+        material_center,
+    )));
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    ))); // green grass
+
+    // objects sampled directly as light sources
+    let lights = HittableList::new();
+    let punctual_lights = LightList::new();
 
     // create camera
     let mut cam = Camera::default();
@@ -19,6 +36,7 @@ fn main() {
     cam.aspect_ratio = 16.0 / 9.0;
     cam.image_width = 2560;
     cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
 
-    cam.render(&world);
+    cam.render(&world, &lights, &punctual_lights);
 }